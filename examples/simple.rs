@@ -28,9 +28,8 @@ lazy_static! {
 }
 
 fn digit_handler(_: Request<Body>, c: Captures) -> Response<Body> {
-    // We know there are captures because that is the only way this function is triggered.
-    let caps = c.unwrap();
-    let digits = &caps[1];
+    // The first group is the whole path, so the digits are the second.
+    let digits = &c[1];
     if digits.len() > 5 {
         Response::new(Body::from("that's a big number!"))
     } else {
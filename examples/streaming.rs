@@ -0,0 +1,47 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use lazy_static::lazy_static;
+use reroute::stream::{self, Event};
+use reroute::RouterBuilder;
+
+lazy_static! {
+    static ref ROUTER: reroute::Router = {
+        let mut builder = RouterBuilder::new();
+
+        // Send an event every second for as long as the client listens.
+        builder.get(r"/ticks", |_, _| {
+            let ticks = tokio::time::interval(Duration::from_secs(1))
+                .enumerate()
+                .map(|(i, _)| Event::new("tick").id(&i.to_string()));
+            stream::events(ticks)
+        });
+
+        // Send a large download a chunk at a time instead of all at once.
+        builder.get(r"/download", |_, _| {
+            let chunks = futures_util::stream::iter(0..1000)
+                .map(|i| Ok::<_, Infallible>(format!("line {}\n", i)));
+            stream::chunked(chunks)
+        });
+
+        builder.finalize().unwrap()
+    };
+}
+
+async fn handler(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    Ok(ROUTER.handle(req))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr = ([127, 0, 0, 1], 3000).into();
+    let svc = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handler)) });
+    let server = Server::bind(&addr).serve(svc);
+
+    server.await?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Attribute macros for registering reroute handlers next to the functions
+//! that serve them. These are re-exported by reroute when its `macros`
+//! feature is enabled, so depend on that rather than on this crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Ident, ItemFn, LitStr, Path, Token};
+
+macro_rules! method_attribute {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[proc_macro_attribute]
+        pub fn $name(args: TokenStream, item: TokenStream) -> TokenStream {
+            let pattern = parse_macro_input!(args as LitStr);
+            let handler = parse_macro_input!(item as ItemFn);
+            route(stringify!($name), &pattern, &handler).into()
+        }
+    };
+}
+
+method_attribute!(
+    /// Register a function as the handler for GET requests to a route:
+    ///
+    /// ```ignore
+    /// #[get("/users/{id}")]
+    /// fn show_user(req: Request<Body>, c: Captures) -> Response<Body> {
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// The route is a pattern like any other, except that `{name}` stands for
+    /// a path segment and is captured in a group of that name, which the
+    /// handler can look up with `Captures::name`. The handler is added to a
+    /// builder by listing it in `collect_routes!`.
+    get
+);
+method_attribute!(
+    /// Register a function as the handler for POST requests to a route. See
+    /// `get`.
+    post
+);
+method_attribute!(
+    /// Register a function as the handler for PUT requests to a route. See
+    /// `get`.
+    put
+);
+method_attribute!(
+    /// Register a function as the handler for PATCH requests to a route. See
+    /// `get`.
+    patch
+);
+method_attribute!(
+    /// Register a function as the handler for DELETE requests to a route. See
+    /// `get`.
+    delete
+);
+method_attribute!(
+    /// Register a function as the handler for OPTIONS requests to a route.
+    /// See `get`.
+    options
+);
+
+/// Build a `RouterBuilder` with the routes of the listed handlers, which
+/// must have been registered with one of the method attributes:
+///
+/// ```ignore
+/// let mut builder = collect_routes![show_user, users::create_user];
+/// builder.not_found(not_found);
+/// let router = builder.finalize()?;
+/// ```
+#[proc_macro]
+pub fn collect_routes(input: TokenStream) -> TokenStream {
+    let handlers =
+        parse_macro_input!(input with Punctuated::<Path, Token![,]>::parse_terminated).into_iter();
+    quote!({
+        let mut builder = ::reroute::RouterBuilder::new();
+        #(#handlers::register(&mut builder);)*
+        builder
+    })
+    .into()
+}
+
+// Keep the handler as it is and add a module of the same name next to it
+// with a function that registers it. Functions and modules live in different
+// namespaces so both can be reached through the handler's path.
+fn route(method: &str, pattern: &LitStr, handler: &ItemFn) -> TokenStream2 {
+    let method = Ident::new(method, pattern.span());
+    let pattern = LitStr::new(&expand(&pattern.value()), pattern.span());
+    let name = &handler.sig.ident;
+    let vis = &handler.vis;
+    quote! {
+        #handler
+
+        #[doc(hidden)]
+        #vis mod #name {
+            pub fn register(builder: &mut ::reroute::RouterBuilder) {
+                builder.#method(#pattern, super::#name);
+            }
+        }
+    }
+}
+
+// Replace each `{name}` placeholder with a named group matching one path
+// segment. Braces around anything other than an identifier are left alone
+// so repetitions like `\d{4}` still work.
+fn expand(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let name = &rest[1..end];
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_identifier {
+            out.push_str(&format!("(?P<{}>[^/]+)", name));
+        } else {
+            out.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
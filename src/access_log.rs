@@ -0,0 +1,260 @@
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyper::body::HttpBody;
+use hyper::header::CONTENT_LENGTH;
+use hyper::{Method, Request, Response, StatusCode};
+
+use crate::json;
+use crate::TraceContext;
+
+type Redactor = Box<dyn Fn(&mut AccessLogEntry) + Send + Sync>;
+type Sink = Box<dyn Fn(&str) + Send + Sync>;
+
+/// A record of one request, passed to the hook installed with
+/// `RouterBuilder::on_access` once the response has been produced.
+#[derive(Clone, Debug)]
+pub struct AccessLogEntry {
+    /// When the request was received.
+    pub timestamp: SystemTime,
+    /// The trace ID from the request's `traceparent` header.
+    pub trace_id: Option<String>,
+    pub method: Method,
+    pub path: String,
+    /// The pattern of the route that handled the request, if any did.
+    pub route: Option<String>,
+    pub status: StatusCode,
+    pub duration: Duration,
+    /// The request body size, when given by its `Content-Length` header.
+    pub bytes_in: Option<u64>,
+    /// The response body size, when it is known up front.
+    pub bytes_out: Option<u64>,
+    /// The client address from a `SocketAddr` request extension or else the
+    /// `X-Forwarded-For` or `X-Real-IP` headers. The headers are set by the
+    /// client unless a trusted proxy overwrites them.
+    pub client_ip: Option<IpAddr>,
+}
+
+/// An access log that writes each entry as a single line JSON document with
+/// a stable schema, for ingestion by log pipelines.
+///
+/// ```text
+/// {"timestamp":"2020-02-09T17:04:05.123Z","trace_id":null,"method":"GET","path":"/users/1",
+///  "route":"/users/(\\d+)","status":200,"duration_ms":0.42,"bytes_in":null,"bytes_out":17,
+///  "client_ip":"127.0.0.1"}
+/// ```
+pub struct JsonAccessLog {
+    sink: Sink,
+    redactors: Vec<Redactor>,
+}
+
+// What needs to be kept from a request before it is handed to its handler.
+pub(crate) struct Pending {
+    timestamp: SystemTime,
+    start: Instant,
+    trace_id: Option<String>,
+    method: Method,
+    path: String,
+    bytes_in: Option<u64>,
+    client_ip: Option<IpAddr>,
+}
+
+impl Pending {
+    pub(crate) fn new<B>(req: &Request<B>) -> Pending {
+        Pending {
+            timestamp: SystemTime::now(),
+            start: Instant::now(),
+            trace_id: TraceContext::from_headers(req.headers()).map(|context| context.trace_id),
+            method: req.method().clone(),
+            path: req.uri().path().to_owned(),
+            bytes_in: req
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok()),
+            client_ip: client_ip(req),
+        }
+    }
+
+    pub(crate) fn finish<B: HttpBody>(
+        self,
+        route: Option<&str>,
+        response: &Response<B>,
+    ) -> AccessLogEntry {
+        AccessLogEntry {
+            timestamp: self.timestamp,
+            trace_id: self.trace_id,
+            method: self.method,
+            path: self.path,
+            route: route.map(str::to_owned),
+            status: response.status(),
+            duration: self.start.elapsed(),
+            bytes_in: self.bytes_in,
+            bytes_out: response.body().size_hint().exact(),
+            client_ip: self.client_ip,
+        }
+    }
+}
+
+pub(crate) fn client_ip<B>(req: &Request<B>) -> Option<IpAddr> {
+    if let Some(addr) = req.extensions().get::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    header("x-forwarded-for")
+        .and_then(|value| value.split(',').next())
+        .or_else(|| header("x-real-ip"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+impl AccessLogEntry {
+    /// Encode the entry as a JSON object. The field names and their order
+    /// are stable.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"timestamp\":");
+        json::string(&mut out, &rfc3339(self.timestamp));
+        out.push_str(",\"trace_id\":");
+        optional_string(&mut out, self.trace_id.as_deref());
+        out.push_str(",\"method\":");
+        json::string(&mut out, self.method.as_str());
+        out.push_str(",\"path\":");
+        json::string(&mut out, &self.path);
+        out.push_str(",\"route\":");
+        optional_string(&mut out, self.route.as_deref());
+        let _ = write!(
+            out,
+            ",\"status\":{},\"duration_ms\":{}",
+            self.status.as_u16(),
+            self.duration.as_secs_f64() * 1000.0
+        );
+        out.push_str(",\"bytes_in\":");
+        optional_number(&mut out, self.bytes_in);
+        out.push_str(",\"bytes_out\":");
+        optional_number(&mut out, self.bytes_out);
+        out.push_str(",\"client_ip\":");
+        optional_string(&mut out, self.client_ip.map(|ip| ip.to_string()).as_deref());
+        out.push('}');
+        out
+    }
+}
+
+impl JsonAccessLog {
+    /// Create an access log that passes each encoded line to `sink`, which
+    /// could write it to standard output or a file.
+    pub fn new<F>(sink: F) -> JsonAccessLog
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        JsonAccessLog {
+            sink: Box::new(sink),
+            redactors: Vec::new(),
+        }
+    }
+
+    /// Add a hook that can scrub an entry before it is written, for example
+    /// to drop the client address or mask IDs in the path. Hooks run in the
+    /// order they were added.
+    pub fn redact<F>(mut self, redactor: F) -> JsonAccessLog
+    where
+        F: Fn(&mut AccessLogEntry) + Send + Sync + 'static,
+    {
+        self.redactors.push(Box::new(redactor));
+        self
+    }
+
+    pub(crate) fn write(&self, entry: &AccessLogEntry) {
+        if self.redactors.is_empty() {
+            return (self.sink)(&entry.to_json());
+        }
+
+        let mut entry = entry.clone();
+        for redactor in &self.redactors {
+            redactor(&mut entry);
+        }
+        (self.sink)(&entry.to_json())
+    }
+}
+
+fn optional_string(out: &mut String, value: Option<&str>) {
+    match value {
+        Some(value) => json::string(out, value),
+        None => out.push_str("null"),
+    }
+}
+
+fn optional_number(out: &mut String, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            let _ = write!(out, "{}", value);
+        }
+        None => out.push_str("null"),
+    }
+}
+
+// Format a time as an RFC 3339 UTC timestamp with millisecond precision.
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Convert days since the Unix epoch to a proleptic Gregorian date, following
+// Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[test]
+fn encodes_redacted_entries() {
+    use std::sync::{Arc, Mutex};
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let log = {
+        let lines = Arc::clone(&lines);
+        JsonAccessLog::new(move |line| lines.lock().unwrap().push(line.to_owned()))
+            .redact(|entry| entry.client_ip = None)
+    };
+    log.write(&AccessLogEntry {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_581_267_845_123),
+        trace_id: None,
+        method: Method::GET,
+        path: "/users/1".to_owned(),
+        route: Some(r"/users/(\d+)".to_owned()),
+        status: StatusCode::OK,
+        duration: Duration::from_millis(2),
+        bytes_in: None,
+        bytes_out: Some(17),
+        client_ip: Some([127, 0, 0, 1].into()),
+    });
+
+    assert_eq!(
+        lines.lock().unwrap()[0],
+        r#"{"timestamp":"2020-02-09T17:04:05.123Z","trace_id":null,"method":"GET","path":"/users/1","route":"/users/(\\d+)","status":200,"duration_ms":2,"bytes_in":null,"bytes_out":17,"client_ip":null}"#
+    );
+}
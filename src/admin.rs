@@ -0,0 +1,355 @@
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::access_log::rfc3339;
+use crate::json::{array, object, optional, quoted};
+use crate::metrics::{Metrics, RouterMetrics};
+use crate::Quota;
+
+type Authorize<B> = Box<dyn Fn(&Request<B>) -> bool + Send + Sync>;
+
+/// Configuration for the admin endpoints mounted by `RouterBuilder::admin`.
+pub struct Admin<B = Body> {
+    authorize: Option<Authorize<B>>,
+    build_info: Vec<(String, String)>,
+    quotas: Vec<(String, Quota<B>)>,
+}
+
+impl<B> Default for Admin<B> {
+    fn default() -> Admin<B> {
+        Admin {
+            authorize: None,
+            build_info: Vec::new(),
+            quotas: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum AdminEndpoint {
+    Routes,
+    Metrics,
+    Usage,
+    Features,
+    Build,
+    Quotas,
+}
+
+impl AdminEndpoint {
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            AdminEndpoint::Routes => "routes",
+            AdminEndpoint::Metrics => "metrics",
+            AdminEndpoint::Usage => "usage",
+            AdminEndpoint::Features => "features",
+            AdminEndpoint::Build => "build",
+            AdminEndpoint::Quotas => "quotas",
+        }
+    }
+}
+
+// The optional crate features, reported by the features endpoint.
+const FEATURES: &[(&str, bool)] = &[
+    ("config", cfg!(feature = "config")),
+    ("extract", cfg!(feature = "extract")),
+    ("macros", cfg!(feature = "macros")),
+    ("opentelemetry", cfg!(feature = "opentelemetry")),
+    ("prometheus", cfg!(feature = "prometheus")),
+    ("reports", cfg!(feature = "reports")),
+    ("tracing", cfg!(feature = "tracing")),
+];
+
+impl<B> Admin<B> {
+    /// Create an admin configuration that allows every request.
+    pub fn new() -> Admin<B> {
+        Admin::default()
+    }
+
+    /// Only serve requests for which `authorize` returns true. Others get a
+    /// 403 response.
+    pub fn authorize<F>(&mut self, authorize: F) -> &mut Admin<B>
+    where
+        F: Fn(&Request<B>) -> bool + Send + Sync + 'static,
+    {
+        self.authorize = Some(Box::new(authorize));
+        self
+    }
+
+    /// Add a key and value to the build endpoint, such as a git revision.
+    /// The crate version is always included.
+    pub fn build_info(&mut self, key: &str, value: &str) -> &mut Admin<B> {
+        self.build_info.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Serve the usage of each key of `quota` under `name` from the quotas
+    /// endpoint.
+    pub fn quota(&mut self, name: &str, quota: Quota<B>) -> &mut Admin<B> {
+        self.quotas.push((name.to_owned(), quota));
+        self
+    }
+
+    pub(crate) fn respond(
+        &self,
+        endpoint: AdminEndpoint,
+        req: &Request<B>,
+        metrics: &Metrics,
+    ) -> Response<Body> {
+        if let Some(authorize) = &self.authorize {
+            if !authorize(req) {
+                return Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body("Forbidden".into())
+                    .unwrap();
+            }
+        }
+
+        let body =
+            match endpoint {
+                AdminEndpoint::Routes => routes(metrics),
+                AdminEndpoint::Metrics => metrics_json(&metrics.snapshot()),
+                AdminEndpoint::Usage => usage(&metrics.snapshot()),
+                AdminEndpoint::Features => object(FEATURES.iter().map(|&(name, enabled)| {
+                    (name, if enabled { "true" } else { "false" }.to_owned())
+                })),
+                AdminEndpoint::Build => self.build(),
+                AdminEndpoint::Quotas => self.quotas(),
+            };
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap()
+    }
+
+    fn build(&self) -> String {
+        let mut pairs = vec![("reroute_version", quoted(env!("CARGO_PKG_VERSION")))];
+        pairs.extend(
+            self.build_info
+                .iter()
+                .map(|(key, value)| (key.as_str(), quoted(value))),
+        );
+        object(pairs.into_iter())
+    }
+
+    // The usage of every key of each quota in its current window.
+    fn quotas(&self) -> String {
+        object(self.quotas.iter().map(|(name, quota)| {
+            let usages = quota.usages().into_iter().map(|usage| {
+                object(
+                    vec![
+                        ("key", quoted(&usage.key)),
+                        ("used", usage.used.to_string()),
+                        ("limit", usage.limit.to_string()),
+                        ("remaining", usage.remaining().to_string()),
+                        ("resets", quoted(&rfc3339(usage.resets))),
+                    ]
+                    .into_iter(),
+                )
+            });
+            (name.as_str(), array(usages))
+        }))
+    }
+}
+
+// The route table, also returned by `Router::to_json`.
+pub(crate) fn routes(metrics: &Metrics) -> String {
+    let routes: Vec<String> = metrics
+        .routes()
+        .map(|(method, pattern, metadata)| {
+            object(
+                vec![
+                    ("method", quoted(method.as_str())),
+                    ("pattern", quoted(pattern)),
+                    ("name", optional(metadata.name.as_deref())),
+                    ("tags", array(metadata.tags.iter().map(|tag| quoted(tag)))),
+                    ("description", optional(metadata.description.as_deref())),
+                ]
+                .into_iter(),
+            )
+        })
+        .collect();
+    format!("[{}]", routes.join(","))
+}
+
+// How often and how recently each route was used, for finding dead endpoints.
+fn usage(metrics: &RouterMetrics) -> String {
+    let routes: Vec<String> = metrics
+        .routes
+        .iter()
+        .map(|route| {
+            object(
+                vec![
+                    ("method", quoted(route.method.as_str())),
+                    ("pattern", quoted(&route.pattern)),
+                    ("requests", route.requests.to_string()),
+                    (
+                        "last_used",
+                        route
+                            .last_used
+                            .map_or("null".to_owned(), |time| quoted(&rfc3339(time))),
+                    ),
+                ]
+                .into_iter(),
+            )
+        })
+        .collect();
+    format!("[{}]", routes.join(","))
+}
+
+fn metrics_json(metrics: &RouterMetrics) -> String {
+    let routes: Vec<String> = metrics
+        .routes
+        .iter()
+        .map(|route| {
+            let buckets: Vec<String> = route
+                .latency
+                .buckets
+                .iter()
+                .map(|(bound, count)| format!("[{},{}]", bound.as_secs_f64(), count))
+                .collect();
+            object(
+                vec![
+                    ("method", quoted(route.method.as_str())),
+                    ("pattern", quoted(&route.pattern)),
+                    ("name", optional(route.name.as_deref())),
+                    ("requests", route.requests.to_string()),
+                    ("in_flight", route.in_flight.to_string()),
+                    ("bytes_out", route.bytes_out.to_string()),
+                    ("status_classes", array(route.status_classes.iter())),
+                    ("latency_count", route.latency.count.to_string()),
+                    (
+                        "latency_sum_seconds",
+                        route.latency.sum.as_secs_f64().to_string(),
+                    ),
+                    ("latency_buckets", format!("[{}]", buckets.join(","))),
+                    (
+                        "slo",
+                        route.slo.as_ref().map_or("null".to_owned(), |slo| {
+                            object(
+                                vec![
+                                    ("target", slo.target.to_string()),
+                                    ("total", slo.total.to_string()),
+                                    ("good", slo.good.to_string()),
+                                    ("compliance", slo.compliance.to_string()),
+                                    ("burn_rate", number(slo.burn_rate)),
+                                ]
+                                .into_iter(),
+                            )
+                        }),
+                    ),
+                ]
+                .into_iter(),
+            )
+        })
+        .collect();
+
+    object(
+        vec![
+            ("routes", format!("[{}]", routes.join(","))),
+            ("not_found", metrics.not_found.to_string()),
+            ("method_not_allowed", metrics.method_not_allowed.to_string()),
+            ("in_flight", metrics.in_flight.to_string()),
+            ("uptime_seconds", metrics.uptime.as_secs_f64().to_string()),
+        ]
+        .into_iter(),
+    )
+}
+
+// JSON has no infinity so report it as null.
+fn number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_owned()
+    }
+}
+
+#[test]
+fn unauthorized_requests_are_forbidden() {
+    use crate::{testing, RouterBuilder};
+
+    let mut admin = Admin::new();
+    admin.authorize(|req| req.headers().contains_key("x-admin-token"));
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.admin("/_admin", admin);
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/_admin/routes"));
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let mut req = testing::get("/_admin/routes");
+    req.headers_mut()
+        .insert("x-admin-token", "secret".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+}
+
+#[test]
+fn reports_route_usage() {
+    use std::time::SystemTime;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users", |_, _| Response::new(Body::empty()));
+    builder.get(r"/posts", |_, _| Response::new(Body::empty()));
+    builder.admin("/_admin", Admin::new());
+    let router = builder.finalize().unwrap();
+
+    let before = SystemTime::now();
+    router.handle(testing::get("/users"));
+    let after = SystemTime::now();
+    let last_used = router.metrics().routes[0].last_used.unwrap();
+    // Times are kept to the millisecond.
+    assert!(last_used <= after);
+    assert!(
+        before
+            .duration_since(last_used)
+            .unwrap_or_default()
+            .as_millis()
+            < 1
+    );
+    assert_eq!(router.metrics().routes[1].last_used, None);
+
+    let response = router.handle(testing::get("/_admin/usage"));
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    let body = testing::body(response);
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.starts_with(r#"[{"method":"GET","pattern":"/users","requests":1,"last_used":""#));
+    assert!(body.contains(r#"{"method":"GET","pattern":"/posts","requests":0,"last_used":null}"#));
+}
+
+#[test]
+fn lists_every_feature() {
+    // The `[features]` table and the optional dependencies that aren't
+    // just there to back one of its features.
+    let manifest = include_str!("../Cargo.toml");
+    let section = |name: &str| -> Vec<&str> {
+        manifest
+            .lines()
+            .skip_while(|line| line.trim() != name)
+            .skip(1)
+            .take_while(|line| !line.starts_with('['))
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    };
+    let features = section("[features]");
+    let mut expected: Vec<&str> = features
+        .iter()
+        .map(|line| line.split('=').next().unwrap().trim())
+        .collect();
+    for line in section("[dependencies]") {
+        let name = line.split('=').next().unwrap().trim();
+        let used = features
+            .iter()
+            .any(|feature| feature.contains(&format!("\"{}\"", name)));
+        if line.contains("optional = true") && !used {
+            expected.push(name);
+        }
+    }
+    expected.sort_unstable();
+
+    let listed: Vec<&str> = FEATURES.iter().map(|&(name, _)| name).collect();
+    assert_eq!(listed, expected);
+}
@@ -0,0 +1,52 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyper::body::{Bytes, HttpBody};
+use hyper::{Body, Response};
+
+use crate::body::RouteBody;
+use crate::metrics::Metrics;
+
+// Count the bytes of a response body against the route at `index`. A body
+// whose size is known up front is counted straight away so hyper can still
+// send a `Content-Length` for it. Streaming bodies are wrapped so that each
+// chunk is counted as it is written.
+pub(crate) fn count<B: RouteBody>(
+    response: Response<B>,
+    metrics: &Arc<Metrics>,
+    index: usize,
+) -> Response<B> {
+    if let Some(size) = HttpBody::size_hint(response.body()).exact() {
+        metrics.record_bytes_out(index, size);
+        return response;
+    }
+
+    response.map(|body| {
+        B::from(Body::wrap_stream(Counted {
+            body: body.into_hyper(),
+            metrics: Arc::clone(metrics),
+            index,
+        }))
+    })
+}
+
+struct Counted {
+    body: Body,
+    metrics: Arc<Metrics>,
+    index: usize,
+}
+
+impl Stream for Counted {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.body).poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            self.metrics
+                .record_bytes_out(self.index, chunk.len() as u64);
+        }
+        poll
+    }
+}
@@ -0,0 +1,278 @@
+//! Helpers for reading a whole request body.
+//!
+//! Each helper takes the largest body it will read, so a client can't make a
+//! handler hold an unbounded amount of memory. Requests whose
+//! `Content-Length` is over the limit are refused before any of the body is
+//! read, and bodies without a length are cut off once they pass it.
+//!
+//! ```ignore
+//! let text = reroute::body::to_string(req, 64 * 1024).await?;
+//! ```
+//!
+//! Routers work with `hyper::Body` unless they are built for another body
+//! type, such as one from body middleware or a test, which has to be a
+//! `RouteBody`:
+//!
+//! ```ignore
+//! let mut builder: RouterBuilder<MyBody> = RouterBuilder::default();
+//! builder.post(r"/echo", |req, _| Response::new(req.into_body()));
+//! ```
+
+use std::any::Any;
+use std::error::Error as StdError;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyper::body::{Buf, Bytes, HttpBody};
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::limits::{self, BodyTimeout, BodyTooLarge};
+
+/// A body type a `Router` can be built for in place of `hyper::Body`. Any
+/// body that can be made from a `hyper::Body` is one.
+///
+/// Bodies reach handlers as they were sent and responses leave the router
+/// as handlers made them. Responses the router makes itself, such as its
+/// default not found response, are made as a `hyper::Body` and converted,
+/// while route settings that wrap bodies, such as
+/// `RouteOptions::max_body_size` or recording, stream them through a
+/// `hyper::Body` first. Only the data is carried over then, so any trailers
+/// are dropped.
+pub trait RouteBody: HttpBody + From<Body> + Send + Sized + 'static {
+    /// Stream the body through a `hyper::Body`. A `hyper::Body` is returned
+    /// as it is.
+    fn into_hyper(self) -> Body;
+}
+
+impl<B> RouteBody for B
+where
+    B: HttpBody + From<Body> + Send + 'static,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    fn into_hyper(self) -> Body {
+        let mut body = Some(self);
+        if let Some(body) = (&mut body as &mut dyn Any).downcast_mut::<Option<Body>>() {
+            return body.take().expect("the body is only taken once");
+        }
+        let body = body.expect("the body is only taken once");
+        Body::wrap_stream(Chunks(Box::pin(body)))
+    }
+}
+
+/// Read the whole body of `req`, as long as it is no more than `max` bytes.
+pub async fn to_bytes<B: RouteBody>(req: Request<B>, max: u64) -> Result<Bytes, BodyError> {
+    if limits::too_large(req.headers(), max) {
+        return Err(BodyError::TooLarge);
+    }
+    let mut body = req.into_body().into_hyper();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(BodyError::Read)?;
+        if (bytes.len() + chunk.len()) as u64 > max {
+            return Err(BodyError::TooLarge);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes.into())
+}
+
+/// Read the whole body of `req` as text, like `to_bytes`.
+pub async fn to_string<B: RouteBody>(req: Request<B>, max: u64) -> Result<String, BodyError> {
+    let bytes = to_bytes(req, max).await?;
+    String::from_utf8(bytes.to_vec()).map_err(|error| BodyError::Invalid(error.to_string()))
+}
+
+/// Read the whole body of `req` and parse it as JSON, like `to_bytes`.
+#[cfg(feature = "extract")]
+pub async fn to_json<T, B>(req: Request<B>, max: u64) -> Result<T, BodyError>
+where
+    T: serde::de::DeserializeOwned,
+    B: RouteBody,
+{
+    let bytes = to_bytes(req, max).await?;
+    serde_json::from_slice(&bytes).map_err(|error| BodyError::Invalid(error.to_string()))
+}
+
+/// Why a request body couldn't be read by one of the helpers in this
+/// module. Handlers can answer with `response`.
+#[derive(Debug)]
+pub enum BodyError {
+    /// The body couldn't be read, such as when the client went away or took
+    /// longer than the route's `RouteOptions::body_timeout`.
+    Read(hyper::Error),
+    /// The body was longer than the limit.
+    TooLarge,
+    /// The body wasn't valid text or JSON.
+    Invalid(String),
+}
+
+impl BodyError {
+    /// The status to respond with: `413 Payload Too Large` for bodies over
+    /// any limit, `408 Request Timeout` for those that took too long and
+    /// `400 Bad Request` otherwise.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            BodyError::Read(error) if BodyTooLarge::is_cause_of(error) => BodyTooLarge::status(),
+            BodyError::Read(error) if BodyTimeout::is_cause_of(error) => BodyTimeout::status(),
+            BodyError::Read(_) | BodyError::Invalid(_) => StatusCode::BAD_REQUEST,
+            BodyError::TooLarge => BodyTooLarge::status(),
+        }
+    }
+
+    /// A response with `status` describing the error.
+    pub fn response(&self) -> Response<Body> {
+        Response::builder()
+            .status(self.status())
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyError::Read(error) => write!(f, "error reading the request body: {}", error),
+            BodyError::TooLarge => write!(f, "request body too large"),
+            BodyError::Invalid(reason) => write!(f, "invalid request body: {}", reason),
+        }
+    }
+}
+
+impl StdError for BodyError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            BodyError::Read(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl<B: RouteBody> From<BodyError> for Response<B> {
+    fn from(error: BodyError) -> Response<B> {
+        error.response().map(B::from)
+    }
+}
+
+// The data of any body as a stream of chunks.
+struct Chunks<B>(Pin<Box<B>>);
+
+impl<B: HttpBody> Stream for Chunks<B> {
+    type Item = Result<Bytes, B::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.0
+            .as_mut()
+            .poll_data(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(|mut data| data.to_bytes())))
+    }
+}
+
+#[test]
+fn reads_bodies() {
+    use futures_util::stream;
+
+    use crate::testing;
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    assert_eq!(
+        runtime
+            .block_on(to_string(testing::post("/", "hello"), 5))
+            .unwrap(),
+        "hello"
+    );
+    let mut req = testing::post("/", Body::empty());
+    req.headers_mut()
+        .insert(hyper::header::CONTENT_LENGTH, "1000".parse().unwrap());
+    let error = runtime.block_on(to_bytes(req, 4)).unwrap_err();
+    assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Without a length the body is only refused once too much has arrived.
+    let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("hel"), Ok("lo")];
+    let streamed = testing::post("/", Body::wrap_stream(stream::iter(chunks)));
+    assert!(matches!(
+        runtime.block_on(to_bytes(streamed, 4)),
+        Err(BodyError::TooLarge)
+    ));
+
+    let error = runtime
+        .block_on(to_string(testing::post("/", vec![0xff, 0xfe]), 16))
+        .unwrap_err();
+    assert_eq!(error.response().status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn routes_other_bodies() {
+    use hyper::{HeaderMap, Method};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    // A body that is either text given by a test or one made by the router.
+    enum Test {
+        Text(Option<Bytes>),
+        Hyper(Body),
+    }
+
+    impl From<Body> for Test {
+        fn from(body: Body) -> Test {
+            Test::Hyper(body)
+        }
+    }
+
+    impl HttpBody for Test {
+        type Data = Bytes;
+        type Error = hyper::Error;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+        ) -> Poll<Option<Result<Bytes, hyper::Error>>> {
+            match &mut *self {
+                Test::Text(text) => Poll::Ready(text.take().map(Ok)),
+                Test::Hyper(body) => Pin::new(body).poll_data(cx),
+            }
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context,
+        ) -> Poll<Result<Option<HeaderMap>, hyper::Error>> {
+            Poll::Ready(Ok(None))
+        }
+    }
+
+    let mut builder: RouterBuilder<Test> = RouterBuilder::default();
+    builder.post(r"/echo", |req: Request<Test>, _| {
+        let sent = matches!(req.body(), Test::Text(_));
+        assert!(sent, "the handler gets the body as it was sent");
+        Response::new(req.into_body())
+    });
+    // Limits stream the body through a `hyper::Body`.
+    builder.route_with(
+        Method::POST,
+        r"/small",
+        RouteOptions::new().max_body_size(4),
+        |req: Request<Test>, _| {
+            assert!(matches!(req.body(), Test::Hyper(_)));
+            Response::new(req.into_body())
+        },
+    );
+    let router = builder.finalize().unwrap();
+    let body = |response: Response<Test>| testing::body(response.map(RouteBody::into_hyper));
+    let post = |uri: &str, text: &'static str| {
+        Request::post(uri)
+            .body(Test::Text(Some(text.into())))
+            .unwrap()
+    };
+
+    assert_eq!(body(router.handle(post("/echo", "hello"))), "hello");
+    assert_eq!(body(router.handle(post("/small", "hi"))), "hi");
+    let mut req = post("/small", "hello");
+    req.headers_mut()
+        .insert(hyper::header::CONTENT_LENGTH, "5".parse().unwrap());
+    let response = router.handle(req);
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let response = router.handle(post("/missing", "hello"));
+    assert_eq!(body(response), "Not Found");
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use hyper::header::COOKIE;
+use hyper::{Body, Request};
+
+use crate::{Captures, IntoResponse, RouteHandler};
+
+/// An alternative handler for a route, such as a new implementation being
+/// tried out, given to `RouteOptions::canary`.
+///
+/// The canary handles requests that opt in with a header or cookie, so
+/// internal users can exercise it first, and optionally a share of all other
+/// requests that ramps up over time. The rest go to the route's handler.
+///
+/// A canary is set up before it is given to its route, which then shares
+/// it, so its settings can't change once requests are being routed.
+pub struct Canary<B = Body> {
+    handler: RouteHandler<B>,
+    header: Option<(String, String)>,
+    cookie: Option<(String, String)>,
+    ramp: Option<Ramp>,
+    seen: AtomicU64,
+}
+
+// A share of requests that grows linearly from `from` to `to` over `over`.
+struct Ramp {
+    from: f64,
+    to: f64,
+    over: Duration,
+    started: Instant,
+}
+
+impl<B> Canary<B> {
+    /// Use `handler` for the requests picked for the canary. Without a
+    /// header, cookie or ramp no requests are.
+    pub fn new<H, R>(handler: H) -> Canary<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        Canary {
+            handler: Box::new(move |req, captures| handler(req, captures).into_response()),
+            header: None,
+            cookie: None,
+            ramp: None,
+            seen: AtomicU64::default(),
+        }
+    }
+
+    /// Send requests whose `name` header is `value`, such as
+    /// `X-Canary: v2`, to the canary.
+    pub fn header(mut self, name: &str, value: &str) -> Canary<B> {
+        self.header = Some((name.to_ascii_lowercase(), value.to_owned()));
+        self
+    }
+
+    /// Send requests with a `name` cookie set to `value` to the canary.
+    pub fn cookie(mut self, name: &str, value: &str) -> Canary<B> {
+        self.cookie = Some((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Also send a percentage of other requests to the canary, starting at
+    /// `from` percent now and growing steadily to `to` percent over `over`.
+    /// Use the same value for both for a fixed split.
+    pub fn ramp(mut self, from: f64, to: f64, over: Duration) -> Canary<B> {
+        self.ramp = Some(Ramp {
+            from: from.clamp(0.0, 100.0),
+            to: to.clamp(0.0, 100.0),
+            over,
+            started: Instant::now(),
+        });
+        self
+    }
+
+    /// The percentage of requests without the header or cookie currently
+    /// sent to the canary.
+    pub fn percentage(&self) -> f64 {
+        self.ramp.as_ref().map_or(0.0, Ramp::percentage)
+    }
+
+    // The canary's handler if it should handle `req`.
+    pub(crate) fn select(&self, req: &Request<B>) -> Option<&RouteHandler<B>> {
+        let header = self.header.as_ref().is_some_and(|(name, value)| {
+            req.headers()
+                .get_all(name.as_str())
+                .iter()
+                .any(|sent| sent.to_str().is_ok_and(|sent| sent.trim() == value))
+        });
+        let cookie = self
+            .cookie
+            .as_ref()
+            .is_some_and(|(name, value)| cookie(req, name) == Some(value));
+        if header || cookie {
+            return Some(&self.handler);
+        }
+
+        let share = self.percentage() / 100.0;
+        if share <= 0.0 {
+            return None;
+        }
+        // Spread the picks evenly: take a request whenever the running total
+        // of the share passes a whole number.
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        if ((seen + 1.0) * share).floor() > (seen * share).floor() {
+            Some(&self.handler)
+        } else {
+            None
+        }
+    }
+}
+
+impl Ramp {
+    fn percentage(&self) -> f64 {
+        let progress = if self.over.is_zero() {
+            1.0
+        } else {
+            (self.started.elapsed().as_secs_f64() / self.over.as_secs_f64()).min(1.0)
+        };
+        self.from + (self.to - self.from) * progress
+    }
+}
+
+impl<B> fmt::Debug for Canary<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Canary")
+            .field("header", &self.header)
+            .field("cookie", &self.cookie)
+            .field("percentage", &self.percentage())
+            .finish()
+    }
+}
+
+// The value of the cookie called `name`, if the request has one.
+pub(crate) fn cookie<'a, B>(req: &'a Request<B>, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            if key.trim() == name {
+                Some(value.trim().trim_matches('"'))
+            } else {
+                None
+            }
+        })
+}
+
+#[test]
+fn routes_to_canaries() {
+    use hyper::Method;
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    fn handler(name: &'static str) -> impl Fn(Request<Body>, Captures) -> &'static str {
+        move |_, _| name
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/search",
+        RouteOptions::new().canary(
+            Canary::new(handler("v2"))
+                .header("X-Canary", "v2")
+                .cookie("canary", "v2"),
+        ),
+        handler("v1"),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/feed",
+        RouteOptions::new().canary(Canary::new(handler("v2")).ramp(25.0, 25.0, Duration::ZERO)),
+        handler("v1"),
+    );
+    let router = builder.finalize().unwrap();
+
+    let body = |req: Request<Body>| testing::body(router.handle(req));
+    assert_eq!(body(testing::get("/search")), "v1");
+    let mut req = testing::get("/search");
+    req.headers_mut().insert("x-canary", "v2".parse().unwrap());
+    assert_eq!(body(req), "v2");
+    let mut req = testing::get("/search");
+    req.headers_mut()
+        .insert(COOKIE, "theme=dark; canary=v2".parse().unwrap());
+    assert_eq!(body(req), "v2");
+
+    let canaries = (0..100)
+        .filter(|_| body(testing::get("/feed")) == "v2")
+        .count();
+    assert_eq!(canaries, 25);
+}
@@ -0,0 +1,288 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::iter::Copied;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+use std::str::FromStr;
+
+use hyper::{Body, Response, StatusCode};
+use regex::Regex;
+use smallvec::SmallVec;
+
+use crate::body::RouteBody;
+use crate::path;
+
+/// The parts of a request's path captured by the route that matched it.
+///
+/// Groups are numbered as in the pattern, starting with the whole path at
+/// zero, and can be indexed like a slice. Groups that didn't take part in the
+/// match, such as an optional group that is absent, keep their number but
+/// have no value, so every group is always at the same position. Indexing
+/// one of those panics, so optional groups should be read with `get`
+/// instead. Named groups such as `(?P<id>\d+)` can also be looked up by name
+/// with `name`.
+///
+/// Groups can be parsed into other types with `get_as` and `name_as`, whose
+/// errors turn into `400 Bad Request` responses.
+///
+/// Routes with a `RouteOptions::host_pattern` also capture the groups of
+/// that pattern, after those of the path.
+///
+/// Handlers called without a matching route, such as the not found handler,
+/// get empty captures.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Captures<'r> {
+    groups: SmallVec<[Option<&'r str>; 4]>,
+    names: SmallVec<[(&'r str, &'r str); 2]>,
+}
+
+impl<'r> Captures<'r> {
+    // The captures of `pattern` in `path`, which it is known to match.
+    pub(crate) fn new(pattern: &'r Regex, path: &'r str) -> Captures<'r> {
+        let mut captures = Captures::default();
+        captures.add(pattern, path, 0);
+        captures
+    }
+
+    // Add the groups of `pattern` in `host` after those of the path, leaving
+    // out the whole host.
+    pub(crate) fn extend(&mut self, pattern: &'r Regex, host: &'r str) {
+        self.add(pattern, host, 1);
+    }
+
+    fn add(&mut self, pattern: &'r Regex, text: &'r str, skip: usize) {
+        if let Some(caps) = pattern.captures(text) {
+            for (group, name) in caps.iter().zip(pattern.capture_names()).skip(skip) {
+                self.add_group(name, group.map(|group| group.as_str()));
+            }
+        }
+    }
+
+    // Add a group, named if `name` is given.
+    pub(crate) fn push(&mut self, name: Option<&'r str>, value: &'r str) {
+        self.add_group(name, Some(value));
+    }
+
+    // Add a group that may not have taken part in the match.
+    fn add_group(&mut self, name: Option<&'r str>, value: Option<&'r str>) {
+        self.groups.push(value);
+        if let (Some(name), Some(value)) = (name, value) {
+            self.names.push((name, value));
+        }
+    }
+
+    /// The group numbered `index`, if there is one and it took part in the
+    /// match.
+    pub fn get(&self, index: usize) -> Option<&'r str> {
+        self.groups.get(index).copied().flatten()
+    }
+
+    /// The group called `name`, if there is one and it took part in the
+    /// match.
+    pub fn name(&self, name: &str) -> Option<&'r str> {
+        self.names
+            .iter()
+            .find(|&&(group, _)| group == name)
+            .map(|&(_, value)| value)
+    }
+
+    /// The group numbered `index` parsed as a `T`, such as an id with
+    /// `captures.get_as::<u64>(1)`.
+    pub fn get_as<T: FromStr>(&self, index: usize) -> Result<T, CaptureError> {
+        parse(index.to_string(), self.get(index))
+    }
+
+    /// The group called `name` parsed as a `T`.
+    pub fn name_as<T: FromStr>(&self, name: &str) -> Result<T, CaptureError> {
+        parse(name.to_owned(), self.name(name))
+    }
+
+    /// The rest of the path after the prefix of a route added with
+    /// `RouterBuilder::route_prefix`, or any other group called `rest`.
+    pub fn rest(&self) -> Option<&'r str> {
+        self.name(path::REST)
+    }
+
+    /// The number of groups, including the whole path and any that didn't
+    /// take part in the match.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Whether there are no groups at all, which is only the case when no
+    /// route matched.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The groups in order, starting with the whole path, with `None` for
+    /// those that didn't take part in the match.
+    pub fn iter(&self) -> Copied<slice::Iter<'_, Option<&'r str>>> {
+        self.groups.iter().copied()
+    }
+
+    /// The named groups and their values, in the order they appear in the
+    /// pattern.
+    pub fn names(&self) -> impl Iterator<Item = (&'r str, &'r str)> + '_ {
+        self.names.iter().copied()
+    }
+
+    /// The groups as a vector, starting with the whole path. Groups that
+    /// didn't take part in the match are empty.
+    pub fn to_vec(&self) -> Vec<&'r str> {
+        self.iter().map(|group| group.unwrap_or("")).collect()
+    }
+}
+
+fn parse<T: FromStr>(group: String, value: Option<&str>) -> Result<T, CaptureError> {
+    match value {
+        Some(value) => value.parse().map_err(|_| CaptureError {
+            group,
+            value: Some(value.to_owned()),
+        }),
+        None => Err(CaptureError { group, value: None }),
+    }
+}
+
+/// A group that was missing or couldn't be parsed by `Captures::get_as` or
+/// `Captures::name_as`. Handlers can answer with `response`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureError {
+    group: String,
+    value: Option<String>,
+}
+
+impl CaptureError {
+    /// The number or name of the group.
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// The text that couldn't be parsed, or `None` if the group was missing.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// A `400 Bad Request` response describing the error.
+    pub fn response(&self) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(f, "invalid value {:?} for {}", value, self.group),
+            None => write!(f, "missing {}", self.group),
+        }
+    }
+}
+
+impl StdError for CaptureError {}
+
+impl<B: RouteBody> From<CaptureError> for Response<B> {
+    fn from(error: CaptureError) -> Response<B> {
+        error.response().map(B::from)
+    }
+}
+
+/// The group numbered `index`. This panics if there is no such group or it
+/// didn't take part in the match, like indexing a slice out of bounds. Use
+/// `get` for groups that may be absent.
+impl Index<usize> for Captures<'_> {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        match self.groups[index] {
+            Some(group) => group,
+            None => panic!("group {} didn't take part in the match", index),
+        }
+    }
+}
+
+/// Unnamed groups in order, starting with the whole path.
+impl<'r> FromIterator<&'r str> for Captures<'r> {
+    fn from_iter<I>(groups: I) -> Captures<'r>
+    where
+        I: IntoIterator<Item = &'r str>,
+    {
+        Captures {
+            groups: groups.into_iter().map(Some).collect(),
+            names: SmallVec::new(),
+        }
+    }
+}
+
+#[test]
+fn looks_up_named_groups() {
+    use hyper::{Body, Response};
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(?P<id>\d+)(/(?P<tab>\w+))?", |_, c| {
+        assert_eq!(c.name("id"), Some("42"));
+        assert_eq!(c.name("tab"), None);
+        assert_eq!(&c[1], "42");
+        assert_eq!(c.get(2), None);
+        assert_eq!(c.len(), 4);
+        Response::new(Body::empty())
+    });
+    builder.get(r"/posts(/(\d+))?/(\w+)", |_, c| {
+        assert_eq!(c.get(2), None);
+        assert_eq!(&c[3], "comments");
+        assert_eq!(
+            c.iter().collect::<Vec<_>>(),
+            [Some("/posts/comments"), None, None, Some("comments")]
+        );
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+    assert_eq!(
+        router.handle(testing::get("/users/42")).status(),
+        hyper::StatusCode::OK
+    );
+    assert_eq!(
+        router.handle(testing::get("/posts/comments")).status(),
+        hyper::StatusCode::OK
+    );
+}
+
+#[test]
+fn parses_groups() {
+    use crate::testing;
+
+    let captures = testing::named_captures("/users/42/x", &[(Some("id"), "42"), (None, "x")]);
+    assert_eq!(captures.get_as::<u64>(1), Ok(42));
+    assert_eq!(captures.name_as::<u8>("id"), Ok(42));
+    let error = captures.get_as::<u64>(2).unwrap_err();
+    assert_eq!(error.value(), Some("x"));
+    assert_eq!(error.response().status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        captures.name_as::<u64>("page").unwrap_err().to_string(),
+        "missing page"
+    );
+}
+
+#[test]
+fn indexes_optional_groups() {
+    let pattern = Regex::new(r"^/posts(/(\d+))?$").unwrap();
+    let captures = Captures::new(&pattern, "/posts/7");
+    assert_eq!(&captures[2], "7");
+    assert_eq!(captures.get(2), Some("7"));
+
+    let captures = Captures::new(&pattern, "/posts");
+    assert_eq!(captures.get(2), None);
+}
+
+#[test]
+#[should_panic(expected = "group 2 didn't take part in the match")]
+fn indexing_absent_groups_panics() {
+    let pattern = Regex::new(r"^/posts(/(\d+))?$").unwrap();
+    let captures = Captures::new(&pattern, "/posts");
+    let _ = &captures[2];
+}
@@ -0,0 +1,152 @@
+use hyper::header::{HeaderMap, HeaderValue, VARY};
+use hyper::Response;
+
+const ACCEPT_CH: &str = "accept-ch";
+
+/// The User-Agent Client Hints and device hints a request carries.
+///
+/// Browsers only send most hints after a response has asked for them with
+/// `Accept-CH`. Routes that want hints name them with
+/// `RouteOptions::client_hints`, which asks for them on every response,
+/// marks responses as varying on them, and adds the parsed hints to requests
+/// as an extension.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientHints {
+    /// The brands and versions from `Sec-CH-UA`, such as
+    /// `("Chromium", "120")`.
+    pub brands: Vec<(String, String)>,
+    /// Whether `Sec-CH-UA-Mobile` says the browser is on a mobile device.
+    pub mobile: Option<bool>,
+    /// The operating system from `Sec-CH-UA-Platform`.
+    pub platform: Option<String>,
+    /// The device pixel ratio from `Sec-CH-DPR` or the older `DPR`.
+    pub dpr: Option<f64>,
+    /// The width in pixels of the image being requested, from `Sec-CH-Width`
+    /// or the older `Width`.
+    pub width: Option<u32>,
+    /// The layout viewport width in CSS pixels, from `Sec-CH-Viewport-Width`
+    /// or the older `Viewport-Width`.
+    pub viewport_width: Option<u32>,
+}
+
+impl ClientHints {
+    /// Parse the hints from a set of headers. Hints that are missing or
+    /// malformed are left as `None`.
+    pub fn from_headers(headers: &HeaderMap) -> ClientHints {
+        let header = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|&name| headers.get(name)?.to_str().ok())
+                .map(str::trim)
+        };
+
+        ClientHints {
+            brands: header(&["sec-ch-ua"]).map(brands).unwrap_or_default(),
+            mobile: header(&["sec-ch-ua-mobile"]).and_then(|value| match value {
+                "?1" => Some(true),
+                "?0" => Some(false),
+                _ => None,
+            }),
+            platform: header(&["sec-ch-ua-platform"]).map(|value| unquote(value).to_owned()),
+            dpr: header(&["sec-ch-dpr", "dpr"]).and_then(|value| value.parse().ok()),
+            width: header(&["sec-ch-width", "width"]).and_then(|value| value.parse().ok()),
+            viewport_width: header(&["sec-ch-viewport-width", "viewport-width"])
+                .and_then(|value| value.parse().ok()),
+        }
+    }
+}
+
+// Ask for `hints` on a response and mark it as depending on them, keeping
+// any hints or `Vary` entries the handler set.
+pub(crate) fn apply<B>(hints: &[String], response: &mut Response<B>) {
+    let headers = response.headers_mut();
+    for &name in &[ACCEPT_CH, VARY.as_str()] {
+        extend_list(headers, name, hints);
+    }
+}
+
+// Add `additions` to the comma separated list in the `name` header, leaving
+// out any already there.
+pub(crate) fn extend_list(headers: &mut HeaderMap, name: &'static str, additions: &[String]) {
+    let mut values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+        .collect();
+    for addition in additions {
+        if !values
+            .iter()
+            .any(|value| value.eq_ignore_ascii_case(addition))
+        {
+            values.push(addition.clone());
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&values.join(", ")) {
+        headers.insert(name, value);
+    }
+}
+
+// Parse a `Sec-CH-UA` list like `"Chromium";v="120", "Not?A_Brand";v="8"`.
+fn brands(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|brand| {
+            let mut parts = brand.split(';');
+            let name = unquote(parts.next()?.trim());
+            let version = parts
+                .find_map(|part| part.trim().strip_prefix("v="))
+                .map_or("", unquote);
+            Some((name.to_owned(), version.to_owned()))
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+#[test]
+fn parses_and_requests_hints() {
+    use hyper::{Body, Method};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/image",
+        RouteOptions::new().client_hints(&["Sec-CH-DPR", "Sec-CH-UA-Mobile"]),
+        |req, _| {
+            let hints = req.extensions().get::<ClientHints>().unwrap();
+            assert_eq!(hints.dpr, Some(2.0));
+            assert_eq!(hints.mobile, Some(true));
+            assert_eq!(hints.brands[0], ("Chromium".to_owned(), "120".to_owned()));
+            Response::builder()
+                .header(VARY, "Accept-Encoding")
+                .body(Body::empty())
+                .unwrap()
+        },
+    );
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/image");
+    let headers = req.headers_mut();
+    headers.insert("sec-ch-dpr", "2".parse().unwrap());
+    headers.insert("sec-ch-ua-mobile", "?1".parse().unwrap());
+    headers.insert(
+        "sec-ch-ua",
+        r#""Chromium";v="120", "Not?A_Brand";v="8""#.parse().unwrap(),
+    );
+    let response = router.handle(req);
+    assert_eq!(
+        response.headers()[ACCEPT_CH],
+        "Sec-CH-DPR, Sec-CH-UA-Mobile"
+    );
+    assert_eq!(
+        response.headers()[VARY],
+        "Accept-Encoding, Sec-CH-DPR, Sec-CH-UA-Mobile"
+    );
+}
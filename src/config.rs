@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use hyper::{Body, Method, Request, Response, StatusCode};
+use toml::{Table, Value};
+
+use crate::{redirect, Captures, Error, RouteHandler, RouteOptions, RouterBuilder};
+
+type SharedHandler = Arc<dyn Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync>;
+
+/// Handlers that a route config can refer to by name, for
+/// `RouterBuilder::load_config`.
+#[derive(Default)]
+pub struct HandlerRegistry {
+    handlers: HashMap<String, SharedHandler>,
+}
+
+impl HandlerRegistry {
+    /// Create an empty registry.
+    pub fn new() -> HandlerRegistry {
+        HandlerRegistry::default()
+    }
+
+    /// Make `handler` available to configs as `name`. Registering a name a
+    /// second time replaces the earlier handler.
+    pub fn register<H>(&mut self, name: &str, handler: H) -> &mut HandlerRegistry
+    where
+        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+    {
+        self.handlers.insert(name.to_owned(), Arc::new(handler));
+        self
+    }
+}
+
+/// Handlers the app already keeps by name.
+impl<'a> From<HashMap<&'a str, RouteHandler>> for HandlerRegistry {
+    fn from(handlers: HashMap<&'a str, RouteHandler>) -> HandlerRegistry {
+        HandlerRegistry {
+            handlers: handlers
+                .into_iter()
+                .map(|(name, handler)| (name.to_owned(), SharedHandler::from(handler)))
+                .collect(),
+        }
+    }
+}
+
+impl RouterBuilder {
+    /// Add the routes declared in a TOML config. Each entry in the `routes`
+    /// array gives a `pattern` and either the name of a `handler` in
+    /// `registry` or a `redirect` target, so routes like these can change
+    /// without recompiling:
+    ///
+    /// ```toml
+    /// [[routes]]
+    /// method = "GET"  # The default.
+    /// pattern = '/users/(\d+)'
+    /// handler = "show_user"
+    /// name = "show_user"  # Optional, as are tags and description.
+    /// tags = ["users"]
+    ///
+    /// [[routes]]
+    /// pattern = '/profile/(\d+)'
+    /// redirect = "/users/$1"  # $1 and so on are replaced with captures.
+    /// status = 301  # Defaults to 308.
+    /// ```
+    ///
+    /// Nothing is added if any entry is invalid.
+    pub fn load_config(
+        &mut self,
+        config: &str,
+        registry: &HandlerRegistry,
+    ) -> Result<&mut RouterBuilder, Error> {
+        let config: Table = config
+            .parse()
+            .map_err(|error: toml::de::Error| Error::Config(error.to_string()))?;
+        let entries = match config.get("routes") {
+            Some(Value::Array(entries)) => entries.as_slice(),
+            Some(_) => return Err(invalid("`routes` must be an array of tables")),
+            None => &[],
+        };
+
+        let routes = entries
+            .iter()
+            .map(|entry| match entry {
+                Value::Table(entry) => route(entry, registry),
+                _ => Err(invalid("`routes` must be an array of tables")),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for (method, pattern, options, handler) in routes {
+            self.route_with(method, &pattern, options, move |req, captures| {
+                handler(req, captures)
+            });
+        }
+        Ok(self)
+    }
+}
+
+fn route(
+    entry: &Table,
+    registry: &HandlerRegistry,
+) -> Result<(Method, String, RouteOptions, SharedHandler), Error> {
+    let pattern = string(entry, "pattern")?.ok_or_else(|| invalid("a route has no `pattern`"))?;
+    let method = match string(entry, "method")? {
+        Some(method) => method
+            .to_uppercase()
+            .parse()
+            .map_err(|_| invalid(&format!("`{}` has an invalid method", pattern)))?,
+        None => Method::GET,
+    };
+
+    let handler: SharedHandler = match (string(entry, "handler")?, string(entry, "redirect")?) {
+        (Some(name), None) => match registry.handlers.get(name) {
+            Some(handler) => Arc::clone(handler),
+            None => {
+                return Err(invalid(&format!(
+                    "`{}` uses the unregistered handler `{}`",
+                    pattern, name
+                )))
+            }
+        },
+        (None, Some(target)) => {
+            let status = match entry.get("status") {
+                Some(Value::Integer(status)) => u16::try_from(*status)
+                    .ok()
+                    .and_then(|status| StatusCode::from_u16(status).ok())
+                    .filter(StatusCode::is_redirection),
+                Some(_) => None,
+                None => Some(StatusCode::PERMANENT_REDIRECT),
+            }
+            .ok_or_else(|| invalid(&format!("`{}` has an invalid redirect status", pattern)))?;
+            let target = target.to_owned();
+            Arc::new(move |_, captures| redirect::respond(&target, status, &captures))
+        }
+        _ => {
+            return Err(invalid(&format!(
+                "`{}` needs either a `handler` or a `redirect`",
+                pattern
+            )))
+        }
+    };
+
+    let mut options = RouteOptions::new();
+    if let Some(name) = string(entry, "name")? {
+        options = options.name(name);
+    }
+    if let Some(description) = string(entry, "description")? {
+        options = options.description(description);
+    }
+    match entry.get("tags") {
+        Some(Value::Array(tags)) => {
+            for tag in tags {
+                let tag = tag.as_str().ok_or_else(|| {
+                    invalid(&format!("`{}` has a tag that isn't a string", pattern))
+                })?;
+                options = options.tag(tag);
+            }
+        }
+        Some(_) => {
+            return Err(invalid(&format!(
+                "`{}` has tags that aren't an array",
+                pattern
+            )))
+        }
+        None => {}
+    }
+
+    Ok((method, pattern.to_owned(), options, handler))
+}
+
+// Look up an optional string field.
+fn string<'a>(entry: &'a Table, key: &str) -> Result<Option<&'a str>, Error> {
+    match entry.get(key) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(invalid(&format!("`{}` must be a string", key))),
+        None => Ok(None),
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Config(message.to_owned())
+}
+
+#[test]
+fn loads_routes() {
+    use hyper::header::LOCATION;
+
+    use crate::testing;
+
+    let mut registry = HandlerRegistry::new();
+    registry.register("show_user", |_, captures| {
+        Response::new(captures[1].to_owned().into())
+    });
+    let config = r#"
+        [[routes]]
+        pattern = '/users/(\d+)'
+        handler = "show_user"
+        tags = ["users"]
+
+        [[routes]]
+        pattern = '/profile/(\d+)'
+        redirect = "/users/$1"
+        status = 301
+    "#;
+    let mut builder = RouterBuilder::new();
+    builder.load_config(config, &registry).unwrap();
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/profile/7"));
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[LOCATION], "/users/7");
+    assert_eq!(router.metrics().routes[0].tags, ["users"]);
+
+    let unknown = "[[routes]]\npattern = '/'\nhandler = 'missing'";
+    assert!(RouterBuilder::new()
+        .load_config(unknown, &registry)
+        .is_err());
+}
+
+#[test]
+fn registers_handler_maps() {
+    use crate::testing;
+
+    let mut handlers: HashMap<&str, RouteHandler> = HashMap::new();
+    handlers.insert(
+        "maintenance",
+        Box::new(|_, _| {
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::empty())
+                .unwrap()
+        }),
+    );
+    handlers.insert(
+        "show_user",
+        Box::new(|_, captures| Response::new(captures[1].to_owned().into())),
+    );
+    let config = r#"
+        [[routes]]
+        pattern = '/users/(\d+)'
+        handler = "show_user"
+
+        [[routes]]
+        pattern = '/admin'
+        handler = "maintenance"
+    "#;
+    let mut builder = RouterBuilder::new();
+    builder.load_config(config, &handlers.into()).unwrap();
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.handle(testing::get("/admin")).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    let response = router.handle(testing::get("/users/7"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = testing::body(response);
+    assert_eq!(body, "7");
+}
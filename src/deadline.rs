@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyper::header::{HeaderMap, HeaderValue};
+
+const REQUEST_DEADLINE: &str = "x-request-deadline";
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// When the caller of a request stops waiting for it, read from its
+/// `X-Request-Deadline` or `grpc-timeout` header.
+///
+/// When deadlines are turned on with `RouterBuilder::deadlines` the deadline
+/// is added to requests as an extension. Requests whose deadline has passed
+/// by the time they reach a handler are answered with `504 Gateway Timeout`
+/// instead, and reading the body fails with `BodyTimeout` once it passes.
+/// Handlers that call other services should pass what is left on with
+/// `apply`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// A deadline `timeout` from now.
+    pub fn after(timeout: Duration) -> Deadline {
+        Deadline(Instant::now() + timeout)
+    }
+
+    // A deadline at `instant`.
+    pub(crate) fn at(instant: Instant) -> Deadline {
+        Deadline(instant)
+    }
+
+    /// Read the deadline from a set of headers. `X-Request-Deadline` holds
+    /// the deadline as milliseconds since the Unix epoch and `grpc-timeout`
+    /// the time left, like `250m` for 250 milliseconds. The earlier one wins
+    /// if both are sent.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Deadline> {
+        let header = |name| headers.get(name)?.to_str().ok().map(str::trim);
+        let absolute = header(REQUEST_DEADLINE)
+            .and_then(|value| value.parse().ok())
+            .map(|millis| {
+                let at = UNIX_EPOCH + Duration::from_millis(millis);
+                let remaining = at.duration_since(SystemTime::now()).unwrap_or_default();
+                Deadline::after(remaining)
+            });
+        let relative = header(GRPC_TIMEOUT)
+            .and_then(grpc_timeout)
+            .map(Deadline::after);
+        match (absolute, relative) {
+            (Some(absolute), Some(relative)) => Some(absolute.min(relative)),
+            (deadline, None) | (None, deadline) => deadline,
+        }
+    }
+
+    /// The instant the deadline passes.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// The time left before the deadline, which is zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed.
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Set the `X-Request-Deadline` and `grpc-timeout` headers of a request
+    /// to another service to what is left of this deadline.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let remaining = self.remaining();
+        let at = SystemTime::now() + remaining;
+        let millis = at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        headers.insert(REQUEST_DEADLINE, HeaderValue::from(millis as u64));
+        // The value may have at most eight digits.
+        let millis = remaining.as_millis().min(99_999_999);
+        if let Ok(value) = HeaderValue::from_str(&format!("{}m", millis)) {
+            headers.insert(GRPC_TIMEOUT, value);
+        }
+    }
+}
+
+// Parse a `grpc-timeout` value: up to eight digits and a unit.
+fn grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = amount.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+#[test]
+fn propagates_deadlines() {
+    use hyper::{Body, Request, Response, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.deadlines();
+    builder.get(r"/", |req: Request<Body>, _| {
+        let deadline = req.extensions().get::<Deadline>().unwrap();
+        assert!(deadline.remaining() > Duration::from_secs(1));
+        assert!(deadline.remaining() <= Duration::from_secs(2));
+        let mut upstream = HeaderMap::new();
+        deadline.apply(&mut upstream);
+        let timeout = grpc_timeout(upstream[GRPC_TIMEOUT].to_str().unwrap()).unwrap();
+        assert!(timeout > Duration::from_secs(1));
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/");
+    req.headers_mut()
+        .insert(GRPC_TIMEOUT, "2S".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+
+    let mut req = testing::get("/");
+    req.headers_mut()
+        .insert(REQUEST_DEADLINE, "1000".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::GATEWAY_TIMEOUT);
+}
+
+#[test]
+fn times_out_routes() {
+    use std::thread;
+
+    use hyper::{Body, Method, Request, Response, StatusCode};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/proxy",
+        RouteOptions::new().timeout(Duration::from_millis(20)),
+        |req: Request<Body>, _| {
+            let deadline = req.extensions().get::<Deadline>().unwrap();
+            assert!(deadline.remaining() <= Duration::from_millis(20));
+            thread::sleep(Duration::from_millis(30));
+            // The handler decides what to do once its time is up.
+            if deadline.expired() {
+                return StatusCode::GATEWAY_TIMEOUT;
+            }
+            StatusCode::OK
+        },
+    );
+    builder.route_with(
+        Method::POST,
+        r"/orders",
+        RouteOptions::new().timeout(Duration::from_millis(20)),
+        |_, _| {
+            thread::sleep(Duration::from_millis(30));
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .body(Body::empty())
+                .unwrap()
+        },
+    );
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.handle(testing::get("/proxy")).status(),
+        StatusCode::GATEWAY_TIMEOUT
+    );
+    // A late handler's response is still sent, since its work was done.
+    assert_eq!(
+        router.handle(testing::post("/orders", "")).status(),
+        StatusCode::CREATED
+    );
+}
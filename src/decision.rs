@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hyper::Method;
+
+use crate::body::RouteBody;
+use crate::{accepts, Captures, Router};
+
+/// A description of how the router handled, or would handle, a request.
+#[derive(Debug)]
+pub struct RoutingDecision<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// The number of patterns the path was tested against.
+    pub tested: usize,
+    /// Every route whose pattern matched the path, in registration order.
+    pub matched: Vec<RouteMatch<'a>>,
+    pub outcome: Outcome,
+    /// The captures passed to the chosen handler, if there was one.
+    pub captures: Vec<&'a str>,
+}
+
+/// A route whose pattern matched the path and what became of it.
+#[derive(Debug)]
+pub struct RouteMatch<'a> {
+    pub index: usize,
+    pub method: &'a Method,
+    pub pattern: &'a str,
+    pub result: MatchResult,
+}
+
+/// What happened to a route whose pattern matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchResult {
+    /// This route's handler was chosen.
+    Dispatched,
+    /// The route was skipped because it is for a different method.
+    MethodMismatch,
+    /// An earlier route was chosen so this one was never considered.
+    Shadowed,
+}
+
+/// The final result of routing a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The handler of the route at this index was called.
+    Dispatched(usize),
+    NotFound,
+    MethodNotAllowed,
+}
+
+// Picks every nth request for routing decision reports.
+pub(crate) struct Sampler {
+    every: u64,
+    seen: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) fn new(every: u64) -> Sampler {
+        Sampler {
+            every: every.max(1),
+            seen: AtomicU64::default(),
+        }
+    }
+
+    pub(crate) fn sample(&self) -> bool {
+        self.seen
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.every)
+    }
+}
+
+impl<B: RouteBody> Router<B> {
+    /// Describe how a request with `method` and `path` would be routed: how
+    /// many patterns were tested, every route that matched and why it was or
+    /// wasn't chosen, the captures its handler would get and the outcome.
+    /// No handler is called.
+    pub fn explain<'a>(&'a self, method: &'a Method, path: &'a str) -> RoutingDecision<'a> {
+        let mut outcome = None;
+        let mut captures = Vec::new();
+        let (matches, matched_path) = self.matches(path);
+        let routed = self.routed_method(method, &matches);
+        let matched = matches
+            .iter()
+            .map(|&index| {
+                let route_method = &self.handlers[index].0;
+                let result = if outcome.is_some() {
+                    MatchResult::Shadowed
+                } else if !accepts(route_method, &routed) {
+                    MatchResult::MethodMismatch
+                } else {
+                    outcome = Some(Outcome::Dispatched(index));
+                    captures = Captures::new(&self.patterns[index], matched_path).to_vec();
+                    MatchResult::Dispatched
+                };
+                RouteMatch {
+                    index,
+                    method: route_method,
+                    pattern: &self.sources[index],
+                    result,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let outcome = outcome.unwrap_or(if matched.is_empty() {
+            Outcome::NotFound
+        } else {
+            Outcome::MethodNotAllowed
+        });
+
+        RoutingDecision {
+            method,
+            path,
+            tested: self.sources.len(),
+            matched,
+            outcome,
+            captures,
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log(decision: &RoutingDecision) {
+    let matched: Vec<String> = decision
+        .matched
+        .iter()
+        .map(|m| format!("{} {} {:?}", m.method, m.pattern, m.result))
+        .collect();
+    tracing::debug!(
+        method = %decision.method,
+        path = decision.path,
+        tested = decision.tested,
+        matched = ?matched,
+        outcome = ?decision.outcome,
+        "routing decision"
+    );
+}
+
+#[test]
+fn explain_reports_captures() {
+    use hyper::{Body, Response};
+
+    let mut builder = crate::RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let decision = router.explain(&Method::GET, "/users/42");
+    assert_eq!(decision.outcome, Outcome::Dispatched(0));
+    assert_eq!(decision.captures, vec!["/users/42", "42"]);
+    assert_eq!(
+        router.explain(&Method::PUT, "/users/42").outcome,
+        Outcome::MethodNotAllowed
+    );
+}
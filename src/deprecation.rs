@@ -0,0 +1,121 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::header::{HeaderValue, LINK};
+use hyper::{Method, Request, Response};
+
+/// Marks a route as deprecated. Responses from the route then carry a
+/// `Deprecation` header, a `Sunset` header if a sunset date is given and a
+/// `Link` header pointing at any successor or documentation.
+///
+/// Attach one to a route with `RouteOptions::deprecated`.
+#[derive(Clone, Debug, Default)]
+pub struct Deprecation {
+    since: Option<SystemTime>,
+    sunset: Option<SystemTime>,
+    successor: Option<String>,
+    documentation: Option<String>,
+}
+
+/// A request to a deprecated route, passed to the hook installed with
+/// `RouterBuilder::on_deprecated_use` so remaining callers can be found.
+#[derive(Debug)]
+pub struct DeprecatedUse<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    pub route: &'a str,
+    pub user_agent: Option<&'a str>,
+}
+
+impl Deprecation {
+    /// Deprecate a route without giving a date.
+    pub fn new() -> Deprecation {
+        Deprecation::default()
+    }
+
+    /// The time the route was deprecated.
+    pub fn since(mut self, since: SystemTime) -> Deprecation {
+        self.since = Some(since);
+        self
+    }
+
+    /// The time after which the route will stop responding.
+    pub fn sunset(mut self, sunset: SystemTime) -> Deprecation {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    /// The URL of the route that replaces this one.
+    pub fn successor(mut self, url: &str) -> Deprecation {
+        self.successor = Some(url.to_owned());
+        self
+    }
+
+    /// The URL of a document describing the deprecation.
+    pub fn documentation(mut self, url: &str) -> Deprecation {
+        self.documentation = Some(url.to_owned());
+        self
+    }
+
+    pub(crate) fn apply<B>(&self, response: &mut Response<B>) {
+        let headers = response.headers_mut();
+
+        // RFC 9745 gives the date as a structured field integer of seconds.
+        let deprecation = match self.since {
+            Some(since) => format!(
+                "@{}",
+                since
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            ),
+            None => "?1".to_owned(),
+        };
+        insert(headers, "deprecation", &deprecation);
+        if let Some(sunset) = self.sunset {
+            insert(headers, "sunset", &httpdate::fmt_http_date(sunset));
+        }
+
+        let links = [
+            (&self.successor, "successor-version"),
+            (&self.documentation, "deprecation"),
+        ];
+        for (url, rel) in links.iter() {
+            if let Some(url) = url {
+                if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"{}\"", url, rel)) {
+                    headers.append(LINK, value);
+                }
+            }
+        }
+    }
+}
+
+fn insert(headers: &mut hyper::HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+pub(crate) fn user_agent<B>(req: &Request<B>) -> Option<&str> {
+    req.headers()
+        .get(hyper::header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[test]
+fn adds_deprecation_headers() {
+    use std::time::Duration;
+
+    use hyper::Body;
+
+    let deprecation = Deprecation::new()
+        .since(UNIX_EPOCH + Duration::from_secs(1_688_169_599))
+        .sunset(UNIX_EPOCH + Duration::from_secs(1_581_267_845))
+        .successor("/v2/users");
+    let mut response = Response::new(Body::empty());
+    deprecation.apply(&mut response);
+
+    let headers = response.headers();
+    assert_eq!(headers["deprecation"], "@1688169599");
+    assert_eq!(headers["sunset"], "Sun, 09 Feb 2020 17:04:05 GMT");
+    assert_eq!(headers[LINK], "</v2/users>; rel=\"successor-version\"");
+}
@@ -0,0 +1,75 @@
+use hyper::header::USER_AGENT;
+use hyper::Request;
+
+/// The kind of client a request came from. It is added to the request's
+/// extensions when devices are classified, which happens if a classifier is
+/// given to `RouterBuilder::device_classifier` or any route is limited to
+/// some devices with `RouteOptions::devices`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Device {
+    /// Phones and tablets.
+    Mobile,
+    Desktop,
+    /// Crawlers and other automated clients that say so.
+    Bot,
+}
+
+impl Device {
+    /// Classify a client by its `User-Agent`. This is the classifier used
+    /// when no other is given and only looks for a few well known words, so
+    /// it is a guess at best.
+    pub fn from_user_agent(user_agent: &str) -> Device {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| user_agent.contains(word));
+        if has(&["bot", "crawler", "spider", "slurp"]) {
+            Device::Bot
+        } else if has(&["mobi", "android", "iphone", "ipad"]) {
+            Device::Mobile
+        } else {
+            Device::Desktop
+        }
+    }
+}
+
+// The classifier used when routes need devices but none was given.
+pub(crate) fn classify<B>(req: &Request<B>) -> Device {
+    req.headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map_or(Device::Desktop, Device::from_user_agent)
+}
+
+#[test]
+fn routes_by_device() {
+    use hyper::{Body, Method, Response};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/",
+        RouteOptions::new().devices(&[Device::Mobile]),
+        |_, _| Response::new("mobile".into()),
+    );
+    builder.get(r"/", |req, _| {
+        let device = req.extensions().get::<Device>().unwrap();
+        Response::new(format!("{:?}", device).into())
+    });
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/");
+    req.headers_mut().insert(
+        USER_AGENT,
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) Mobile/15E148"
+            .parse()
+            .unwrap(),
+    );
+    let body = |req: Request<Body>| testing::body(router.handle(req));
+    assert_eq!(body(req), "mobile");
+
+    let mut req = testing::get("/");
+    req.headers_mut()
+        .insert(USER_AGENT, "Googlebot/2.1".parse().unwrap());
+    assert_eq!(body(req), "Bot");
+}
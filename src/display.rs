@@ -0,0 +1,242 @@
+use std::fmt;
+
+use hyper::Method;
+
+use crate::{admin, RouteOptions, Router};
+
+/// A route in a router's route table, as returned by `Router::routes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RouteInfo<'a> {
+    pub method: &'a Method,
+    /// The pattern as it was registered, before anchoring.
+    pub pattern: &'a str,
+    /// The name given with `RouteOptions::name`, if any.
+    pub name: Option<&'a str>,
+    /// The tags given with `RouteOptions::tag`.
+    pub tags: &'a [String],
+}
+
+// Renders the route table in registration order, one route per line, with any
+// per-route settings listed after the pattern. For example:
+//
+//     GET     /users/(\d+)
+//     POST    /users        [deprecated, slo 99% within 200ms]
+impl<B> fmt::Display for Router<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.sources.iter().map(String::len).max().unwrap_or(0);
+        for ((method, _), (source, options)) in self
+            .handlers
+            .iter()
+            .zip(self.sources.iter().zip(&self.options))
+        {
+            let notes = notes(options);
+            if notes.is_empty() {
+                writeln!(f, "{:<7} {}", method.as_str(), source)?;
+            } else {
+                writeln!(
+                    f,
+                    "{:<7} {:<width$}  [{}]",
+                    method.as_str(),
+                    source,
+                    notes.join(", "),
+                    width = width
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B> fmt::Debug for Router<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.handlers
+                    .iter()
+                    .zip(&self.sources)
+                    .map(|((method, _), source)| (method, source)),
+            )
+            .finish()
+    }
+}
+
+impl<B> Router<B> {
+    /// Every route in registration order, to display the route table,
+    /// generate documentation or check in tests which routes were added.
+    pub fn routes(&self) -> impl Iterator<Item = RouteInfo<'_>> {
+        self.handlers
+            .iter()
+            .zip(self.sources.iter().zip(&self.options))
+            .map(|((method, _), (pattern, options))| RouteInfo {
+                method,
+                pattern,
+                name: options.metadata.name.as_deref(),
+                tags: &options.metadata.tags,
+            })
+    }
+
+    /// Print the route table to standard output, which is handy when a
+    /// server starts up.
+    pub fn print_routes(&self) {
+        print!("{}", self);
+    }
+
+    /// Describe every route as JSON for tools that consume the routing
+    /// topology, such as gateway or contract test generators. The document is
+    /// an array with an object for each route in registration order:
+    ///
+    /// ```text
+    /// [{"method":"GET","pattern":"/users/(\\d+)","name":"get_user","tags":["users"],
+    ///   "description":null}]
+    /// ```
+    ///
+    /// Fields may be added over time but existing ones won't change. This is
+    /// the same document the admin routes endpoint serves.
+    pub fn to_json(&self) -> String {
+        admin::routes(&self.metrics)
+    }
+}
+
+fn notes<B>(options: &RouteOptions<B>) -> Vec<String> {
+    let mut notes = Vec::new();
+    if let Some(name) = &options.metadata.name {
+        notes.push(format!("name {}", name));
+    }
+    for tag in &options.metadata.tags {
+        notes.push(format!("#{}", tag));
+    }
+    if let Some(slo) = &options.slo {
+        notes.push(format!("{}", slo));
+    }
+    if let Some(threshold) = options.slow_threshold {
+        notes.push(format!("slow after {:?}", threshold));
+    }
+    if let Some(timeout) = options.timeout {
+        notes.push(format!("timeout {:?}", timeout));
+    }
+    if let Some(max) = options.max_body_size {
+        notes.push(format!("max body {}", max));
+    }
+    if options.priority != 0 {
+        notes.push(format!("priority {}", options.priority));
+    }
+    if let Some(host) = &options.host {
+        notes.push(format!("host {}", host));
+    }
+    if let Some(pattern) = &options.host_pattern {
+        notes.push(format!("host {}", pattern));
+    }
+    if let Some(variant) = &options.variant {
+        notes.push(format!("variant {}", variant));
+    }
+    if let Some(scheme) = &options.scheme {
+        notes.push(format!("scheme {}", scheme));
+    }
+    if let Some(media) = &options.consumes {
+        notes.push(format!("consumes {}", media));
+    }
+    if let Some(media) = &options.produces {
+        notes.push(format!("produces {}", media));
+    }
+    if !options.guards.is_empty() {
+        notes.push("guarded".to_owned());
+    }
+    if let Some(regions) = &options.regions {
+        notes.push(format!("regions {}", regions.join(" ")));
+    }
+    if let Some(devices) = &options.devices {
+        let devices: Vec<String> = devices
+            .iter()
+            .map(|device| format!("{:?}", device))
+            .collect();
+        notes.push(format!("devices {}", devices.join(" ").to_lowercase()));
+    }
+    if options.deprecation.is_some() {
+        notes.push("deprecated".to_owned());
+    }
+    if options.recorder.is_some() {
+        notes.push("recorded".to_owned());
+    }
+    if options.quota.is_some() {
+        notes.push("quota".to_owned());
+    }
+    if options.canary.is_some() {
+        notes.push("canary".to_owned());
+    }
+    notes
+}
+
+#[test]
+fn lists_routes_with_settings() {
+    use std::time::Duration;
+
+    use hyper::{Body, Method, Response};
+
+    use crate::{Deprecation, RouterBuilder, Slo};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.route_with(
+        Method::POST,
+        r"/users",
+        RouteOptions::new()
+            .deprecated(Deprecation::new())
+            .slo(Slo::new(0.99, Duration::from_millis(200))),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.to_string(),
+        "GET     /users/(\\d+)\nPOST    /users        [slo 99% within 200ms, deprecated]\n"
+    );
+}
+
+#[test]
+fn iterates_routes() {
+    use hyper::{Body, Response};
+
+    use crate::RouterBuilder;
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/users/(\d+)",
+        RouteOptions::new().name("get_user").tag("users"),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.post(r"/users", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let routes: Vec<RouteInfo> = router.routes().collect();
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[0].method, Method::GET);
+    assert_eq!(routes[0].pattern, r"/users/(\d+)");
+    assert_eq!(routes[0].name, Some("get_user"));
+    assert_eq!(routes[0].tags, ["users"]);
+    assert_eq!(
+        (routes[1].method, routes[1].pattern, routes[1].name),
+        (&Method::POST, "/users", None)
+    );
+}
+
+#[test]
+fn exports_routes_as_json() {
+    use hyper::{Body, Method, Response};
+
+    use crate::RouterBuilder;
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/users/(\d+)",
+        RouteOptions::new().name("get_user").tag("users"),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.to_json(),
+        r#"[{"method":"GET","pattern":"/users/(\\d+)","name":"get_user","tags":["users"],"description":null}]"#
+    );
+}
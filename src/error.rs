@@ -1,9 +1,26 @@
 use std::fmt;
 
+use hyper::Method;
+
+use crate::Shadowing;
+
 // Potential errors that can happen while constructing a router.
 #[derive(Debug)]
 pub enum Error {
     BadRegex(::regex::Error),
+    /// A route has the same method and pattern as an earlier one that
+    /// matches every request it could, so it would never be used.
+    DuplicateRoute {
+        method: Method,
+        pattern: String,
+    },
+    /// An earlier route takes every request a later route could match, so
+    /// the later one would never be used. Only reported when asked for with
+    /// `RouterBuilder::deny_unreachable`.
+    UnreachableRoute(Shadowing),
+    /// A route config passed to `RouterBuilder::load_config` was invalid.
+    #[cfg(feature = "config")]
+    Config(String),
 }
 
 impl From<::regex::Error> for Error {
@@ -16,6 +33,15 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::BadRegex(ref error) => write!(f, "{}", error),
+            Error::DuplicateRoute {
+                ref method,
+                ref pattern,
+            } => write!(f, "duplicate route: {} {}", method, pattern),
+            Error::UnreachableRoute(ref shadowing) => {
+                write!(f, "unreachable route: {}", shadowing)
+            }
+            #[cfg(feature = "config")]
+            Error::Config(ref message) => write!(f, "invalid route config: {}", message),
         }
     }
 }
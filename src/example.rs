@@ -0,0 +1,99 @@
+use hyper::Method;
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::Router;
+
+// Characters to prefer when picking one from a class, so examples look like
+// paths a person would write.
+const PREFERRED: &[char] = &['1', 'a', 'A', 'x', '-', '_'];
+
+impl<B> Router<B> {
+    /// An example path for each route, for smoke tests and documentation.
+    /// Every path is checked against its route so routes whose patterns
+    /// can't be satisfied by a simple example are left out.
+    pub fn example_paths(&self) -> Vec<(Method, String)> {
+        self.handlers
+            .iter()
+            .zip(&self.sources)
+            .zip(&self.patterns)
+            .filter_map(|(((method, _), source), pattern)| {
+                path(source)
+                    .filter(|path| pattern.is_match(path))
+                    .map(|path| (method.clone(), path))
+            })
+            .collect()
+    }
+}
+
+// Build a string matching `pattern`, taking the first alternative, one
+// repetition and the plainest character of every class. Optional parts are
+// left out.
+pub(crate) fn path(pattern: &str) -> Option<String> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+    let mut out = String::new();
+    write(&hir, &mut out)?;
+    Some(out)
+}
+
+fn write(hir: &Hir, out: &mut String) -> Option<()> {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => out.push_str(std::str::from_utf8(&literal.0).ok()?),
+        HirKind::Class(Class::Unicode(class)) => {
+            let ranges = class.ranges();
+            let contains = |c: char| {
+                ranges
+                    .iter()
+                    .any(|range| range.start() <= c && c <= range.end())
+            };
+            let printable = || {
+                ranges
+                    .iter()
+                    .map(|range| range.start().max('!'))
+                    .find(|&c| contains(c))
+            };
+            out.push(
+                PREFERRED
+                    .iter()
+                    .copied()
+                    .find(|&c| contains(c))
+                    .or_else(printable)?,
+            );
+        }
+        HirKind::Class(Class::Bytes(_)) => return None,
+        HirKind::Repetition(repetition) => {
+            let count = match (repetition.min, repetition.max) {
+                (0, Some(1)) => 0,
+                (min, _) => min.max(1),
+            };
+            for _ in 0..count {
+                write(&repetition.sub, out)?;
+            }
+        }
+        HirKind::Capture(capture) => write(&capture.sub, out)?,
+        HirKind::Concat(parts) => {
+            for part in parts {
+                write(part, out)?;
+            }
+        }
+        HirKind::Alternation(alternatives) => write(alternatives.first()?, out)?,
+    }
+    Some(())
+}
+
+#[test]
+fn generates_matching_paths() {
+    use hyper::{Body, Response};
+
+    use crate::RouterBuilder;
+
+    assert_eq!(path(r"/users/(\d+)").unwrap(), "/users/1");
+    assert_eq!(path(r"/files/([^/]+)\.(?:txt|md)").unwrap(), "/files/1.txt");
+    assert_eq!(path(r"/posts(?:/page/\d{2})?").unwrap(), "/posts");
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/(?P<slug>[a-z-]+)", |_, _| Response::new(Body::empty()));
+    builder.get(r"/a\bb", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+    assert_eq!(router.example_paths(), vec![(Method::GET, "/a".to_owned())]);
+}
@@ -0,0 +1,106 @@
+use hyper::Request;
+
+use crate::canary::cookie;
+
+/// Where the router reads which variant of an A/B experiment each request
+/// is in, given to `RouterBuilder::experiment`.
+///
+/// The variant is read once per request and added to it as a `Variant`
+/// extension, so every route and handler sees the same one. Routes for a
+/// single variant are added with `RouteOptions::variant`, and a route for
+/// the same path without one, added after them, serves the default variant
+/// and requests in none.
+#[derive(Clone, Debug, Default)]
+pub struct Experiment {
+    header: Option<String>,
+    cookie: Option<String>,
+}
+
+/// The experiment variant a request is in, such as `b`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant(pub String);
+
+impl Experiment {
+    /// An experiment that reads no variants until given a header or cookie.
+    pub fn new() -> Experiment {
+        Experiment::default()
+    }
+
+    /// Read the variant from the `name` header, such as `X-Experiment: b`.
+    pub fn header(mut self, name: &str) -> Experiment {
+        self.header = Some(name.to_ascii_lowercase());
+        self
+    }
+
+    /// Read the variant from the `name` cookie. The header wins if a request
+    /// has both.
+    pub fn cookie(mut self, name: &str) -> Experiment {
+        self.cookie = Some(name.to_owned());
+        self
+    }
+
+    // Record the variant `req` is in on it, if it is in one.
+    pub(crate) fn assign<B>(&self, req: &mut Request<B>) {
+        let header = self.header.as_ref().and_then(|name| {
+            req.headers()
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+        });
+        let variant = header
+            .or_else(|| self.cookie.as_ref().and_then(|name| cookie(req, name)))
+            .map(str::trim)
+            .filter(|variant| !variant.is_empty())
+            .map(str::to_owned);
+        if let Some(variant) = variant {
+            req.extensions_mut().insert(Variant(variant));
+        }
+    }
+}
+
+// Whether `req` is in `variant`.
+pub(crate) fn allowed<B>(variant: &str, req: &Request<B>) -> bool {
+    req.extensions()
+        .get::<Variant>()
+        .is_some_and(|Variant(assigned)| assigned == variant)
+}
+
+#[test]
+fn routes_by_variant() {
+    use hyper::header::COOKIE;
+    use hyper::{Body, Method, Response};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.experiment(
+        Experiment::new()
+            .header("X-Experiment")
+            .cookie("experiment"),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/checkout",
+        RouteOptions::new().variant("b"),
+        |_, _| Response::new("b".into()),
+    );
+    builder.get(r"/checkout", |req, _| {
+        assert!(req.extensions().get::<Variant>() != Some(&Variant("b".to_owned())));
+        Response::new("a".into())
+    });
+    let router = builder.finalize().unwrap();
+
+    let body = |req: Request<Body>| testing::body(router.handle(req));
+    assert_eq!(body(testing::get("/checkout")), "a");
+    let mut req = testing::get("/checkout");
+    req.headers_mut()
+        .insert("x-experiment", "b".parse().unwrap());
+    assert_eq!(body(req), "b");
+    let mut req = testing::get("/checkout");
+    req.headers_mut()
+        .insert(COOKIE, "experiment=b".parse().unwrap());
+    assert_eq!(body(req), "b");
+    let mut req = testing::get("/checkout");
+    req.headers_mut()
+        .insert("x-experiment", "c".parse().unwrap());
+    assert_eq!(body(req), "a");
+}
@@ -0,0 +1,338 @@
+//! Typed arguments for handlers, taken from the parts of a request.
+//!
+//! Handlers added with `RouterBuilder::route_extract` are given the request
+//! followed by any number of extractors, each parsed before the handler is
+//! called. A request an extractor can't be parsed from is answered with the
+//! `Rejection` instead, so the handler only sees well formed input:
+//!
+//! ```ignore
+//! builder.get_extract(r"/users/(\d+)/posts/(\w+)", |_: Request<Body>, Path((id, slug)): Path<(u64, String)>| {
+//!     format!("post {} of user {}", slug, id)
+//! });
+//! ```
+//!
+//! `Query` and `Json` need the `extract` feature. Handlers are called
+//! synchronously, so request bodies can't be extracted before they run;
+//! `Json::from_body` parses one where a handler can wait for it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hyper::header::HeaderMap;
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+use crate::body::RouteBody;
+use crate::{Captures, IntoResponse, RouterBuilder};
+
+/// A value that can be parsed from a request and its captures.
+pub trait FromRequest: Sized {
+    /// Parse the value, or say why the request can't be handled.
+    fn from_request(req: &Request<Body>, captures: &Captures) -> Result<Self, Rejection>;
+}
+
+/// Why a request was turned away before reaching its handler, which becomes
+/// its response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    status: StatusCode,
+    message: String,
+}
+
+impl Rejection {
+    /// Turn a request away with `status`, explaining why with `message`.
+    pub fn new(status: StatusCode, message: &str) -> Rejection {
+        Rejection {
+            status,
+            message: message.to_owned(),
+        }
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Why the request was turned away.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl<B: RouteBody> IntoResponse<B> for Rejection {
+    fn into_response(self) -> Response<B> {
+        (self.status, self.message).into_response()
+    }
+}
+
+/// The capture groups of the path, starting from the first after the whole
+/// path. A single value is the first group and a tuple takes as many groups
+/// as it has fields, each parsed with `FromStr`. Groups that are missing or
+/// don't parse give a `400 Bad Request`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path<T>(pub T);
+
+/// Values that can be parsed from a path's capture groups, for `Path`.
+pub trait FromCaptures: Sized {
+    /// Parse the value from `captures`.
+    fn from_captures(captures: &Captures) -> Result<Self, Rejection>;
+}
+
+impl<T: FromCaptures> FromRequest for Path<T> {
+    fn from_request(_: &Request<Body>, captures: &Captures) -> Result<Self, Rejection> {
+        T::from_captures(captures).map(Path)
+    }
+}
+
+// Parse the group numbered `index`.
+fn group<T: FromStr>(captures: &Captures, index: usize) -> Result<T, Rejection> {
+    captures
+        .get_as(index)
+        .map_err(|error| Rejection::new(StatusCode::BAD_REQUEST, &error.to_string()))
+}
+
+macro_rules! from_captures_single {
+    ($($ty:ty),*) => {
+        $(
+            impl FromCaptures for $ty {
+                fn from_captures(captures: &Captures) -> Result<Self, Rejection> {
+                    group(captures, 1)
+                }
+            }
+        )*
+    };
+}
+
+from_captures_single!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+macro_rules! from_captures_tuple {
+    ($($ty:ident $index:tt),*) => {
+        impl<$($ty: FromStr),*> FromCaptures for ($($ty,)*) {
+            fn from_captures(captures: &Captures) -> Result<Self, Rejection> {
+                Ok(($(group::<$ty>(captures, $index + 1)?,)*))
+            }
+        }
+    };
+}
+
+from_captures_tuple!(A 0);
+from_captures_tuple!(A 0, B 1);
+from_captures_tuple!(A 0, B 1, C 2);
+from_captures_tuple!(A 0, B 1, C 2, D 3);
+
+/// A copy of the request's headers.
+#[derive(Clone, Debug)]
+pub struct Headers(pub HeaderMap);
+
+impl FromRequest for Headers {
+    fn from_request(req: &Request<Body>, _: &Captures) -> Result<Self, Rejection> {
+        Ok(Headers(req.headers().clone()))
+    }
+}
+
+impl FromRequest for Method {
+    fn from_request(req: &Request<Body>, _: &Captures) -> Result<Self, Rejection> {
+        Ok(req.method().clone())
+    }
+}
+
+/// The query string deserialized into a `T`. Query strings that don't fit
+/// give a `400 Bad Request`, and a missing one is treated as empty.
+#[cfg(feature = "extract")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query<T>(pub T);
+
+#[cfg(feature = "extract")]
+impl<T: serde::de::DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(req: &Request<Body>, _: &Captures) -> Result<Self, Rejection> {
+        serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+            .map(Query)
+            .map_err(|error| Rejection::new(StatusCode::BAD_REQUEST, &error.to_string()))
+    }
+}
+
+/// A JSON document. Handlers can return one to respond with it, and parse a
+/// request body into one with `from_body`.
+#[cfg(feature = "extract")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "extract")]
+impl<T: serde::de::DeserializeOwned> Json<T> {
+    /// Read the whole of `body` and parse it. Bodies that can't be read give
+    /// a `400 Bad Request` and those that don't hold a `T` a
+    /// `422 Unprocessable Entity`.
+    pub async fn from_body(body: Body) -> Result<Json<T>, Rejection> {
+        let bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(|error| Rejection::new(StatusCode::BAD_REQUEST, &error.to_string()))?;
+        serde_json::from_slice(&bytes)
+            .map(Json)
+            .map_err(|error| Rejection::new(StatusCode::UNPROCESSABLE_ENTITY, &error.to_string()))
+    }
+}
+
+#[cfg(feature = "extract")]
+impl<B: RouteBody, T: serde::Serialize> IntoResponse<B> for Json<T> {
+    fn into_response(self) -> Response<B> {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => Response::builder()
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body).into())
+                .unwrap(),
+            Err(error) => crate::failure::respond(error.into()),
+        }
+    }
+}
+
+/// A handler taking the request and extractors, for
+/// `RouterBuilder::route_extract`. This is implemented for functions and
+/// closures taking a request and up to four extractors.
+pub trait ExtractHandler<Args>: Send + Sync + 'static {
+    /// Parse the extractors and call the handler, or respond with the first
+    /// rejection.
+    fn call(&self, req: Request<Body>, captures: Captures) -> Response<Body>;
+}
+
+macro_rules! extract_handler {
+    ($($ty:ident),*) => {
+        impl<F, R, $($ty,)*> ExtractHandler<($($ty,)*)> for F
+        where
+            F: Fn(Request<Body>, $($ty),*) -> R + Send + Sync + 'static,
+            R: IntoResponse,
+            $($ty: FromRequest,)*
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, req: Request<Body>, captures: Captures) -> Response<Body> {
+                $(
+                    let $ty = match $ty::from_request(&req, &captures) {
+                        Ok(value) => value,
+                        Err(rejection) => return rejection.into_response(),
+                    };
+                )*
+                self(req, $($ty),*).into_response()
+            }
+        }
+    };
+}
+
+extract_handler!(A);
+extract_handler!(A, B);
+extract_handler!(A, B, C);
+extract_handler!(A, B, C, D);
+
+impl RouterBuilder {
+    /// Install a handler taking extractors for requests of method `verb` to
+    /// paths matching `route`. See the `extract` module.
+    pub fn route_extract<H, Args>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder
+    where
+        H: ExtractHandler<Args>,
+    {
+        self.route(verb, route, move |req, captures| {
+            handler.call(req, captures)
+        })
+    }
+
+    /// Convenience method to install a GET handler taking extractors.
+    pub fn get_extract<H, Args>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    where
+        H: ExtractHandler<Args>,
+    {
+        self.route_extract(Method::GET, route, handler)
+    }
+
+    /// Convenience method to install a POST handler taking extractors.
+    pub fn post_extract<H, Args>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    where
+        H: ExtractHandler<Args>,
+    {
+        self.route_extract(Method::POST, route, handler)
+    }
+}
+
+#[test]
+fn extracts_paths() {
+    use crate::testing;
+
+    let mut builder = RouterBuilder::new();
+    builder.get_extract(
+        r"/users/(\d+)/posts/(\w+)",
+        |_: Request<Body>, Path((id, slug)): Path<(u64, String)>, method: Method| {
+            format!("{} post {} of user {}", method, slug, id)
+        },
+    );
+    builder.get_extract(r"/items/(\w+)", |_: Request<Body>, Path(id): Path<u32>| {
+        id.to_string()
+    });
+    let router = builder.finalize().unwrap();
+
+    let send = |path| {
+        let response = router.handle(testing::get(path));
+        let status = response.status();
+        let body = testing::body(response);
+        (status, body)
+    };
+    assert_eq!(
+        send("/users/7/posts/hello"),
+        (StatusCode::OK, "GET post hello of user 7".into())
+    );
+    assert_eq!(send("/items/12"), (StatusCode::OK, "12".into()));
+    assert_eq!(
+        send("/items/abc"),
+        (
+            StatusCode::BAD_REQUEST,
+            "invalid value \"abc\" for 1".into()
+        )
+    );
+}
+
+#[cfg(feature = "extract")]
+#[test]
+fn extracts_queries_and_json() {
+    use serde::{Deserialize, Serialize};
+
+    use crate::testing;
+
+    #[derive(Deserialize, Serialize)]
+    struct Search {
+        q: String,
+        page: Option<u32>,
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get_extract(
+        r"/search",
+        |_: Request<Body>, Query(search): Query<Search>| Json(search),
+    );
+    let router = builder.finalize().unwrap();
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let response = router.handle(testing::get("/search?q=rust&page=2"));
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = testing::body(response);
+    assert_eq!(body, r#"{"q":"rust","page":2}"#);
+    assert_eq!(
+        router.handle(testing::get("/search?page=x")).status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let parsed = runtime.block_on(Json::<Search>::from_body(r#"{"q":"x"}"#.into()));
+    assert_eq!(parsed.unwrap().0.q, "x");
+    let rejected = runtime.block_on(Json::<Search>::from_body("{".into()));
+    assert_eq!(
+        rejected.err().map(|rejection| rejection.status()),
+        Some(StatusCode::UNPROCESSABLE_ENTITY)
+    );
+}
@@ -0,0 +1,213 @@
+use std::any::Any;
+use std::error::Error as StdError;
+use std::fmt;
+
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::body::RouteBody;
+
+/// An error returned by a fallible handler, such as one added with
+/// `RouterBuilder::route_fallible`.
+pub type HandlerError = Box<dyn StdError + Send + Sync>;
+
+// Carries a handler's error on its response until the router turns it into
+// the response that is sent.
+pub(crate) struct Failure(pub(crate) HandlerError);
+
+// The response for a handler that failed with `error`. Unless the router has
+// an `internal_error` handler this is what is sent.
+pub(crate) fn respond<B: RouteBody>(error: HandlerError) -> Response<B> {
+    let mut response = Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .body(Body::from("Internal Server Error").into())
+        .unwrap();
+    response.extensions_mut().insert(Failure(error));
+    response
+}
+
+/// The error a handler that panicked is treated as having failed with, when
+/// `RouterBuilder::catch_panics` is on. Error handlers can check for it with
+/// `downcast_ref` to log the panic.
+#[derive(Clone, Debug)]
+pub struct Panicked {
+    message: String,
+}
+
+impl Panicked {
+    // Describe a panic from its payload, which is usually the message.
+    pub(crate) fn new(payload: Box<dyn Any + Send>) -> Panicked {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast_ref::<&str>() {
+                Some(message) => (*message).to_owned(),
+                None => "Box<dyn Any>".to_owned(),
+            },
+        };
+        Panicked { message }
+    }
+
+    /// The message the handler panicked with.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "handler panicked: {}", self.message)
+    }
+}
+
+impl StdError for Panicked {}
+
+// A copy of the parts of a request an error handler is shown, taken before
+// the handler consumes it.
+pub(crate) fn head<B>(req: &Request<B>) -> Request<()> {
+    let mut head = Request::new(());
+    *head.method_mut() = req.method().clone();
+    *head.uri_mut() = req.uri().clone();
+    *head.version_mut() = req.version();
+    *head.headers_mut() = req.headers().clone();
+    head
+}
+
+#[test]
+fn maps_handler_errors() {
+    use std::io;
+
+    use crate::{testing, Captures, RouterBuilder};
+
+    fn show(_: Request<Body>, captures: Captures) -> Result<Response<Body>, HandlerError> {
+        let id: u64 = captures.get_as(1)?;
+        if id == 0 {
+            return Err(io::Error::other("database unavailable").into());
+        }
+        Ok(Response::new(Body::empty()))
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get_fallible(r"/users/(\d+)", show);
+    let router = builder.finalize().unwrap();
+    let response = router.handle(testing::get("/users/0"));
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.extensions().get::<Failure>().is_none());
+
+    let mut builder = RouterBuilder::new();
+    builder.get_fallible(r"/users/(\d+)", show);
+    builder.internal_error(|req, error| {
+        Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(format!("{} for {}", error, req.uri()).into())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+    assert_eq!(
+        router.handle(testing::get("/users/1")).status(),
+        StatusCode::OK
+    );
+    let response = router.handle(testing::get("/users/0"));
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = testing::body(response);
+    assert_eq!(body, "database unavailable for /users/0");
+}
+
+#[test]
+fn maps_error_types() {
+    use std::{fmt, io};
+
+    use crate::{testing, Captures, RouterBuilder};
+
+    #[derive(Debug)]
+    struct NotFound(u64);
+
+    impl fmt::Display for NotFound {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "no user {}", self.0)
+        }
+    }
+
+    impl StdError for NotFound {}
+
+    fn show(_: Request<Body>, captures: Captures) -> Result<Response<Body>, HandlerError> {
+        match captures.get_as(1)? {
+            0 => Err(io::Error::other("database unavailable").into()),
+            id => Err(NotFound(id).into()),
+        }
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get_fallible(r"/users/(\d+)", show);
+    builder.map_error(|_: &NotFound| StatusCode::GONE);
+    builder.map_error(|error: &NotFound| (StatusCode::NOT_FOUND, error.to_string()));
+    builder.internal_error(|_, _| StatusCode::SERVICE_UNAVAILABLE);
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/users/7"));
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = testing::body(response);
+    assert_eq!(body, "no user 7");
+    assert_eq!(
+        router.handle(testing::get("/users/0")).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+}
+
+#[test]
+fn renders_internal_errors() {
+    use std::io;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/broken", |_, _| -> Result<&str, io::Error> {
+        Err(io::Error::other("disk full"))
+    });
+    builder.not_found(|_, _| -> Result<&str, io::Error> {
+        Err(io::Error::other("templates missing"))
+    });
+    builder.internal_error(|req, error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("<h1>Sorry</h1> {} {}", req.uri(), error),
+        )
+    });
+    let router = builder.finalize().unwrap();
+
+    let body = |path| {
+        let response = router.handle(testing::get(path));
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.extensions().get::<Failure>().is_none());
+        testing::body(response)
+    };
+    assert_eq!(body("/broken"), "<h1>Sorry</h1> /broken disk full");
+    assert_eq!(
+        body("/missing"),
+        "<h1>Sorry</h1> /missing templates missing"
+    );
+}
+
+#[test]
+fn catches_panics() {
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.catch_panics(true);
+    builder.get(r"/users/(\d+)", |_, c| -> &str {
+        panic!("no user {}", &c[1])
+    });
+    builder.get(r"/ok", |_, _| "ok");
+    builder.internal_error(|_, error| match error.downcast_ref::<Panicked>() {
+        Some(panicked) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            panicked.message().to_owned(),
+        ),
+        None => (StatusCode::INTERNAL_SERVER_ERROR, String::new()),
+    });
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/users/7"));
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = testing::body(response);
+    assert_eq!(body, "no user 7");
+    assert_eq!(router.handle(testing::get("/ok")).status(), StatusCode::OK);
+}
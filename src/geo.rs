@@ -0,0 +1,72 @@
+use std::net::IpAddr;
+
+use hyper::Request;
+
+use crate::access_log::client_ip;
+
+/// The region a request came from, as named by the resolver given to
+/// `RouterBuilder::geo_resolver`. It is added to the request's extensions
+/// whenever the resolver knows the client's address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region(pub String);
+
+// Look up the region of the client that sent `req` and record it on the
+// request for routes and handlers.
+pub(crate) fn resolve<B, F>(resolver: F, req: &mut Request<B>)
+where
+    F: Fn(IpAddr) -> Option<String>,
+{
+    if let Some(region) = client_ip(req).and_then(resolver) {
+        req.extensions_mut().insert(Region(region));
+    }
+}
+
+// Whether `req` came from one of `regions`. Requests from unknown regions
+// never do.
+pub(crate) fn allowed<B>(regions: &[String], req: &Request<B>) -> bool {
+    req.extensions()
+        .get::<Region>()
+        .is_some_and(|Region(region)| regions.contains(region))
+}
+
+#[test]
+fn routes_by_region() {
+    use hyper::{Body, Response, StatusCode};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.geo_resolver(|ip| match ip.to_string().as_str() {
+        "192.0.2.1" => Some("eu".to_owned()),
+        _ => Some("us".to_owned()),
+    });
+    builder.route_with(
+        hyper::Method::GET,
+        r"/shop",
+        RouteOptions::new().regions(&["eu"]),
+        |req, _| {
+            let Region(region) = req.extensions().get::<Region>().unwrap();
+            Response::new(region.clone().into())
+        },
+    );
+    builder.get(r"/shop", |_, _| {
+        Response::builder()
+            .status(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS)
+            .body(Body::empty())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/shop");
+    req.headers_mut()
+        .insert("x-forwarded-for", "192.0.2.1".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+
+    let mut req = testing::get("/shop");
+    req.headers_mut()
+        .insert("x-forwarded-for", "198.51.100.1".parse().unwrap());
+    assert_eq!(
+        router.handle(req).status(),
+        StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+    );
+}
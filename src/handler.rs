@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use hyper::{Body, Method, Request, Response};
+
+use crate::body::RouteBody;
+use crate::{Captures, IntoResponse, RouteHandler, RouterBuilder};
+
+/// Something that can handle the requests routed to it, such as a struct
+/// that owns a connection pool and configuration.
+///
+/// Every function or closure taking a request and its captures is a
+/// handler, so there is no need to implement this for them. `route` and the
+/// methods named after verbs take functions, so that the argument types of
+/// closures can be left out, and other handlers are given to them with
+/// `into_route_handler`:
+///
+/// ```ignore
+/// builder.get(r"/users/(\d+)", UserHandler { pool }.into_route_handler());
+/// ```
+///
+/// Handlers are shared by every request, so state they change has to be
+/// kept behind a lock or atomic. An `Arc` of a handler is also a handler,
+/// letting one be installed on several routes or kept by the application,
+/// and `RouterBuilder::route_mut` installs closures that change their state
+/// directly.
+pub trait Handler<B = Body>: Send + Sync + 'static {
+    /// Respond to `req`, whose path was captured in `captures`.
+    fn handle(&self, req: Request<B>, captures: Captures) -> Response<B>;
+
+    /// The handler as a function, for `RouterBuilder::route` and the other
+    /// methods that install handlers.
+    fn into_route_handler(self) -> RouteHandler<B>
+    where
+        Self: Sized,
+    {
+        Box::new(move |req, captures| self.handle(req, captures))
+    }
+}
+
+impl<B, F, R> Handler<B> for F
+where
+    F: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+    R: IntoResponse<B>,
+{
+    fn handle(&self, req: Request<B>, captures: Captures) -> Response<B> {
+        self(req, captures).into_response()
+    }
+}
+
+impl<B, H: Handler<B> + ?Sized> Handler<B> for Arc<H> {
+    fn handle(&self, req: Request<B>, captures: Captures) -> Response<B> {
+        (**self).handle(req, captures)
+    }
+}
+
+impl<B: RouteBody> RouterBuilder<B> {
+    /// Install a handler that changes its own state, such as a counter, for
+    /// requests of method `verb` to paths matching `route`. The handler is
+    /// kept behind a lock, so it handles one request at a time.
+    pub fn route_mut<H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: FnMut(Request<B>, Captures) -> R + Send + 'static,
+        R: IntoResponse<B>,
+    {
+        let handler = Mutex::new(handler);
+        self.route(verb, route, move |req, captures| {
+            // A handler that panicked before is still called, as with any
+            // other handler.
+            let mut handler = handler.lock().unwrap_or_else(PoisonError::into_inner);
+            handler(req, captures).into_response()
+        })
+    }
+}
+
+#[test]
+fn routes_to_structs() {
+    use hyper::StatusCode;
+
+    use crate::testing;
+
+    struct Greeter {
+        greeting: String,
+    }
+
+    impl Handler for Greeter {
+        fn handle(&self, _: Request<Body>, captures: Captures) -> Response<Body> {
+            Response::new(format!("{}, {}", self.greeting, &captures[1]).into())
+        }
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get(
+        r"/hello/(\w+)",
+        Greeter {
+            greeting: "Hello".to_owned(),
+        }
+        .into_route_handler(),
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/hello/world"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = testing::body(response);
+    assert_eq!(body, "Hello, world");
+}
+
+#[test]
+fn routes_to_stateful_handlers() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::testing;
+
+    struct Counter(AtomicUsize);
+
+    impl Handler for Counter {
+        fn handle(&self, _: Request<Body>, _: Captures) -> Response<Body> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Response::new(count.to_string().into())
+        }
+    }
+
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/a", Arc::clone(&counter).into_route_handler());
+    builder.get(r"/b", Arc::clone(&counter).into_route_handler());
+    let mut visits = Vec::new();
+    builder.route_mut(Method::GET, r"/visit/(\w+)", move |_, c| {
+        visits.push(c[1].to_owned());
+        visits.join(",")
+    });
+    let router = builder.finalize().unwrap();
+
+    let body = |path| testing::body(router.handle(testing::get(path)));
+    assert_eq!(body("/a"), "1");
+    assert_eq!(body("/b"), "2");
+    assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+    assert_eq!(body("/visit/x"), "x");
+    assert_eq!(body("/visit/y"), "x,y");
+}
+
+#[test]
+fn functions_are_handlers() {
+    use hyper::StatusCode;
+
+    use crate::testing;
+
+    fn created(_: Request<Body>, captures: Captures) -> (StatusCode, String) {
+        (StatusCode::CREATED, captures[1].to_owned())
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.post(r"/users/(\w+)", created);
+    builder.get(
+        r"/ping",
+        Arc::new(|_: Request<Body>, _: Captures| "pong").into_route_handler(),
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::post("/users/ada", ""));
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(testing::body(response), "ada");
+    let response = router.handle(testing::get("/ping"));
+    assert_eq!(testing::body(response), "pong");
+}
@@ -0,0 +1,150 @@
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Response, StatusCode};
+
+use crate::json;
+
+type Check = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// A set of named checks served by `RouterBuilder::health_routes`.
+///
+/// Readiness checks decide whether the service should receive traffic (a
+/// database or cache ping, say) while liveness checks decide whether it
+/// should be restarted. Checks run on the request's thread, so they should
+/// be quick and bounded.
+#[derive(Default)]
+pub struct HealthChecks {
+    ready: Vec<(String, Check)>,
+    live: Vec<(String, Check)>,
+}
+
+// Which checks a probe route runs.
+#[derive(Clone, Copy)]
+pub(crate) enum Probe {
+    Health,
+    Ready,
+    Live,
+}
+
+impl HealthChecks {
+    /// Create an empty set of checks. With no checks every probe reports ok.
+    pub fn new() -> HealthChecks {
+        HealthChecks::default()
+    }
+
+    /// Add a check that is run for `/readyz` and `/healthz`.
+    pub fn ready<F>(&mut self, name: &str, check: F) -> &mut HealthChecks
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.ready.push((name.to_owned(), Box::new(check)));
+        self
+    }
+
+    /// Add a check that is run for `/livez` and `/healthz`.
+    pub fn live<F>(&mut self, name: &str, check: F) -> &mut HealthChecks
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.live.push((name.to_owned(), Box::new(check)));
+        self
+    }
+
+    // Run the checks for `probe` and render them as a JSON document. The
+    // status is 503 if any check failed so orchestrators can act on it.
+    pub(crate) fn respond(&self, probe: Probe) -> Response<Body> {
+        let checks: Vec<&(String, Check)> = match probe {
+            Probe::Health => self.ready.iter().chain(&self.live).collect(),
+            Probe::Ready => self.ready.iter().collect(),
+            Probe::Live => self.live.iter().collect(),
+        };
+
+        let mut healthy = true;
+        let mut body = String::from("{\"checks\":{");
+        for (i, (name, check)) in checks.into_iter().enumerate() {
+            if i > 0 {
+                body.push(',');
+            }
+            json::string(&mut body, name);
+            match check() {
+                Ok(()) => body.push_str(":{\"status\":\"ok\"}"),
+                Err(error) => {
+                    healthy = false;
+                    body.push_str(":{\"status\":\"error\",\"error\":");
+                    json::string(&mut body, &error);
+                    body.push('}');
+                }
+            }
+        }
+        body.push_str("},\"status\":");
+        body.push_str(if healthy { "\"ok\"}" } else { "\"error\"}" });
+
+        let status = if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap()
+    }
+}
+
+#[test]
+fn failing_check_is_unavailable() {
+    let mut checks = HealthChecks::new();
+    checks.ready("db", || Err("connection refused".to_owned()));
+    checks.live("loop", || Ok(()));
+
+    assert_eq!(
+        checks.respond(Probe::Ready).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        checks.respond(Probe::Health).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(checks.respond(Probe::Live).status(), StatusCode::OK);
+}
+
+#[test]
+fn status_follows_each_run() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use crate::{testing, RouterBuilder};
+
+    let up = Arc::new(AtomicBool::new(true));
+    let mut checks = HealthChecks::new();
+    {
+        let up = Arc::clone(&up);
+        checks.ready("db", move || {
+            if up.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err("connection refused".to_owned())
+            }
+        });
+    }
+    let mut builder = RouterBuilder::new();
+    builder.health_routes(checks);
+    let router = builder.finalize().unwrap();
+
+    // The very first probe already reports the checks it ran.
+    let response = router.handle(testing::get("/readyz"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = testing::body(response);
+    assert_eq!(body, r#"{"checks":{"db":{"status":"ok"}},"status":"ok"}"#);
+
+    up.store(false, Ordering::SeqCst);
+    assert_eq!(
+        router.handle(testing::get("/readyz")).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    up.store(true, Ordering::SeqCst);
+    assert_eq!(
+        router.handle(testing::get("/healthz")).status(),
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,172 @@
+use hyper::header::HOST;
+use hyper::{Body, Method, Request};
+
+use crate::body::RouteBody;
+use crate::{Captures, IntoResponse, RouteOptions, RouterBuilder};
+
+/// Adds routes that only match requests for one virtual host, returned by
+/// `RouterBuilder::host`.
+pub struct HostRoutes<'a, B = Body> {
+    builder: &'a mut RouterBuilder<B>,
+    host: String,
+}
+
+impl<'a, B: RouteBody> HostRoutes<'a, B> {
+    pub(crate) fn new(builder: &'a mut RouterBuilder<B>, host: &str) -> HostRoutes<'a, B> {
+        HostRoutes {
+            builder,
+            host: host.to_owned(),
+        }
+    }
+
+    /// Install a handler for requests to this host with `verb` and a path
+    /// matching `route`.
+    pub fn route<H, R>(&mut self, verb: Method, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new(), handler)
+    }
+
+    /// Install a handler like `route` but with settings specific to this
+    /// route.
+    pub fn route_with<H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        options: RouteOptions<B>,
+        handler: H,
+    ) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        let options = options.host(&self.host);
+        self.builder.route_with(verb, route, options, handler);
+        self
+    }
+
+    /// Convenience method to install a GET handler for this host.
+    pub fn get<H, R>(&mut self, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::GET, route, handler)
+    }
+
+    /// Convenience method to install a POST handler for this host.
+    pub fn post<H, R>(&mut self, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::POST, route, handler)
+    }
+
+    /// Convenience method to install a PUT handler for this host.
+    pub fn put<H, R>(&mut self, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::PUT, route, handler)
+    }
+
+    /// Convenience method to install a PATCH handler for this host.
+    pub fn patch<H, R>(&mut self, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::PATCH, route, handler)
+    }
+
+    /// Convenience method to install a DELETE handler for this host.
+    pub fn delete<H, R>(&mut self, route: &str, handler: H) -> &mut HostRoutes<'a, B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::DELETE, route, handler)
+    }
+}
+
+// The host a request was sent to without its port, from the `Host` header
+// or, for HTTP/2 requests, the URI.
+pub(crate) fn of<B>(req: &Request<B>) -> Option<&str> {
+    let host = match req.headers().get(HOST) {
+        Some(host) => host.to_str().ok()?,
+        None => req.uri().host()?,
+    };
+    // Leave the brackets around IPv6 addresses alone.
+    match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => Some(&host[..colon]),
+        _ => Some(host),
+    }
+}
+
+// Whether `req` was sent to `host`. Host names are case insensitive.
+pub(crate) fn allowed<B>(host: &str, req: &Request<B>) -> bool {
+    of(req).is_some_and(|sent| sent.eq_ignore_ascii_case(host))
+}
+
+#[test]
+fn routes_by_host() {
+    use hyper::{Response, StatusCode};
+
+    use crate::testing;
+
+    let mut builder = RouterBuilder::new();
+    builder
+        .host("api.example.com")
+        .get(r"/", |_, _| Response::new("api".into()));
+    builder.get(r"/", |_, _| Response::new("www".into()));
+    builder
+        .host("admin.example.com")
+        .get(r"/users", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let send = |host: &str, path: &str| {
+        let mut req = testing::get(path);
+        req.headers_mut().insert(HOST, host.parse().unwrap());
+        let response = router.handle(req);
+        let status = response.status();
+        let body = testing::body(response);
+        (status, body)
+    };
+    assert_eq!(send("API.example.com:8080", "/").1, "api");
+    assert_eq!(send("www.example.com", "/").1, "www");
+    assert_eq!(send("admin.example.com", "/users").0, StatusCode::OK);
+    assert_eq!(send("www.example.com", "/users").0, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn captures_subdomains() {
+    use hyper::{Response, StatusCode};
+
+    use crate::testing;
+
+    let mut builder = RouterBuilder::new();
+    builder.get_host(
+        r"(?P<tenant>[a-z0-9]+)\.example\.com",
+        r"/projects/(\d+)",
+        |_, c| {
+            assert_eq!(c.name("tenant"), Some("acme"));
+            assert_eq!(&c[1], "7");
+            assert_eq!(&c[2], "acme");
+            Response::new(Body::empty())
+        },
+    );
+    let router = builder.finalize().unwrap();
+
+    let send = |host: &str| {
+        let mut req = testing::get("/projects/7");
+        req.headers_mut().insert(HOST, host.parse().unwrap());
+        router.handle(req).status()
+    };
+    assert_eq!(send("acme.example.com:443"), StatusCode::OK);
+    assert_eq!(send("acme.example.org"), StatusCode::NOT_FOUND);
+    assert_eq!(send("a.b.example.com"), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,67 @@
+use std::fmt::Write;
+
+// Write `value` as a quoted JSON string. This is enough for the small,
+// fixed-shape documents the router produces without needing a serializer.
+pub(crate) fn string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Encode `value` as a JSON string.
+pub(crate) fn quoted(value: &str) -> String {
+    let mut out = String::new();
+    string(&mut out, value);
+    out
+}
+
+// Encode `value` as a JSON string or null.
+pub(crate) fn optional(value: Option<&str>) -> String {
+    value.map_or("null".to_owned(), quoted)
+}
+
+// Write a JSON array from values that are already encoded, or numbers.
+pub(crate) fn array<T, I>(values: I) -> String
+where
+    T: ToString,
+    I: Iterator<Item = T>,
+{
+    let values: Vec<String> = values.map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+// Write a JSON object from keys and already encoded values.
+pub(crate) fn object<'a, I>(pairs: I) -> String
+where
+    I: Iterator<Item = (&'a str, String)>,
+{
+    let mut out = String::from("{");
+    for (i, (key, value)) in pairs.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        string(&mut out, key);
+        let _ = write!(out, ":{}", value);
+    }
+    out.push('}');
+    out
+}
+
+#[test]
+fn escapes_strings() {
+    let mut out = String::new();
+    string(&mut out, "a \"b\"\\\n\u{1}");
+    assert_eq!(out, r#""a \"b\"\\\n\u0001""#);
+}
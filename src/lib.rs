@@ -1,207 +1,3036 @@
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::error::Error as StdError;
+use std::iter::FromIterator;
+use std::net::IpAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use hyper::body::HttpBody;
+use hyper::header::{HeaderValue, ALLOW, CONTENT_LENGTH};
 use hyper::Method;
 use hyper::{Body, Request, Response, StatusCode};
-use regex::{Regex, RegexSet};
-use smallvec::SmallVec;
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+use admin::AdminEndpoint;
+use body::RouteBody;
+use decision::Sampler;
+use failure::Failure;
+use health::Probe;
+use metrics::Metrics;
+use split::Split;
+use trap::Traps;
 
+#[doc(hidden)]
+pub use macros::__duplicate_route;
+
+pub use access_log::{AccessLogEntry, JsonAccessLog};
+pub use admin::Admin;
+pub use canary::Canary;
+pub use captures::{CaptureError, Captures};
+pub use client_hints::ClientHints;
+#[cfg(feature = "config")]
+pub use config::HandlerRegistry;
+pub use deadline::Deadline;
+pub use decision::{MatchResult, Outcome, RouteMatch, RoutingDecision};
+pub use deprecation::{DeprecatedUse, Deprecation};
+pub use device::Device;
+pub use display::RouteInfo;
 pub use error::Error;
+pub use experiment::{Experiment, Variant};
+pub use failure::{HandlerError, Panicked};
+pub use geo::Region;
+pub use handler::Handler;
+pub use health::HealthChecks;
+pub use host::HostRoutes;
+pub use limits::{BodyTimeout, BodyTooLarge};
+pub use method_override::OverriddenMethod;
+pub use metrics::{Drained, LatencyHistogram, RouteMetrics, RouterMetrics};
+pub use openapi::OpenApi;
+pub use options::RouteOptions;
+pub use problem::Problem;
+pub use quota::{MemoryQuotaStore, Quota, QuotaStore, QuotaUsage};
+pub use record::{Recorder, Recording};
+#[cfg(feature = "reports")]
+pub use report_collector::{Report, ReportCollector};
+pub use reports::Reporting;
+#[cfg(feature = "macros")]
+pub use reroute_macros::{collect_routes, delete, get, options, patch, post, put};
+pub use resource::Resource;
+pub use response::IntoResponse;
+pub use scheme::SchemePolicy;
+pub use shadow::Shadowing;
+pub use sitemap::Sitemap;
+pub use slo::{Slo, SloAlert, SloStatus};
+pub use slow::{SlowRequest, REDACTED};
+pub use swap::SwappableRouter;
+pub use trace_context::TraceContext;
+pub use trap::TrapHit;
+pub use unmatched::{Unmatched, UnmatchedKind};
+pub use version::VersionScope;
+
+// The attribute macros refer to this crate by name, which needs an alias
+// when they are used inside it.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as reroute;
 
+#[macro_use]
+mod macros;
+
+mod access_log;
+mod admin;
+mod bandwidth;
+pub mod body;
+mod canary;
+mod captures;
+mod client_hints;
+#[cfg(feature = "config")]
+mod config;
+mod deadline;
+mod decision;
+mod deprecation;
+mod device;
+mod display;
 mod error;
+mod example;
+mod experiment;
+pub mod extract;
+mod failure;
+mod geo;
+mod handler;
+mod health;
+mod host;
+mod json;
+mod limits;
+mod method_override;
+mod metrics;
+mod negotiate;
+mod openapi;
+mod options;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod path;
+pub mod patterns;
+mod problem;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod quota;
+mod record;
+mod redirect;
+#[cfg(feature = "reports")]
+mod report_collector;
+mod reports;
+mod resource;
+mod response;
+mod scheme;
+mod shadow;
+mod sitemap;
+mod slo;
+mod slow;
+mod split;
+pub mod stream;
+mod swap;
+pub mod testing;
+#[cfg(feature = "tracing")]
+mod trace;
+mod trace_context;
+mod trap;
+mod unmatched;
+mod url;
+mod version;
+
+/// A boxed handler, for keeping handlers of different types together such as
+/// when generating routes to add with `RouterBuilder::extend`.
+pub type RouteHandler<B = Body> = Box<dyn Fn(Request<B>, Captures) -> Response<B> + Send + Sync>;
+type MethodNotAllowedHandler<B> = Box<dyn Fn(Request<B>, &[Method]) -> Response<B> + Send + Sync>;
+type ErrorHandler<B> =
+    Box<dyn Fn(&Request<()>, &(dyn StdError + Send + Sync + 'static)) -> Response<B> + Send + Sync>;
+type ErrorMapper<B> =
+    Box<dyn Fn(&(dyn StdError + Send + Sync + 'static)) -> Option<Response<B>> + Send + Sync>;
+type NotAcceptableHandler<B> = Box<dyn Fn(Request<B>, &[&str]) -> Response<B> + Send + Sync>;
+type UnmatchedHook = Box<dyn Fn(&Unmatched) + Send + Sync>;
+type SlowRequestHook = Box<dyn Fn(&SlowRequest) + Send + Sync>;
+type SloHook = Box<dyn Fn(&SloAlert) + Send + Sync>;
+type TrapHook = Box<dyn Fn(&TrapHit) + Send + Sync>;
+type DeprecatedHook = Box<dyn Fn(&DeprecatedUse) + Send + Sync>;
+type UnreachableHook = Box<dyn Fn(&Shadowing) + Send + Sync>;
+type DeviceClassifier<B> = Box<dyn Fn(&Request<B>) -> Device + Send + Sync>;
+type GeoResolver = Box<dyn Fn(IpAddr) -> Option<String> + Send + Sync>;
+type AccessHook = Box<dyn Fn(&AccessLogEntry) + Send + Sync>;
+type DecisionHook = Box<dyn Fn(&RoutingDecision) + Send + Sync>;
+type MetricsSlot = Arc<OnceLock<Arc<Metrics>>>;
 
-pub type Captures<'r> = Option<SmallVec<[&'r str; 4]>>;
-type RouteHandler = Box<dyn Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync>;
+// Items used by the exported macros, which can't rely on the caller's imports.
+#[doc(hidden)]
+pub mod __private {
+    pub use hyper::Method;
+}
 
 /// The Router struct contains the information for your app to route requests
 /// properly based on their HTTP method and matching route. It allows the use
 /// of a custom 404 handler if desired but provides a default as well.
 ///
-/// Under the hood a Router uses a `RegexSet` to match URI's that come in to the
-/// instance of the hyper server. Because of this, it has the potential to match
-/// multiple patterns that you provide. It will call the first handler that it
-/// matches against so the order in which you add routes matters.
-pub struct Router {
+/// Under the hood a Router uses a `RegexSet` to match the paths of URI's that
+/// come in to the instance of the hyper server, leaving out the query string
+/// unless `RouterBuilder::match_full_uri` is set. Because of this, it has the
+/// potential to match multiple patterns that you provide. It will call the
+/// first handler that it matches against so the order in which you add routes
+/// matters, unless they are given priorities with `RouteOptions::priority`.
+///
+/// When a path matches routes for other methods only, the request is
+/// answered with `405 Method Not Allowed` and an `Allow` header listing them.
+/// `OPTIONS` requests for such paths get `204 No Content` with the same
+/// header, unless an `OPTIONS` route matches.
+///
+/// Requests and responses have a `hyper::Body` unless the router was built
+/// for another `RouteBody`.
+pub struct Router<B = Body> {
     routes: RegexSet,
+    // The routes using each pattern in the set, since routes for different
+    // methods often share one.
+    set_routes: Vec<Vec<usize>>,
+    // The routes matching each path that some route matches literally, so
+    // those paths skip the regex engine.
+    literals: HashMap<String, Vec<usize>>,
     patterns: Vec<Regex>,
-    handlers: Vec<(Method, RouteHandler)>,
-    not_found: RouteHandler,
+    // The compiled `RouteOptions::host_pattern` of each route.
+    host_patterns: Vec<Option<Regex>>,
+    // The patterns as they were registered, before anchoring.
+    sources: Vec<String>,
+    handlers: Vec<(Method, RouteHandler<B>)>,
+    options: Vec<RouteOptions<B>>,
+    // Routes can be switched off while the router is running.
+    enabled: Vec<AtomicBool>,
+    not_found: RouteHandler<B>,
+    disabled: Option<RouteHandler<B>>,
+    method_not_allowed: MethodNotAllowedHandler<B>,
+    not_acceptable: NotAcceptableHandler<B>,
+    internal_error: Option<ErrorHandler<B>>,
+    error_mappers: Vec<(TypeId, ErrorMapper<B>)>,
+    problem_details: bool,
+    catch_panics: bool,
+    traps: Option<Traps<B>>,
+    reporting: Option<Reporting>,
+    deadlines: bool,
+    normalize_trailing_slash: bool,
+    redirect_trailing_slash: bool,
+    match_full_uri: bool,
+    head_fallback: bool,
+    method_override: bool,
+    scheme_policy: SchemePolicy,
+    on_unmatched: Option<UnmatchedHook>,
+    slow_threshold: Option<Duration>,
+    on_slow_request: Option<SlowRequestHook>,
+    on_decision: Option<(Sampler, DecisionHook)>,
+    on_access: Option<AccessHook>,
+    on_slo_burn: Option<SloHook>,
+    on_deprecated_use: Option<DeprecatedHook>,
+    geo_resolver: Option<GeoResolver>,
+    device_classifier: Option<DeviceClassifier<B>>,
+    experiment: Option<Experiment>,
+    metrics: Arc<Metrics>,
 }
 
-impl Router {
+impl<B: RouteBody> Router<B> {
     /// This function should be called inside of a hyper service. It will find the correct handler
     /// for the given route and handle errors appropriately.
-    pub fn handle(&self, req: Request<Body>) -> Response<Body> {
+    ///
+    /// The router never collects request or response bodies. The handler is
+    /// called as soon as a request is routed and bodies stream through as
+    /// chunks arrive, so a slow reader holds back the client in turn. Route
+    /// settings that look at bodies, like recording or counting bytes, only
+    /// wrap them, and a `Recorder` keeps no more than its `max_body` of each.
+    /// The `stream` module helps handlers respond with bodies that are still
+    /// being produced.
+    pub fn handle(&self, req: Request<B>) -> Response<B> {
+        let access = self
+            .on_access
+            .as_ref()
+            .map(|hook| (hook, access_log::Pending::new(&req)));
+
+        #[cfg(feature = "tracing")]
+        let (routed, mut response) = trace::instrument(req, |req| self.route_request(req));
+        #[cfg(not(feature = "tracing"))]
+        let (routed, mut response) = self.route_request(req);
+        if let Some(reporting) = &self.reporting {
+            reporting.apply(response.headers_mut());
+        }
+
+        if let Some((hook, pending)) = access {
+            let route = routed.map(|index| self.sources[index].as_str());
+            hook(&pending.finish(route, &response));
+        }
+        response
+    }
+
+    // Find and call the handler for a request, returning the index of the
+    // route that handled it along with the response.
+    fn route_request(&self, mut req: Request<B>) -> (Option<usize>, Response<B>) {
+        let received = Instant::now();
+        if self.method_override {
+            method_override::apply(&mut req);
+        }
+        // Error handlers see a copy of the request's head.
+        let head =
+            (self.internal_error.is_some() || self.problem_details).then(|| failure::head(&req));
+        let (routed, mut response) = self.route_to(req, head.as_ref(), received);
+        // Fallback handlers such as the not found handler can fail too.
+        self.recover(&mut response, head.as_ref());
+        (routed, response)
+    }
+
+    fn route_to(
+        &self,
+        mut req: Request<B>,
+        req_head: Option<&Request<()>>,
+        received: Instant,
+    ) -> (Option<usize>, Response<B>) {
         // It should be cheaper to clone this than making an owned string of the path.
         let uri = req.uri().clone();
-        let uri = uri.path();
-        let matches = self.routes.matches(uri);
-        if !matches.matched_any() {
-            return (self.not_found)(req, None);
+        // Routes match the path alone unless they were asked to see the query.
+        let uri = match uri.path_and_query() {
+            Some(full) if self.match_full_uri => full.as_str(),
+            _ => uri.path(),
+        };
+        if let Some((sampler, hook)) = &self.on_decision {
+            if sampler.sample() {
+                hook(&self.explain(req.method(), uri));
+            }
+        }
+
+        if let Some(resolver) = &self.geo_resolver {
+            geo::resolve(resolver, &mut req);
+        }
+        if let Some(classifier) = &self.device_classifier {
+            let device = classifier(&req);
+            req.extensions_mut().insert(device);
+        }
+        if let Some(experiment) = &self.experiment {
+            experiment.assign(&mut req);
+        }
+
+        if let Some(traps) = &self.traps {
+            if let Some(trap) = traps.matched(uri) {
+                return (None, traps.spring(trap, req, uri, &self.not_found));
+            }
+        }
+
+        let (matches, matched) = self.matches(uri);
+        if matches.is_empty() {
+            return (None, self.unmatched_route(req, matched));
+        }
+        if self.redirect_trailing_slash && matched.len() < uri.len() {
+            let query = req.uri().query().filter(|_| !self.match_full_uri);
+            return (None, redirect::canonical(matched, query).map(B::from));
+        }
+        let uri = matched;
+
+        let method = self.routed_method(req.method(), &matches);
+        let head = method != req.method();
+
+        // Routes whose conditions turn a request away leave it unmatched
+        // rather than with the wrong method.
+        let mut turned_away = false;
+        let mut disabled = false;
+        // The media types of routes passed over because of `Accept`.
+        let mut unacceptable = Vec::new();
+        // Whether routes were passed over because of `Content-Type`.
+        let mut unsupported = false;
+        for &index in &matches {
+            if !accepts(&self.handlers[index].0, &method) {
+                continue;
+            }
+            if !self.consumes(index, &req) {
+                unsupported = true;
+                continue;
+            }
+            if !self.admits(index, &req) {
+                turned_away = true;
+                continue;
+            }
+            if !self.enabled[index].load(Ordering::Relaxed) {
+                disabled = true;
+                continue;
+            }
+            let index = match self.options[index].produces {
+                Some(ref media) => match self.negotiate(index, &matches, &req) {
+                    Some(best) => best,
+                    None => {
+                        if !unacceptable.contains(&media.as_str()) {
+                            unacceptable.push(media.as_str());
+                        }
+                        continue;
+                    }
+                },
+                None => index,
+            };
+
+            let host = self.host_patterns[index]
+                .as_ref()
+                .and(host::of(&req))
+                .map(str::to_owned);
+            let mut captures = Captures::new(&self.patterns[index], uri);
+            if let (Some(pattern), Some(host)) = (&self.host_patterns[index], &host) {
+                captures.extend(pattern, host);
+            }
+            let mut response = self.dispatch(index, req, captures, req_head, received);
+            if head {
+                strip_body(&mut response);
+            }
+            return (Some(index), response);
+        }
+        if unsupported {
+            return (None, unsupported_media_type());
+        }
+        if !unacceptable.is_empty() {
+            return (None, (self.not_acceptable)(req, &unacceptable));
+        }
+        if disabled {
+            if let Some(handler) = &self.disabled {
+                return (None, handler(req, Captures::default()));
+            }
+        }
+        if turned_away || disabled {
+            return (None, self.unmatched_route(req, uri));
         }
 
-        for index in matches {
-            let (ref method, ref handler) = self.handlers[index];
-            if method != req.method() {
+        let allowed = self.allowed_methods(&matches);
+        if req.method() == Method::OPTIONS {
+            return (None, options_response(&allowed));
+        }
+
+        self.metrics.record_method_not_allowed();
+        if let Some(hook) = &self.on_unmatched {
+            hook(&Unmatched {
+                kind: UnmatchedKind::MethodNotAllowed,
+                method: req.method(),
+                path: uri,
+                nearest: matches.iter().map(|&i| self.sources[i].as_str()).collect(),
+            });
+        }
+        let mut response = (self.method_not_allowed)(req, &allowed);
+        if !response.headers().contains_key(ALLOW) {
+            if let Ok(value) = HeaderValue::from_str(&allow(&allowed)) {
+                response.headers_mut().insert(ALLOW, value);
+            }
+        }
+        (None, response)
+    }
+
+    // Pick the route the request prefers among those for the same method and
+    // pattern as the route at `index` that produce different media types.
+    fn negotiate(&self, index: usize, matches: &[usize], req: &Request<B>) -> Option<usize> {
+        let mut best = None;
+        for &variant in matches {
+            let media = match self.options[variant].produces {
+                Some(ref media) => media,
+                None => continue,
+            };
+            if self.handlers[variant].0 != self.handlers[index].0
+                || self.sources[variant] != self.sources[index]
+                || !self.consumes(variant, req)
+                || !self.admits(variant, req)
+            {
                 continue;
             }
+            let quality = negotiate::quality(req, media);
+            if quality > 0.0 && best.is_none_or(|(_, most)| quality > most) {
+                best = Some((variant, quality));
+            }
+        }
+        best.map(|(variant, _)| variant)
+    }
+
+    // The methods accepted by the routes in `matches`, for `Allow` headers.
+    // OPTIONS is always allowed since the router answers it itself.
+    fn allowed_methods(&self, matches: &[usize]) -> Vec<Method> {
+        let mut allowed = Vec::new();
+        let mut allow = |method: &Method| {
+            if !allowed.contains(method) {
+                allowed.push(method.clone());
+            }
+        };
+        for &index in matches {
+            let method = &self.handlers[index].0;
+            if method.as_str() == ANY_METHOD {
+                let every = [
+                    Method::GET,
+                    Method::HEAD,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                ];
+                every.iter().for_each(&mut allow);
+            } else {
+                allow(method);
+            }
+            if self.head_fallback && *method == Method::GET {
+                allow(&Method::HEAD);
+            }
+        }
+        allow(&Method::OPTIONS);
+        allowed
+    }
+
+    // The method to route a request with. With the HEAD fallback on, HEAD
+    // requests to paths with no HEAD route are routed like GET requests.
+    fn routed_method(&self, method: &Method, matches: &[usize]) -> Method {
+        if self.head_fallback
+            && method == Method::HEAD
+            && !matches
+                .iter()
+                .any(|&index| accepts(&self.handlers[index].0, method))
+        {
+            Method::GET
+        } else {
+            method.clone()
+        }
+    }
+
+    // Match `path` against the routes, returning the indexes of those that
+    // matched in order and the path that was matched. When trailing slashes
+    // are normalized or redirected a path that matches nothing is tried again
+    // without its trailing slash.
+    fn matches<'p>(&self, path: &'p str) -> (Vec<usize>, &'p str) {
+        let matches = self.matching_routes(path);
+        let retry = self.normalize_trailing_slash || self.redirect_trailing_slash;
+        if !matches.is_empty() || !retry || path.len() < 2 {
+            return (matches, path);
+        }
+        match path.strip_suffix('/') {
+            Some(trimmed) => (self.matching_routes(trimmed), trimmed),
+            None => (matches, path),
+        }
+    }
+
+    fn matching_routes(&self, path: &str) -> Vec<usize> {
+        if let Some(matches) = self.literals.get(path) {
+            return matches.clone();
+        }
+        let mut matches: Vec<usize> = self
+            .routes
+            .matches(path)
+            .iter()
+            .flat_map(|pattern| self.set_routes[pattern].iter().copied())
+            .collect();
+        matches.sort_unstable();
+        matches
+    }
+
+    fn unmatched_route(&self, req: Request<B>, uri: &str) -> Response<B> {
+        self.metrics.record_not_found();
+        if let Some(hook) = &self.on_unmatched {
+            hook(&Unmatched {
+                kind: UnmatchedKind::NotFound,
+                method: req.method(),
+                path: uri,
+                nearest: unmatched::nearest(&self.sources, uri),
+            });
+        }
+        (self.not_found)(req, Captures::default())
+    }
+
+    // Whether the conditions on the route at `index` allow it to handle `req`.
+    fn admits(&self, index: usize, req: &Request<B>) -> bool {
+        let options = &self.options[index];
+        if let Some(host) = &options.host {
+            if !host::allowed(host, req) {
+                return false;
+            }
+        }
+        if let Some(variant) = &options.variant {
+            if !experiment::allowed(variant, req) {
+                return false;
+            }
+        }
+        if let Some(scheme) = &options.scheme {
+            if self.scheme_policy.scheme(req) != *scheme {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.host_patterns[index] {
+            if !host::of(req).is_some_and(|host| pattern.is_match(host)) {
+                return false;
+            }
+        }
+        if !options.guards.iter().all(|guard| guard.admits(req)) {
+            return false;
+        }
+        if let Some(regions) = &options.regions {
+            if !geo::allowed(regions, req) {
+                return false;
+            }
+        }
+        if let Some(devices) = &options.devices {
+            if !req
+                .extensions()
+                .get::<Device>()
+                .is_some_and(|device| devices.contains(device))
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Whether the route at `index` takes the request's body, if it only
+    // takes some media types.
+    fn consumes(&self, index: usize, req: &Request<B>) -> bool {
+        match self.options[index].consumes {
+            Some(ref media) => negotiate::consumes(req, media),
+            None => true,
+        }
+    }
+
+    // Turn the response of a handler that failed into the one to send, with
+    // the first of the error mappings, error handler and problem details
+    // that applies. `head` is the copy of the request taken for them.
+    fn recover(&self, response: &mut Response<B>, head: Option<&Request<()>>) {
+        if let Some(Failure(error)) = response.extensions_mut().remove() {
+            let mapped = self.error_mappers.iter().find_map(|(_, map)| map(&*error));
+            if let Some(mapped) = mapped {
+                *response = mapped;
+            } else if let (Some(handler), Some(head)) = (&self.internal_error, head) {
+                *response = handler(head, &*error);
+            } else if let Some(head) = head.filter(|_| self.problem_details) {
+                *response = problem::internal_error(head);
+            }
+        }
+    }
+
+    // Run the handler for the route at `index`, recording metrics and tracing around it.
+    fn dispatch(
+        &self,
+        index: usize,
+        mut req: Request<B>,
+        captures: Captures,
+        head: Option<&Request<()>>,
+        received: Instant,
+    ) -> Response<B> {
+        if let Some(context) = TraceContext::from_headers(req.headers()) {
+            req.extensions_mut().insert(context);
+        }
+
+        for data in &self.options[index].data {
+            data.insert(req.extensions_mut());
+        }
+
+        let hints = &self.options[index].client_hints;
+        if !hints.is_empty() {
+            let parsed = ClientHints::from_headers(req.headers());
+            req.extensions_mut().insert(parsed);
+        }
+
+        let deadline = if self.deadlines {
+            Deadline::from_headers(req.headers())
+        } else {
+            None
+        };
+        // The route's timeout brings the deadline forward.
+        let timeout = self.options[index].timeout;
+        let deadline = match (
+            deadline,
+            timeout.map(|timeout| Deadline::at(received + timeout)),
+        ) {
+            (Some(deadline), Some(route)) => Some(deadline.min(route)),
+            (deadline, None) | (None, deadline) => deadline,
+        };
+        // The body has to arrive by the earlier of the route's body timeout
+        // and the request's deadline.
+        let body_deadline = match (self.options[index].body_timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some((received + timeout).min(deadline.instant())),
+            (Some(timeout), None) => Some(received + timeout),
+            (None, deadline) => deadline.map(|deadline| deadline.instant()),
+        };
+        if let Some(deadline) = deadline {
+            req.extensions_mut().insert(deadline);
+        }
+        if let Some(at) = body_deadline {
+            req = req.map(|body| B::from(limits::timeout(body.into_hyper(), at)));
+        }
+        let max_body_size = self.options[index].max_body_size;
+        if let Some(max) = max_body_size {
+            req = req.map(|body| B::from(limits::limit(body.into_hyper(), max)));
+        }
+
+        let capture = self.options[index]
+            .recorder
+            .as_ref()
+            .and_then(|recorder| recorder.start(&mut req));
+
+        let deprecated = self.options[index].deprecation.as_ref();
+        if let (Some(_), Some(hook)) = (deprecated, &self.on_deprecated_use) {
+            hook(&DeprecatedUse {
+                method: req.method(),
+                path: req.uri().path(),
+                route: &self.sources[index],
+                user_agent: deprecation::user_agent(&req),
+            });
+        }
+
+        // Only hold on to what a slow request report needs if one could be made.
+        let slow = match (&self.on_slow_request, self.slow_threshold(index)) {
+            (Some(hook), Some(threshold)) => Some((
+                hook,
+                threshold,
+                req.method().clone(),
+                req.uri().path().to_owned(),
+                captures.clone(),
+            )),
+            _ => None,
+        };
+
+        #[cfg(feature = "tracing")]
+        trace::record_route(&self.sources[index]);
+        #[cfg(feature = "opentelemetry")]
+        let cx = otel::start(&req, &self.sources[index]);
+
+        let usage = self.options[index]
+            .quota
+            .as_ref()
+            .and_then(|quota| quota.count(&req));
+
+        let start = Instant::now();
+        let mut response = {
+            let _in_flight = self.metrics.start(index);
+            #[cfg(feature = "opentelemetry")]
+            let _attached = cx.clone().attach();
+            let handler = match &self.options[index].canary {
+                Some(canary) => canary.select(&req),
+                None => None,
+            };
+            if deadline.is_some_and(|deadline| deadline.expired()) {
+                gateway_timeout()
+            } else if max_body_size.is_some_and(|max| limits::too_large(req.headers(), max)) {
+                payload_too_large()
+            } else if let Some(usage) = usage.as_ref().filter(|usage| usage.exceeded()) {
+                quota::exceeded(usage).map(B::from)
+            } else {
+                let handler = handler.unwrap_or(&self.handlers[index].1);
+                let mut response = if self.catch_panics {
+                    panic::catch_unwind(AssertUnwindSafe(|| handler(req, captures))).unwrap_or_else(
+                        |payload| failure::respond(Box::new(Panicked::new(payload))),
+                    )
+                } else {
+                    handler(req, captures)
+                };
+                self.recover(&mut response, head);
+                response
+            }
+        };
+        let elapsed = start.elapsed();
+        if let Some(usage) = &usage {
+            quota::apply(usage, response.headers_mut());
+        }
+        if let Some(status) = self.metrics.record(index, response.status(), elapsed) {
+            if let Some(hook) = &self.on_slo_burn {
+                hook(&SloAlert {
+                    method: &self.handlers[index].0,
+                    route: &self.sources[index],
+                    status,
+                });
+            }
+        }
+
+        if let Some((hook, threshold, method, path, captures)) = slow {
+            let total = received.elapsed();
+            if total > threshold {
+                let captures = if self.options[index].redact_captures {
+                    captures.iter().map(|_| REDACTED).collect()
+                } else {
+                    captures.to_vec()
+                };
+                hook(&SlowRequest {
+                    method: &method,
+                    path: &path,
+                    route: &self.sources[index],
+                    captures,
+                    threshold,
+                    routing: total - elapsed,
+                    handler: elapsed,
+                    total,
+                });
+            }
+        }
+
+        #[cfg(feature = "opentelemetry")]
+        otel::finish(&cx, response.status());
+
+        if let Some(deprecation) = deprecated {
+            deprecation.apply(&mut response);
+        }
+        if !hints.is_empty() {
+            client_hints::apply(hints, &mut response);
+        }
+        if let Some(media) = &self.options[index].produces {
+            negotiate::apply(media, &mut response);
+        }
+
+        let response = bandwidth::count(response, &self.metrics, index);
+        match capture {
+            Some(capture) => capture.finish(response),
+            None => response,
+        }
+    }
 
-            let regex = &self.patterns[index];
-            let captures = get_captures(regex, uri);
-            return handler(req, captures);
+    fn slow_threshold(&self, index: usize) -> Option<Duration> {
+        self.options[index].slow_threshold.or(self.slow_threshold)
+    }
+
+    /// Switch the routes named `name` with `RouteOptions::name` on or off,
+    /// such as to stop using a broken feature without a restart. Requests
+    /// fall through disabled routes to later ones, and are answered by the
+    /// `RouterBuilder::disabled` handler or as not found if none take them.
+    /// Returns whether any route has the name.
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for (options, flag) in self.options.iter().zip(&self.enabled) {
+            if options.metadata.name.as_deref() == Some(name) {
+                flag.store(enabled, Ordering::Relaxed);
+                found = true;
+            }
         }
+        found
+    }
 
-        not_allowed()
+    /// Whether the routes named `name` are enabled, or `None` if no route
+    /// has the name.
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.options
+            .iter()
+            .zip(&self.enabled)
+            .find(|(options, _)| options.metadata.name.as_deref() == Some(name))
+            .map(|(_, flag)| flag.load(Ordering::Relaxed))
+    }
+
+    /// Take a snapshot of the request counts, status classes and latencies
+    /// recorded for each route so far.
+    pub fn metrics(&self) -> RouterMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Returns a future that resolves once no handlers are executing. Once a
+    /// server has stopped accepting connections this tells you when it is
+    /// safe to terminate the process.
+    pub fn drained(&self) -> Drained<'_> {
+        Drained {
+            metrics: &self.metrics,
+        }
     }
 }
 
 /// A `RouterBuilder` enables you to build up a set of routes and their handlers
 /// to be handled by a `Router`.
-#[derive(Default)]
-pub struct RouterBuilder {
+pub struct RouterBuilder<B = Body> {
     routes: Vec<String>,
-    handlers: Vec<(Method, RouteHandler)>,
-    not_found: Option<RouteHandler>,
+    handlers: Vec<(Method, RouteHandler<B>)>,
+    options: Vec<RouteOptions<B>>,
+    not_found: Option<RouteHandler<B>>,
+    disabled: Option<RouteHandler<B>>,
+    method_not_allowed: Option<MethodNotAllowedHandler<B>>,
+    not_acceptable: Option<NotAcceptableHandler<B>>,
+    internal_error: Option<ErrorHandler<B>>,
+    error_mappers: Vec<(TypeId, ErrorMapper<B>)>,
+    problem_details: bool,
+    catch_panics: bool,
+    traps: Vec<String>,
+    reporting: Option<Reporting>,
+    deadlines: bool,
+    normalize_trailing_slash: bool,
+    redirect_trailing_slash: bool,
+    match_full_uri: bool,
+    head_fallback: bool,
+    method_override: bool,
+    scheme_policy: SchemePolicy,
+    case_insensitive: bool,
+    trap_response: Option<RouteHandler<B>>,
+    on_trap: Option<TrapHook>,
+    on_unmatched: Option<UnmatchedHook>,
+    slow_threshold: Option<Duration>,
+    on_slow_request: Option<SlowRequestHook>,
+    on_decision: Option<(Sampler, DecisionHook)>,
+    on_access: Option<AccessHook>,
+    on_slo_burn: Option<SloHook>,
+    on_deprecated_use: Option<DeprecatedHook>,
+    on_unreachable: Option<UnreachableHook>,
+    deny_unreachable: bool,
+    geo_resolver: Option<GeoResolver>,
+    device_classifier: Option<DeviceClassifier<B>>,
+    experiment: Option<Experiment>,
+    // Slots for built in handlers that need the router's metrics.
+    metrics_slots: Vec<MetricsSlot>,
+}
+
+// Derived, this would only be for bodies with a default.
+impl<B> Default for RouterBuilder<B> {
+    fn default() -> RouterBuilder<B> {
+        RouterBuilder {
+            routes: Default::default(),
+            handlers: Default::default(),
+            options: Default::default(),
+            not_found: Default::default(),
+            disabled: Default::default(),
+            method_not_allowed: Default::default(),
+            not_acceptable: Default::default(),
+            internal_error: Default::default(),
+            error_mappers: Default::default(),
+            problem_details: Default::default(),
+            catch_panics: Default::default(),
+            traps: Default::default(),
+            reporting: Default::default(),
+            deadlines: Default::default(),
+            normalize_trailing_slash: Default::default(),
+            redirect_trailing_slash: Default::default(),
+            match_full_uri: Default::default(),
+            head_fallback: Default::default(),
+            method_override: Default::default(),
+            scheme_policy: Default::default(),
+            case_insensitive: Default::default(),
+            trap_response: Default::default(),
+            on_trap: Default::default(),
+            on_unmatched: Default::default(),
+            slow_threshold: Default::default(),
+            on_slow_request: Default::default(),
+            on_decision: Default::default(),
+            on_access: Default::default(),
+            on_slo_burn: Default::default(),
+            on_deprecated_use: Default::default(),
+            on_unreachable: Default::default(),
+            deny_unreachable: Default::default(),
+            geo_resolver: Default::default(),
+            device_classifier: Default::default(),
+            experiment: Default::default(),
+            metrics_slots: Default::default(),
+        }
+    }
 }
 
 impl RouterBuilder {
-    /// Create a new `RouterBuilder` with no route handlers.
+    /// Create a new `RouterBuilder` with no route handlers, for requests and
+    /// responses with a `hyper::Body`. Builders for another `RouteBody` start
+    /// from `RouterBuilder::default()`.
     pub fn new() -> RouterBuilder {
         RouterBuilder::default()
     }
+}
 
+impl<B: RouteBody> RouterBuilder<B> {
     /// Install a handler for requests of method `verb` and which have paths
     /// matching `route`. There are also convenience methods named after the
     /// appropriate verb.
-    pub fn route<H>(&mut self, verb: Method, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn route<H, R>(&mut self, verb: Method, route: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new(), handler)
+    }
+
+    /// Install a handler like `route` but with settings specific to this route.
+    pub fn route_with<H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        options: RouteOptions<B>,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.routes.push(route.to_owned());
+        self.handlers.push((
+            verb,
+            Box::new(move |req, captures| handler(req, captures).into_response()),
+        ));
+        self.options.push(options);
+
+        self
+    }
+
+    /// Install a handler like `route` for a route named `name`, so URLs for
+    /// it can be built with `Router::url_for`. See `RouteOptions::name`.
+    pub fn route_named<H, R>(
+        &mut self,
+        verb: Method,
+        name: &str,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new().name(name), handler)
+    }
+
+    /// Convenience method to install a named GET handler.
+    pub fn get_named<H, R>(&mut self, name: &str, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
-        // Anchor the pattern at the start and end so routes only match exactly.
-        let pattern = [r"\A", route, r"\z"].join("");
+        self.route_named(Method::GET, name, route, handler)
+    }
+
+    /// Install a handler like `route` that only matches requests `guard`
+    /// returns true for. Other requests fall through to later routes, so
+    /// several routes for the same path can be told apart by headers or
+    /// extensions. See `RouteOptions::guard`.
+    pub fn route_guarded<G, H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        guard: G,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        G: Fn(&Request<B>) -> bool + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new().guard(guard), handler)
+    }
+
+    /// Install a handler like `route` that only matches requests whose
+    /// `Content-Type` is `media`, so one path can take several kinds of
+    /// upload. See `RouteOptions::consumes`.
+    pub fn route_consuming<H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        media: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new().consumes(media), handler)
+    }
+
+    /// Convenience method to install a POST handler for one `Content-Type`.
+    pub fn post_consuming<H, R>(
+        &mut self,
+        route: &str,
+        media: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_consuming(Method::POST, route, media, handler)
+    }
+
+    /// Convenience method to install a guarded GET handler.
+    pub fn get_guarded<G, H, R>(
+        &mut self,
+        route: &str,
+        guard: G,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        G: Fn(&Request<B>) -> bool + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_guarded(Method::GET, route, guard, handler)
+    }
+
+    /// Install a handler like `route` that only matches requests to hosts
+    /// matching the pattern `host`, whose groups are added to the captures.
+    /// See `RouteOptions::host_pattern`.
+    pub fn route_host<H, R>(
+        &mut self,
+        verb: Method,
+        host: &str,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_with(verb, route, RouteOptions::new().host_pattern(host), handler)
+    }
+
+    /// Convenience method to install a GET handler for hosts matching a
+    /// pattern, such as one subdomain per tenant.
+    pub fn get_host<H, R>(&mut self, host: &str, route: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_host(Method::GET, host, route, handler)
+    }
+
+    /// Add routes that only match requests for the virtual host `host`, such
+    /// as `api.example.com`, so one router can serve several hosts. Requests
+    /// for other hosts fall through to later routes.
+    pub fn host(&mut self, host: &str) -> HostRoutes<'_, B> {
+        HostRoutes::new(self, host)
+    }
+
+    /// Add the routes and traps of another builder under `prefix`, so a set
+    /// of routes can be built separately and mounted into a larger app. The
+    /// prefix is matched literally and the routes keep their handlers and
+    /// settings, so mounting a builder with `/users/(\d+)` under `/api`
+    /// gives `/api/users/(\d+)`. Router wide settings of the mounted
+    /// builder, such as its not found handler and hooks, are ignored.
+    pub fn mount(&mut self, prefix: &str, builder: RouterBuilder<B>) -> &mut RouterBuilder<B> {
+        let prefix = regex::escape(prefix.trim_end_matches('/'));
+        self.routes.extend(
+            builder
+                .routes
+                .iter()
+                .map(|route| format!("{}{}", prefix, route)),
+        );
+        self.handlers.extend(builder.handlers);
+        self.options.extend(builder.options);
+        self.traps.extend(
+            builder
+                .traps
+                .iter()
+                .map(|trap| format!("{}{}", prefix, trap)),
+        );
+        self.metrics_slots.extend(builder.metrics_slots);
+        self
+    }
+
+    /// Add the routes and traps of another builder after this one's, so an
+    /// app can be assembled from builders made by separate crates. Its not
+    /// found handler is used if this builder doesn't have one, but its other
+    /// router wide settings are ignored as with `mount`.
+    pub fn merge(&mut self, mut builder: RouterBuilder<B>) -> &mut RouterBuilder<B> {
+        if self.not_found.is_none() {
+            self.not_found = builder.not_found.take();
+        }
+        self.mount("", builder)
+    }
 
-        self.routes.push(pattern);
-        self.handlers.push((verb, Box::new(handler)));
+    /// Remove the routes for `verb` whose pattern is exactly `route`, such as
+    /// to drop a route from a default set before finalizing.
+    pub fn remove(&mut self, verb: Method, route: &str) -> &mut RouterBuilder<B> {
+        for index in (0..self.routes.len()).rev() {
+            if self.handlers[index].0 == verb && self.routes[index] == route {
+                self.routes.remove(index);
+                drop(self.handlers.remove(index));
+                self.options.remove(index);
+            }
+        }
+        self
+    }
 
+    /// Give the routes for `verb` whose pattern is exactly `route` a new
+    /// handler, keeping their place and settings. The route is added if
+    /// there isn't one yet.
+    pub fn replace<H, R>(&mut self, verb: Method, route: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        let handler: Arc<RouteHandler<B>> = Arc::new(Box::new(move |req, captures| {
+            handler(req, captures).into_response()
+        }));
+        let mut replaced = false;
+        for (existing, (method, current)) in self.routes.iter().zip(&mut self.handlers) {
+            if *method == verb && existing == route {
+                let handler = Arc::clone(&handler);
+                *current = Box::new(move |req, captures| handler(req, captures));
+                replaced = true;
+            }
+        }
+        if !replaced {
+            self.route(verb, route, move |req, captures| handler(req, captures));
+        }
         self
     }
 
+    /// Finalize a builder that is only borrowed, leaving it empty. This lets
+    /// a router be built in a single expression, such as in a `lazy_static`:
+    ///
+    /// ```ignore
+    /// let router = RouterBuilder::new()
+    ///     .get(r"/users/(\d+)", show_user)
+    ///     .post(r"/users", create_user)
+    ///     .build()?;
+    /// ```
+    pub fn build(&mut self) -> Result<Router<B>, Error> {
+        std::mem::take(self).finalize()
+    }
+
     /// Compile the routes in a `RouterBuilder` to produce a `Router` capable
     /// of handling Hyper requests.
-    pub fn finalize(self) -> Result<Router, Error> {
-        Ok(Router {
-            routes: RegexSet::new(self.routes.iter())?,
-            patterns: self
-                .routes
+    pub fn finalize(mut self) -> Result<Router<B>, Error> {
+        self.sort_by_priority();
+        let anchored: Vec<String> = (0..self.routes.len())
+            .map(|index| anchor(&self.pattern(index)))
+            .collect();
+        self.check_duplicates(&anchored)?;
+        self.check_reachable()?;
+        // Paths matched literally are looked up directly once built.
+        let literals: Vec<String> = (0..self.routes.len())
+            .filter_map(|index| path::literal(&self.pattern(index)))
+            .collect();
+
+        let metrics = Arc::new(Metrics::new(
+            self.handlers
+                .iter()
+                .zip(&self.routes)
+                .zip(&self.options)
+                .map(|(((method, _), route), options)| (method, route.as_str(), options)),
+        ));
+
+        for slot in &self.metrics_slots {
+            let _ = slot.set(Arc::clone(&metrics));
+        }
+
+        // Routes limited to some devices need requests classified even when
+        // no classifier was given.
+        let device_classifier = match self.device_classifier {
+            Some(classifier) => Some(classifier),
+            None if self.options.iter().any(|options| options.devices.is_some()) => {
+                Some(Box::new(device::classify) as DeviceClassifier<B>)
+            }
+            None => None,
+        };
+
+        // Each distinct pattern is only compiled once.
+        let mut unique: HashMap<&str, usize> = HashMap::new();
+        let mut set_routes: Vec<Vec<usize>> = Vec::new();
+        let mut distinct = Vec::new();
+        let mut size_limits: Vec<Option<usize>> = Vec::new();
+        let mut pattern_of = Vec::with_capacity(anchored.len());
+        for (index, route) in anchored.iter().enumerate() {
+            let pattern = *unique.entry(route).or_insert_with(|| {
+                distinct.push(route.as_str());
+                size_limits.push(None);
+                set_routes.push(Vec::new());
+                distinct.len() - 1
+            });
+            set_routes[pattern].push(index);
+            size_limits[pattern] = size_limits[pattern].max(self.options[index].regex.size_limit);
+            pattern_of.push(pattern);
+        }
+        let compiled = distinct
+            .iter()
+            .zip(&size_limits)
+            .map(|(route, limit)| {
+                let mut builder = RegexBuilder::new(route);
+                if let Some(limit) = *limit {
+                    builder.size_limit(limit);
+                }
+                builder.build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // The set holds every pattern, so it gets the room that routes
+        // asked for on top of the usual limit.
+        let mut set = RegexSetBuilder::new(&distinct);
+        let extra: usize = size_limits.iter().flatten().sum();
+        if extra > 0 {
+            set.size_limit(DEFAULT_SIZE_LIMIT.saturating_add(extra));
+        }
+
+        let problem_details = self.problem_details;
+        let mut router = Router {
+            routes: set.build()?,
+            set_routes,
+            literals: HashMap::new(),
+            patterns: pattern_of
                 .iter()
-                .map(|route| Regex::new(route))
+                .map(|&pattern| compiled[pattern].clone())
+                .collect(),
+            host_patterns: self
+                .options
+                .iter()
+                .map(|options| {
+                    let pattern = options.host_pattern.as_ref()?;
+                    Some(Regex::new(&anchor(&format!("(?i:{})", pattern))))
+                })
+                .map(Option::transpose)
                 .collect::<Result<_, _>>()?,
+            sources: self.routes,
             handlers: self.handlers,
-            not_found: self
-                .not_found
-                .unwrap_or_else(|| Box::new(default_not_found)),
-        })
+            options: self.options,
+            enabled: anchored.iter().map(|_| AtomicBool::new(true)).collect(),
+            not_found: self.not_found.unwrap_or_else(|| {
+                if problem_details {
+                    Box::new(problem::not_found)
+                } else {
+                    Box::new(default_not_found)
+                }
+            }),
+            disabled: self.disabled,
+            method_not_allowed: self.method_not_allowed.unwrap_or_else(|| {
+                if problem_details {
+                    Box::new(problem::not_allowed)
+                } else {
+                    Box::new(not_allowed)
+                }
+            }),
+            not_acceptable: self
+                .not_acceptable
+                .unwrap_or_else(|| Box::new(not_acceptable)),
+            internal_error: self.internal_error,
+            error_mappers: self.error_mappers,
+            problem_details,
+            catch_panics: self.catch_panics,
+            traps: Traps::new(self.traps, self.trap_response, self.on_trap)?,
+            reporting: self.reporting,
+            deadlines: self.deadlines,
+            normalize_trailing_slash: self.normalize_trailing_slash,
+            redirect_trailing_slash: self.redirect_trailing_slash,
+            match_full_uri: self.match_full_uri,
+            head_fallback: self.head_fallback,
+            method_override: self.method_override,
+            scheme_policy: self.scheme_policy,
+            on_unmatched: self.on_unmatched,
+            slow_threshold: self.slow_threshold,
+            on_slow_request: self.on_slow_request,
+            on_decision: self.on_decision,
+            on_access: self.on_access,
+            on_slo_burn: self.on_slo_burn,
+            on_deprecated_use: self.on_deprecated_use,
+            geo_resolver: self.geo_resolver,
+            device_classifier,
+            experiment: self.experiment,
+            metrics,
+        };
+        for path in literals {
+            if !router.literals.contains_key(&path) {
+                let matches = router.matching_routes(&path);
+                router.literals.insert(path, matches);
+            }
+        }
+        Ok(router)
     }
 
     /// Convenience method to install a GET handler.
-    pub fn get<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn get<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::GET, route, handler)
     }
 
     /// Convenience method to install a POST handler.
-    pub fn post<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn post<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::POST, route, handler)
     }
 
     /// Convenience method to install a PUT handler.
-    pub fn put<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn put<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::PUT, route, handler)
     }
 
     /// Convenience method to install a PATCH handler.
-    pub fn patch<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn patch<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::PATCH, route, handler)
     }
 
     /// Convenience method to install a DELETE handler.
-    pub fn delete<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn delete<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::DELETE, route, handler)
     }
 
     /// Convenience method to install an OPTIONS handler.
-    pub fn options<H>(&mut self, route: &str, handler: H) -> &mut RouterBuilder
+    pub fn options<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
         self.route(Method::OPTIONS, route, handler)
     }
 
-    /// Install a fallback handler for when there is no matching route for a
-    /// request. If none is installed, the resulting `Router` will use a
-    /// default handler.
-    pub fn not_found<H>(&mut self, not_found: H) -> &mut RouterBuilder
+    /// Install a handler for requests with any method to a path matching
+    /// `route`, such as for proxies. The route is listed with the method
+    /// `*` in the route table and metrics.
+    pub fn any<H, R>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
     where
-        H: Fn(Request<Body>, Captures) -> Response<Body> + Send + Sync + 'static,
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
     {
-        self.not_found = Some(Box::new(not_found));
-        self
+        let any = Method::from_bytes(ANY_METHOD.as_bytes()).expect("valid method");
+        self.route(any, route, handler)
     }
-}
 
-// The default 404 handler.
-fn default_not_found(_: Request<Body>, _: Captures) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::NOT_FOUND)
-        .body("Not Found".into())
-        .unwrap()
-}
+    /// Install a handler like `route` that can fail. Its errors are turned
+    /// into responses by `map_error` or `internal_error`, so handlers
+    /// can use `?` instead of building error responses themselves.
+    pub fn route_fallible<H, E>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> Result<Response<B>, E> + Send + Sync + 'static,
+        E: Into<HandlerError>,
+    {
+        self.route(verb, route, handler)
+    }
 
-// This handler will get fired when a URI matches a route but contains the wrong method.
-fn not_allowed() -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::METHOD_NOT_ALLOWED)
-        .body("Method Not Allowed".into())
-        .unwrap()
-}
+    /// Convenience method to install a fallible GET handler.
+    pub fn get_fallible<H, E>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> Result<Response<B>, E> + Send + Sync + 'static,
+        E: Into<HandlerError>,
+    {
+        self.route_fallible(Method::GET, route, handler)
+    }
 
-// Return that captures from a pattern that was matched.
-fn get_captures<'r>(pattern: &'r Regex, uri: &'r str) -> Captures<'r> {
-    // We know this compiles because it was part of the set.
-    let caps = pattern.captures(uri);
-    match caps {
-        Some(caps) => {
-            let mut v = SmallVec::<[&str; 4]>::new();
-            caps.iter()
-                .filter(|c| c.is_some())
-                .for_each(|c| v.push(c.unwrap().as_str()));
-            Some(v)
-        }
-        None => None,
+    /// Convenience method to install a fallible POST handler.
+    pub fn post_fallible<H, E>(&mut self, route: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> Result<Response<B>, E> + Send + Sync + 'static,
+        E: Into<HandlerError>,
+    {
+        self.route_fallible(Method::POST, route, handler)
     }
-}
 
-#[test]
-fn bad_regular_expression() {
-    fn test_handler(_: Request<Body>, _: Captures) -> Response<Body> {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body("Ok".into())
-            .unwrap()
+    /// Install a handler for an extension method given by name, such as
+    /// WebDAV's `PROPFIND` or `MKCOL`. The method is case sensitive and is
+    /// listed in `Allow` headers like any other.
+    ///
+    /// This panics if `method` isn't a valid method name.
+    pub fn route_custom<H, R>(
+        &mut self,
+        method: &str,
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        let verb = Method::from_bytes(method.as_bytes())
+            .unwrap_or_else(|_| panic!("`{}` is not a valid method", method));
+        self.route(verb, route, handler)
+    }
+
+    /// Spread requests with `verb` to a path matching `route` between
+    /// several handlers in proportion to their weights, such as 90 and 10 to
+    /// send a tenth of requests to a new implementation. Requests are handed
+    /// out in turn so the split is exact over every cycle of the total
+    /// weight. Handlers of different types need to be boxed.
+    ///
+    /// This panics if no handler has a weight above zero.
+    pub fn split(
+        &mut self,
+        verb: Method,
+        route: &str,
+        variants: Vec<(u32, RouteHandler<B>)>,
+    ) -> &mut RouterBuilder<B> {
+        let split = Split::new(variants, None);
+        self.route(verb, route, move |req, captures| {
+            split.handle(req, captures)
+        })
+    }
+
+    /// Split requests like `split`, but pick the handler by a hash of the
+    /// `header` of each request, such as a user id, so the same value is
+    /// always sent to the same handler, on every server. Requests without
+    /// the header are handed out in turn.
+    pub fn split_by(
+        &mut self,
+        verb: Method,
+        route: &str,
+        header: &str,
+        variants: Vec<(u32, RouteHandler<B>)>,
+    ) -> &mut RouterBuilder<B> {
+        let split = Split::new(variants, Some(header));
+        self.route(verb, route, move |req, captures| {
+            split.handle(req, captures)
+        })
+    }
+
+    /// Redirect requests with any method to a path matching `route` to
+    /// `target` with `status`, such as `308 Permanent Redirect`. `$1` and so
+    /// on in the target are replaced with the route's captures, so
+    /// `/old/(\d+)` can redirect to `/new/$1`.
+    pub fn redirect(
+        &mut self,
+        route: &str,
+        target: &str,
+        status: StatusCode,
+    ) -> &mut RouterBuilder<B> {
+        let target = target.to_owned();
+        self.any(route, move |_, captures| {
+            redirect::respond(&target, status, &captures).map(B::from)
+        })
+    }
+
+    /// Install one handler for requests with any of `verbs` to a path
+    /// matching `route`, such as a form shown with GET and submitted with
+    /// POST. Each method is listed as its own route, but they share the
+    /// handler and the pattern is only compiled once.
+    pub fn methods<H, R>(
+        &mut self,
+        verbs: &[Method],
+        route: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        let handler = Arc::new(handler);
+        for verb in verbs {
+            let handler = Arc::clone(&handler);
+            self.route(verb.clone(), route, move |req, captures| {
+                handler(req, captures)
+            });
+        }
+        self
+    }
+
+    /// Install a handler for a Sinatra style path instead of a pattern.
+    /// `:name` in the path captures a single path segment and `*name` the
+    /// rest of the path, each in a group of that name, so `/users/:id` is
+    /// the same as the pattern `/users/(?P<id>[^/]+)`. A parameter can say
+    /// what it matches in braces, with the name of a fragment from
+    /// `patterns` as in `/users/:id{uuid}` or a regex as in `/:year{\d{4}}`.
+    /// Everything else is matched literally.
+    pub fn route_path<H, R>(
+        &mut self,
+        verb: Method,
+        path: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(verb, &path::compile(path), handler)
+    }
+
+    /// Convenience method to install a GET handler for a Sinatra style path.
+    pub fn get_path<H, R>(&mut self, path: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_path(Method::GET, path, handler)
+    }
+
+    /// Convenience method to install a POST handler for a Sinatra style path.
+    pub fn post_path<H, R>(&mut self, path: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_path(Method::POST, path, handler)
+    }
+
+    /// Convenience method to install a PUT handler for a Sinatra style path.
+    pub fn put_path<H, R>(&mut self, path: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_path(Method::PUT, path, handler)
+    }
+
+    /// Convenience method to install a PATCH handler for a Sinatra style path.
+    pub fn patch_path<H, R>(&mut self, path: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_path(Method::PATCH, path, handler)
+    }
+
+    /// Convenience method to install a DELETE handler for a Sinatra style path.
+    pub fn delete_path<H, R>(&mut self, path: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_path(Method::DELETE, path, handler)
+    }
+
+    /// Install a handler for every path starting with `prefix`, such as
+    /// `/static/` for a file server. The prefix is matched literally and the
+    /// rest of the path, which may be empty, is captured as the first group
+    /// and given by `Captures::rest`.
+    pub fn route_prefix<H, R>(
+        &mut self,
+        verb: Method,
+        prefix: &str,
+        handler: H,
+    ) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(verb, &path::prefix(prefix), handler)
+    }
+
+    /// Convenience method to install a GET handler for a path prefix.
+    pub fn get_prefix<H, R>(&mut self, prefix: &str, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route_prefix(Method::GET, prefix, handler)
+    }
+
+    /// Install a fallback handler for when there is no matching route for a
+    /// request. If none is installed, the resulting `Router` will use a
+    /// default handler.
+    ///
+    /// Like route handlers, this and the other fallback handlers below can
+    /// return anything that is `IntoResponse`.
+    pub fn not_found<H, R>(&mut self, not_found: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.not_found = Some(Box::new(move |req, captures| {
+            not_found(req, captures).into_response()
+        }));
+        self
+    }
+
+    /// Install a handler for requests that only disabled routes would have
+    /// taken, such as to explain that a feature is switched off. Without
+    /// one they are answered as not found. See `Router::set_enabled`.
+    pub fn disabled<H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.disabled = Some(Box::new(move |req, captures| {
+            handler(req, captures).into_response()
+        }));
+        self
+    }
+
+    /// Install a handler for requests whose path matches routes for other
+    /// methods only. It is given the methods those routes accept, which the
+    /// `Allow` header of its response is set to unless it sets one itself.
+    /// If none is installed, the resulting `Router` answers with a plain
+    /// `405 Method Not Allowed`.
+    pub fn method_not_allowed<H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, &[Method]) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.method_not_allowed = Some(Box::new(move |req, allowed| {
+            handler(req, allowed).into_response()
+        }));
+        self
+    }
+
+    /// Install a handler for requests whose `Accept` header rules out every
+    /// media type produced by the routes for their path, which it is given.
+    /// See `RouteOptions::produces`. If none is installed, the resulting
+    /// `Router` answers with a plain `406 Not Acceptable`.
+    pub fn not_acceptable<H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, &[&str]) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.not_acceptable = Some(Box::new(move |req, produced| {
+            handler(req, produced).into_response()
+        }));
+        self
+    }
+
+    /// Install a handler for requests the router couldn't answer normally
+    /// because a handler failed, or panicked with `catch_panics` on, such as
+    /// to render a branded error page. It is given the method, URI and
+    /// headers of the request and the error. Errors from fallible handlers,
+    /// such as those added with `route_fallible`, and from fallback
+    /// handlers, such as the one given to `not_found`, come here unless
+    /// `map_error` takes them. If none is installed, the resulting `Router`
+    /// answers with a plain `500 Internal Server Error`.
+    pub fn internal_error<H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(&Request<()>, &(dyn StdError + Send + Sync + 'static)) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.internal_error = Some(Box::new(move |req, error| {
+            handler(req, error).into_response()
+        }));
+        self
+    }
+
+    /// Turn the errors of type `E` from fallible handlers into responses
+    /// with `handler`, such as `404 Not Found` for a missing record. Each
+    /// type has one mapping, so mapping a type again replaces it. Errors of
+    /// types without a mapping go to the `internal_error` handler.
+    pub fn map_error<E, H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        E: StdError + Send + Sync + 'static,
+        H: Fn(&E) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        let mapper: ErrorMapper<B> = Box::new(move |error| {
+            error
+                .downcast_ref::<E>()
+                .map(|error| handler(error).into_response())
+        });
+        let key = TypeId::of::<E>();
+        match self.error_mappers.iter_mut().find(|(id, _)| *id == key) {
+            Some(existing) => existing.1 = mapper,
+            None => self.error_mappers.push((key, mapper)),
+        }
+        self
+    }
+
+    /// Add a decoy route, such as `/wp-admin.*` on a site that isn't
+    /// WordPress, to catch clients probing for vulnerabilities. Traps match
+    /// any method and are checked before every other route. Hits are
+    /// reported to the hook installed with `on_trap` and answered like
+    /// unmatched requests unless `trap_response` is set.
+    pub fn trap(&mut self, route: &str) -> &mut RouterBuilder<B> {
+        self.traps.push(route.to_owned());
+        self
+    }
+
+    /// Answer requests that hit a trap with `handler` instead of the not
+    /// found handler.
+    pub fn trap_response<H, R>(&mut self, handler: H) -> &mut RouterBuilder<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.trap_response = Some(Box::new(move |req, captures| {
+            handler(req, captures).into_response()
+        }));
+        self
+    }
+
+    /// Collect browser reports, such as CSP violations and network errors,
+    /// sent to `route` with POST and pass them to the collector's sink.
+    #[cfg(feature = "reports")]
+    pub fn reports(&mut self, route: &str, collector: ReportCollector) -> &mut RouterBuilder<B> {
+        let collector = Arc::new(collector);
+        self.post(route, move |req, _| {
+            report_collector::respond(&collector, req.map(RouteBody::into_hyper)).map(B::from)
+        })
+    }
+
+    /// Add the headers described by `reporting` to every response, asking
+    /// browsers to send reports to an endpoint added with `reports`.
+    pub fn reporting(&mut self, reporting: Reporting) -> &mut RouterBuilder<B> {
+        self.reporting = Some(reporting);
+        self
+    }
+
+    /// Install a hook that is called for every request that hits a trap.
+    pub fn on_trap<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&TrapHit) + Send + Sync + 'static,
+    {
+        self.on_trap = Some(Box::new(hook));
+        self
+    }
+
+    /// Install a hook that is called whenever a request is answered with the
+    /// not found or method not allowed response. This is useful for logging
+    /// requests that reveal mistakes in the route table.
+    pub fn on_unmatched<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&Unmatched) + Send + Sync + 'static,
+    {
+        self.on_unmatched = Some(Box::new(hook));
+        self
+    }
+
+    /// Register `/healthz`, `/readyz` and `/livez` GET routes that run the
+    /// given checks and report their results as JSON. `/healthz` runs every
+    /// check while the others only run the readiness or liveness checks.
+    pub fn health_routes(&mut self, checks: HealthChecks) -> &mut RouterBuilder<B> {
+        let checks = Arc::new(checks);
+        for &(route, probe) in &[
+            ("/healthz", Probe::Health),
+            ("/readyz", Probe::Ready),
+            ("/livez", Probe::Live),
+        ] {
+            let checks = Arc::clone(&checks);
+            self.get(route, move |_, _| checks.respond(probe).map(B::from));
+        }
+        self
+    }
+
+    /// Emit a `tracing` warning for every unmatched request.
+    #[cfg(feature = "tracing")]
+    pub fn log_unmatched(&mut self) -> &mut RouterBuilder<B> {
+        self.on_unmatched(unmatched::log)
+    }
+
+    /// Report requests to any route that take longer than `threshold` to
+    /// the slow request hook. Routes can override this with
+    /// `RouteOptions::slow_threshold`.
+    pub fn slow_request_threshold(&mut self, threshold: Duration) -> &mut RouterBuilder<B> {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Install a hook that is called for requests slower than their route's
+    /// threshold, with the route, captures and a breakdown of the time spent.
+    pub fn on_slow_request<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&SlowRequest) + Send + Sync + 'static,
+    {
+        self.on_slow_request = Some(Box::new(hook));
+        self
+    }
+
+    /// Emit a `tracing` warning for every slow request.
+    #[cfg(feature = "tracing")]
+    pub fn log_slow_requests(&mut self) -> &mut RouterBuilder<B> {
+        self.on_slow_request(slow::log)
+    }
+
+    /// Install a hook that is called when a route with an objective starts
+    /// burning its error budget faster than its alert rate. It is called
+    /// again only after the burn rate has dropped back below the alert rate.
+    pub fn on_slo_burn<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&SloAlert) + Send + Sync + 'static,
+    {
+        self.on_slo_burn = Some(Box::new(hook));
+        self
+    }
+
+    /// Install a hook that `finalize` calls for each route an earlier route
+    /// always shadows, so it can never be selected, such as `/a/b` added
+    /// after `/a/(.*)`. See `analyze` for how they are found.
+    pub fn on_unreachable<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&Shadowing) + Send + Sync + 'static,
+    {
+        self.on_unreachable = Some(Box::new(hook));
+        self
+    }
+
+    /// Make `finalize` fail with `Error::UnreachableRoute` if an earlier
+    /// route always shadows a later one.
+    pub fn deny_unreachable(&mut self, deny: bool) -> &mut RouterBuilder<B> {
+        self.deny_unreachable = deny;
+        self
+    }
+
+    /// Install a hook that is called for every request to a deprecated route,
+    /// to find the callers that still need to migrate.
+    pub fn on_deprecated_use<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&DeprecatedUse) + Send + Sync + 'static,
+    {
+        self.on_deprecated_use = Some(Box::new(hook));
+        self
+    }
+
+    /// Let routes match paths with an extra trailing slash, so `/users/`
+    /// is routed like `/users`. Paths are only tried without their slash when
+    /// they match no route as they are, so routes should be written without
+    /// one.
+    pub fn normalize_trailing_slash(&mut self, normalize: bool) -> &mut RouterBuilder<B> {
+        self.normalize_trailing_slash = normalize;
+        self
+    }
+
+    /// Answer requests for paths with an extra trailing slash, such as
+    /// `/users/`, with a `308 Permanent Redirect` to the path without it when
+    /// only that matches a route, so every resource has one canonical URL.
+    /// The query string is kept. This takes precedence over
+    /// `normalize_trailing_slash`.
+    pub fn redirect_trailing_slash(&mut self, redirect: bool) -> &mut RouterBuilder<B> {
+        self.redirect_trailing_slash = redirect;
+        self
+    }
+
+    /// Catch handlers that panic and treat them as having failed with a
+    /// `Panicked` error, so they are answered like other failed handlers
+    /// instead of the panic tearing down the connection. Handlers that
+    /// panic while holding a lock leave it poisoned, which they should be
+    /// prepared for.
+    pub fn catch_panics(&mut self, catch: bool) -> &mut RouterBuilder<B> {
+        self.catch_panics = catch;
+        self
+    }
+
+    /// Answer unmatched requests, disallowed methods and failed handlers
+    /// with `application/problem+json` bodies, as described by RFC 7807,
+    /// instead of plain text. Handlers installed for these with
+    /// `not_found`, `method_not_allowed`, `map_error` or `internal_error` are
+    /// still used. See `Problem`.
+    pub fn problem_details(&mut self, enabled: bool) -> &mut RouterBuilder<B> {
+        self.problem_details = enabled;
+        self
+    }
+
+    /// Match routes against the path and query string of requests, such as
+    /// `/search?q=rust`, instead of only the path. Routes then have to allow
+    /// for any query string they might be sent.
+    pub fn match_full_uri(&mut self, full: bool) -> &mut RouterBuilder<B> {
+        self.match_full_uri = full;
+        self
+    }
+
+    /// Answer HEAD requests to paths without a HEAD route with their GET
+    /// route, dropping the body of its response, instead of with a 405.
+    pub fn head_fallback(&mut self, fallback: bool) -> &mut RouterBuilder<B> {
+        self.head_fallback = fallback;
+        self
+    }
+
+    /// Let POST requests ask to be routed as PUT, PATCH or DELETE requests
+    /// with an `X-HTTP-Method-Override` header or a `_method` query
+    /// parameter, since HTML forms can only send GET and POST. The router
+    /// doesn't read request bodies, so forms have to put `_method` in their
+    /// action URL rather than in a field. Handlers can find the method sent
+    /// in the `OverriddenMethod` extension.
+    pub fn method_override(&mut self, enabled: bool) -> &mut RouterBuilder<B> {
+        self.method_override = enabled;
+        self
+    }
+
+    /// Match every route's pattern without regard to case, so `/Users/42`
+    /// is routed like `/users/42`. Routes can override this with
+    /// `RouteOptions::case_insensitive`. Captures keep the case of the path.
+    pub fn case_insensitive(&mut self, insensitive: bool) -> &mut RouterBuilder<B> {
+        self.case_insensitive = insensitive;
+        self
+    }
+
+    /// Read deadlines from the `X-Request-Deadline` and `grpc-timeout`
+    /// headers of requests, giving them to handlers as a `Deadline`
+    /// extension and answering requests that have run out of time with
+    /// `504 Gateway Timeout`.
+    pub fn deadlines(&mut self) -> &mut RouterBuilder<B> {
+        self.deadlines = true;
+        self
+    }
+
+    /// Decide which scheme requests were sent with by `policy`, for routes
+    /// limited to one with `RouteOptions::scheme`.
+    pub fn scheme_policy(&mut self, policy: SchemePolicy) -> &mut RouterBuilder<B> {
+        self.scheme_policy = policy;
+        self
+    }
+
+    /// Read which variant of an A/B experiment each request is in as set
+    /// out by `experiment`, for routes limited to one variant with
+    /// `RouteOptions::variant`.
+    pub fn experiment(&mut self, experiment: Experiment) -> &mut RouterBuilder<B> {
+        self.experiment = Some(experiment);
+        self
+    }
+
+    /// Look up the region each request comes from with `resolver`, which is
+    /// given the client's address and could be backed by a GeoIP database.
+    /// The region is added to requests as a `Region` extension and routes can
+    /// be limited to some regions with `RouteOptions::regions`.
+    pub fn geo_resolver<F>(&mut self, resolver: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(IpAddr) -> Option<String> + Send + Sync + 'static,
+    {
+        self.geo_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Classify the kind of device each request comes from with
+    /// `classifier` instead of by matching words in its `User-Agent`. The
+    /// result is added to requests as a `Device` extension and routes can be
+    /// limited to some devices with `RouteOptions::devices`.
+    pub fn device_classifier<F>(&mut self, classifier: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&Request<B>) -> Device + Send + Sync + 'static,
+    {
+        self.device_classifier = Some(Box::new(classifier));
+        self
+    }
+
+    /// Install a hook that is called with an access log entry for every
+    /// request once its response has been produced.
+    pub fn on_access<F>(&mut self, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&AccessLogEntry) + Send + Sync + 'static,
+    {
+        self.on_access = Some(Box::new(hook));
+        self
+    }
+
+    /// Write a JSON access log line for every request.
+    pub fn json_access_log(&mut self, log: JsonAccessLog) -> &mut RouterBuilder<B> {
+        self.on_access(move |entry| log.write(entry))
+    }
+
+    /// Install a hook that receives a report of how every `every`th request
+    /// was routed: the patterns that matched, which were skipped because of
+    /// their method or shadowed by an earlier route, and the final outcome.
+    /// This is meant for diagnosing route tables in production so keep the
+    /// sample small.
+    pub fn sample_routing_decisions<F>(&mut self, every: u64, hook: F) -> &mut RouterBuilder<B>
+    where
+        F: Fn(&RoutingDecision) + Send + Sync + 'static,
+    {
+        self.on_decision = Some((Sampler::new(every), Box::new(hook)));
+        self
+    }
+
+    /// Emit a `tracing` debug event describing every `every`th routing decision.
+    #[cfg(feature = "tracing")]
+    pub fn log_routing_decisions(&mut self, every: u64) -> &mut RouterBuilder<B> {
+        self.sample_routing_decisions(every, decision::log)
+    }
+
+    /// Serve the router's metrics in the Prometheus text format for GET
+    /// requests to `route`. Routes are labeled with their method, pattern
+    /// and name, and routes sharing all three, such as ones for different
+    /// hosts, with their index in registration order as well.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_endpoint(&mut self, route: &str) -> &mut RouterBuilder<B> {
+        let slot = self.metrics_slot();
+        self.get(route, move |_, _| {
+            prometheus::response(metrics_from(&slot)).map(B::from)
+        })
+    }
+
+    /// Mount the admin endpoints described by `admin` under `prefix`. These
+    /// serve the route table, metrics, per-route usage, enabled crate
+    /// features, build information and API key quota usage as JSON, so they
+    /// should be protected with `Admin::authorize` in production.
+    pub fn admin(&mut self, prefix: &str, admin: Admin<B>) -> &mut RouterBuilder<B> {
+        let admin = Arc::new(admin);
+        let prefix = regex::escape(prefix.trim_end_matches('/'));
+        for &endpoint in &[
+            AdminEndpoint::Routes,
+            AdminEndpoint::Metrics,
+            AdminEndpoint::Usage,
+            AdminEndpoint::Features,
+            AdminEndpoint::Build,
+            AdminEndpoint::Quotas,
+        ] {
+            let admin = Arc::clone(&admin);
+            let slot = self.metrics_slot();
+            let route = format!("{}/{}", prefix, endpoint.path());
+            self.get(&route, move |req, _| {
+                admin
+                    .respond(endpoint, &req, metrics_from(&slot))
+                    .map(B::from)
+            });
+        }
+        self
+    }
+
+    /// Serve an OpenAPI document describing the router's routes as JSON at
+    /// `route`. See `OpenApi` for how routes are described.
+    pub fn openapi(&mut self, route: &str, api: OpenApi) -> &mut RouterBuilder<B> {
+        let slot = self.metrics_slot();
+        self.get(route, move |_, _| {
+            openapi::response(&api, metrics_from(&slot)).map(B::from)
+        })
+    }
+
+    /// Serve a Swagger UI page at `route` that browses the OpenAPI document
+    /// served from `spec`, such as the route given to `openapi`. The page
+    /// loads Swagger UI from a public CDN.
+    pub fn swagger_ui(&mut self, route: &str, spec: &str) -> &mut RouterBuilder<B> {
+        let spec = spec.to_owned();
+        self.get(route, move |_, _| openapi::swagger_ui(&spec).map(B::from))
+    }
+
+    /// Serve a sitemap of the routes described by `sitemap` at
+    /// `/sitemap.xml`.
+    pub fn sitemap(&mut self, sitemap: Sitemap) -> &mut RouterBuilder<B> {
+        let slot = self.metrics_slot();
+        self.get(r"/sitemap\.xml", move |_, _| {
+            sitemap::response(&sitemap, metrics_from(&slot)).map(B::from)
+        })
+    }
+
+    // Order the routes by their priority, highest first, keeping the order
+    // they were added in among routes of the same priority.
+    fn sort_by_priority(&mut self) {
+        if self.options.iter().all(|options| options.priority == 0) {
+            return;
+        }
+        let mut routes: Vec<_> = std::mem::take(&mut self.routes)
+            .into_iter()
+            .zip(std::mem::take(&mut self.handlers))
+            .zip(std::mem::take(&mut self.options))
+            .collect();
+        routes.sort_by_key(|(_, options)| std::cmp::Reverse(options.priority));
+        for ((route, handler), options) in routes {
+            self.routes.push(route);
+            self.handlers.push(handler);
+            self.options.push(options);
+        }
+    }
+
+    // The pattern of the route at `index` with its flags, before anchoring.
+    fn pattern(&self, index: usize) -> String {
+        let options = &self.options[index];
+        let case_insensitive = options.case_insensitive.unwrap_or(self.case_insensitive);
+        options.regex.apply(&self.routes[index], case_insensitive)
+    }
+
+    // Fail if a route can never be used because an earlier one with the same
+    // method and pattern takes every request.
+    fn check_duplicates(&self, anchored: &[String]) -> Result<(), Error> {
+        let mut unconditional: HashSet<(&Method, &str)> = HashSet::new();
+        let any = Method::from_bytes(ANY_METHOD.as_bytes()).expect("valid method");
+        for (index, (method, _)) in self.handlers.iter().enumerate() {
+            let pattern = anchored[index].as_str();
+            if unconditional.contains(&(method, pattern))
+                || unconditional.contains(&(&any, pattern))
+            {
+                return Err(Error::DuplicateRoute {
+                    method: method.clone(),
+                    pattern: self.routes[index].clone(),
+                });
+            }
+            if !self.options[index].conditional() {
+                unconditional.insert((method, pattern));
+            }
+        }
+        Ok(())
+    }
+
+    // Built in handlers that report on the router can't have its metrics until
+    // `finalize` creates them, so they read them from a slot filled in there.
+    fn metrics_slot(&mut self) -> MetricsSlot {
+        let slot = Arc::new(OnceLock::new());
+        self.metrics_slots.push(Arc::clone(&slot));
+        slot
+    }
+}
+
+/// Add routes from an iterator of methods, patterns and handlers, for routes
+/// that are generated rather than written out. Closures usually need to be
+/// boxed as a `RouteHandler` for this.
+impl<B, S, H, R> Extend<(Method, S, H)> for RouterBuilder<B>
+where
+    B: RouteBody,
+    S: AsRef<str>,
+    H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+    R: IntoResponse<B>,
+{
+    fn extend<I>(&mut self, routes: I)
+    where
+        I: IntoIterator<Item = (Method, S, H)>,
+    {
+        for (method, route, handler) in routes {
+            self.route(method, route.as_ref(), handler);
+        }
+    }
+}
+
+impl<B, S, H, R> FromIterator<(Method, S, H)> for RouterBuilder<B>
+where
+    B: RouteBody,
+    S: AsRef<str>,
+    H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+    R: IntoResponse<B>,
+{
+    fn from_iter<I>(routes: I) -> RouterBuilder<B>
+    where
+        I: IntoIterator<Item = (Method, S, H)>,
+    {
+        let mut builder = RouterBuilder::default();
+        builder.extend(routes);
+        builder
+    }
+}
+
+fn metrics_from(slot: &MetricsSlot) -> &Metrics {
+    slot.get().expect("metrics are set by finalize")
+}
+
+// Drop the body of a response to a GET request so it can answer a HEAD
+// request, keeping its length if that is known.
+fn strip_body<B: RouteBody>(response: &mut Response<B>) {
+    if let Some(length) = HttpBody::size_hint(response.body()).exact() {
+        response
+            .headers_mut()
+            .entry(CONTENT_LENGTH)
+            .or_insert_with(|| length.into());
+    }
+    *response.body_mut() = Body::empty().into();
+}
+
+// Routes added with `RouterBuilder::any` are stored with this method, which is
+// how they show up in the route table and metrics.
+const ANY_METHOD: &str = "*";
+
+// Whether a route registered for `route` handles requests with `method`.
+fn accepts(route: &Method, method: &Method) -> bool {
+    route == method || route.as_str() == ANY_METHOD
+}
+
+// The size limit the regex crate gives compiled patterns unless told otherwise.
+const DEFAULT_SIZE_LIMIT: usize = 10 * (1 << 20);
+
+// Anchor the pattern at the start and end so routes only match exactly.
+fn anchor(route: &str) -> String {
+    [r"\A", route, r"\z"].join("")
+}
+
+// The default 404 handler.
+fn default_not_found<B: RouteBody>(_: Request<B>, _: Captures) -> Response<B> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("Not Found").into())
+        .unwrap()
+}
+
+// Requests whose deadline passed before their handler was called get this.
+fn gateway_timeout<B: RouteBody>() -> Response<B> {
+    Response::builder()
+        .status(StatusCode::GATEWAY_TIMEOUT)
+        .body(Body::from("Gateway Timeout").into())
+        .unwrap()
+}
+
+// This handler will get fired when a URI matches a route but contains the wrong method.
+fn not_allowed<B: RouteBody>(_: Request<B>, _: &[Method]) -> Response<B> {
+    Response::builder()
+        .status(StatusCode::METHOD_NOT_ALLOWED)
+        .body(Body::from("Method Not Allowed").into())
+        .unwrap()
+}
+
+// Requests declaring a body over their route's limit get this.
+fn payload_too_large<B: RouteBody>() -> Response<B> {
+    Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .body(Body::from("Payload Too Large").into())
+        .unwrap()
+}
+
+// Requests whose body no route for their path takes get this.
+fn unsupported_media_type<B: RouteBody>() -> Response<B> {
+    Response::builder()
+        .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        .body(Body::from("Unsupported Media Type").into())
+        .unwrap()
+}
+
+// Requests that accept none of the media types a path's routes produce get this.
+fn not_acceptable<B: RouteBody>(_: Request<B>, _: &[&str]) -> Response<B> {
+    Response::builder()
+        .status(StatusCode::NOT_ACCEPTABLE)
+        .body(Body::from("Not Acceptable").into())
+        .unwrap()
+}
+
+// The answer to OPTIONS requests for paths without an OPTIONS route.
+fn options_response<B: RouteBody>(allowed: &[Method]) -> Response<B> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(ALLOW, allow(allowed))
+        .body(Body::empty().into())
+        .unwrap()
+}
+
+fn allow(methods: &[Method]) -> String {
+    let methods: Vec<&str> = methods.iter().map(Method::as_str).collect();
+    methods.join(", ")
+}
+
+#[test]
+fn bad_regular_expression() {
+    fn test_handler(_: Request<Body>, _: Captures) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .body("Ok".into())
+            .unwrap()
     }
     let mut router = RouterBuilder::new();
     router.route(Method::GET, r"/[", test_handler);
     let e = router.finalize();
     assert!(e.is_err());
 }
+
+#[test]
+fn metrics_are_keyed_by_pattern() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    router.handle(testing::get("/users/1"));
+    router.handle(testing::get("/users/2"));
+    router.handle(testing::post("/users/3", ""));
+    router.handle(testing::get("/missing"));
+
+    let metrics = router.metrics();
+    let route = &metrics.routes[0];
+    assert_eq!(route.pattern, r"/users/(\d+)");
+    assert_eq!(route.requests, 2);
+    assert_eq!(route.status_classes[1], 2);
+    assert_eq!(route.latency.count, 2);
+    assert!(route.last_used.is_some());
+    assert_eq!(metrics.not_found, 1);
+    assert_eq!(metrics.method_not_allowed, 1);
+}
+
+#[test]
+fn drained_waits_for_in_flight_handlers() {
+    use std::sync::mpsc;
+    use std::thread;
+
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = std::sync::Mutex::new(release_rx);
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/slow", move |_, _| {
+        entered_tx.send(()).unwrap();
+        release_rx.lock().unwrap().recv().unwrap();
+        Response::new(Body::empty())
+    });
+    let router = Arc::new(builder.finalize().unwrap());
+
+    let worker = {
+        let router = Arc::clone(&router);
+        thread::spawn(move || router.handle(testing::get("/slow")))
+    };
+    entered_rx.recv().unwrap();
+    assert_eq!(router.metrics().in_flight, 1);
+    assert_eq!(router.metrics().routes[0].in_flight, 1);
+
+    release_tx.send(()).unwrap();
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(router.drained());
+    assert_eq!(router.metrics().in_flight, 0);
+    worker.join().unwrap();
+}
+
+#[test]
+fn slow_requests_redact_captures() {
+    use std::sync::Mutex;
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/tokens/(\w+)",
+        RouteOptions::new()
+            .slow_threshold(Duration::from_millis(0))
+            .redact_captures(),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.get(r"/fast", |_, _| Response::new(Body::empty()));
+    {
+        let reported = Arc::clone(&reported);
+        builder.on_slow_request(move |slow| {
+            reported
+                .lock()
+                .unwrap()
+                .push((slow.route.to_owned(), slow.captures.join(",")));
+        });
+    }
+    let router = builder.finalize().unwrap();
+
+    router.handle(testing::get("/tokens/secret"));
+    router.handle(testing::get("/fast"));
+    assert_eq!(
+        *reported.lock().unwrap(),
+        vec![(
+            r"/tokens/(\w+)".to_owned(),
+            format!("{},{}", REDACTED, REDACTED)
+        )]
+    );
+}
+
+#[test]
+fn routing_decisions_report_shadowed_routes() {
+    use std::sync::Mutex;
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let mut builder = RouterBuilder::new();
+    builder.post(r"/users/(.*)", |_, _| Response::new(Body::empty()));
+    builder.get(r"/users/(.*)", |_, _| Response::new(Body::empty()));
+    builder.get(r"/users/new", |_, _| Response::new(Body::empty()));
+    {
+        let reported = Arc::clone(&reported);
+        builder.sample_routing_decisions(2, move |decision| {
+            let results: Vec<MatchResult> = decision.matched.iter().map(|m| m.result).collect();
+            reported.lock().unwrap().push((decision.outcome, results));
+        });
+    }
+    let router = builder.finalize().unwrap();
+
+    router.handle(testing::get("/users/new"));
+    router.handle(testing::get("/users/new"));
+    router.handle(testing::get("/missing"));
+    assert_eq!(
+        *reported.lock().unwrap(),
+        vec![
+            (
+                Outcome::Dispatched(1),
+                vec![
+                    MatchResult::MethodMismatch,
+                    MatchResult::Dispatched,
+                    MatchResult::Shadowed
+                ]
+            ),
+            (Outcome::NotFound, vec![]),
+        ]
+    );
+}
+
+#[test]
+fn counts_response_bytes() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/fixed", |_, _| Response::new("hello".into()));
+    builder.get(r"/stream", |_, _| {
+        let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("ab"), Ok("cde")];
+        Response::new(Body::wrap_stream(futures_util::stream::iter(chunks)))
+    });
+    let router = builder.finalize().unwrap();
+
+    router.handle(testing::get("/fixed"));
+    let response = router.handle(testing::get("/stream"));
+    let body = testing::body(response);
+    assert_eq!(body, "abcde");
+
+    let metrics = router.metrics();
+    assert_eq!(metrics.routes[0].bytes_out, 5);
+    assert_eq!(metrics.routes[1].bytes_out, 5);
+}
+
+#[cfg(all(test, feature = "macros"))]
+#[get("/users/{id}")]
+fn show_user(_: Request<Body>, c: Captures) -> Response<Body> {
+    Response::new(c[1].to_owned().into())
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn attribute_macros_register_routes() {
+    let router = collect_routes![show_user].finalize().unwrap();
+
+    assert_eq!(router.metrics().routes[0].pattern, "/users/(?P<id>[^/]+)");
+    let response = router.handle(testing::get("/users/7"));
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn normalizes_trailing_slashes() {
+    let mut builder = RouterBuilder::new();
+    builder.normalize_trailing_slash(true);
+    builder.get(r"/users/(\d+)", |_, c| {
+        Response::new(c[1].to_owned().into())
+    });
+    builder.get(r"/docs/", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    for path in &["/users/1", "/users/1/", "/docs/"] {
+        assert_eq!(router.handle(testing::get(path)).status(), StatusCode::OK);
+    }
+    assert_eq!(
+        router.handle(testing::get("/users/1//")).status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        router.explain(&Method::GET, "/users/1/").captures,
+        vec!["/users/1", "1"]
+    );
+}
+
+#[test]
+fn any_routes_match_every_method() {
+    let mut builder = RouterBuilder::new();
+    builder.post(r"/proxy/.*", |_, _| Response::new("post".into()));
+    builder.any(r"/proxy/.*", |_, _| Response::new("any".into()));
+    let router = builder.finalize().unwrap();
+
+    for method in &[Method::GET, Method::PUT, Method::DELETE] {
+        let req = testing::request(method.clone(), "/proxy/a", Body::empty());
+        assert_eq!(router.handle(req).status(), StatusCode::OK);
+    }
+    match router.explain(&Method::POST, "/proxy/a").outcome {
+        Outcome::Dispatched(index) => assert_eq!(index, 0),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert_eq!(router.to_string(), "POST    /proxy/.*\n*       /proxy/.*\n");
+}
+
+#[test]
+fn answers_options_requests() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.delete(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.put(r"/users/.*", |_, _| Response::new(Body::empty()));
+    builder.options(r"/files", |_, _| Response::new("files".into()));
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::request(Method::OPTIONS, "/users/1", Body::empty()));
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()[ALLOW], "GET, DELETE, PUT, OPTIONS");
+    let response = router.handle(testing::post("/users/1", ""));
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "GET, DELETE, PUT, OPTIONS");
+    // Routes for OPTIONS itself still win.
+    let response = router.handle(testing::request(Method::OPTIONS, "/files", Body::empty()));
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn custom_method_not_allowed() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.delete(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.method_not_allowed(|req, allowed| {
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(allowed, [Method::GET, Method::DELETE, Method::OPTIONS]);
+        Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(format!("Try one of {} methods", allowed.len()).into())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::post("/users/1", ""));
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "GET, DELETE, OPTIONS");
+}
+
+#[test]
+fn guards_choose_routes() {
+    let mut builder = RouterBuilder::new();
+    builder.get_guarded(
+        r"/admin/.*",
+        |req| req.headers().contains_key("x-admin"),
+        |_, _| Response::new("admin".into()),
+    );
+    builder.get(r"/admin/.*", |_, _| {
+        Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::empty())
+            .unwrap()
+    });
+    builder.get_guarded(r"/beta", |_| false, |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/admin/users");
+    req.headers_mut().insert("x-admin", "1".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+    assert_eq!(
+        router.handle(testing::get("/admin/users")).status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        router.handle(testing::get("/beta")).status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn routes_share_patterns() {
+    let mut builder = RouterBuilder::new();
+    builder.methods(&[Method::GET, Method::POST], r"/form", |req, _| {
+        Response::new(req.method().as_str().to_owned().into())
+    });
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.delete(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(router.routes.len(), 2);
+    assert_eq!(router.sources.len(), 4);
+    assert_eq!(
+        router.handle(testing::get("/form")).status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        router.handle(testing::post("/form", "")).status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        router
+            .handle(testing::request(Method::PUT, "/form", Body::empty()))
+            .status(),
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    assert_eq!(
+        router
+            .handle(testing::request(Method::DELETE, "/users/1", Body::empty()))
+            .status(),
+        StatusCode::OK
+    );
+}
+
+#[test]
+fn configures_patterns() {
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/notes/(.+)",
+        RouteOptions::new().dot_matches_new_line(true),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.route_with(
+        Method::GET,
+        r"
+        /users/(\d+)  # the id
+        /posts",
+        RouteOptions::new()
+            .ignore_whitespace(true)
+            .size_limit(1 << 20),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    assert!(router.patterns[0].is_match("/notes/a\nb"));
+    assert_eq!(
+        router.handle(testing::get("/users/1/posts")).status(),
+        StatusCode::OK
+    );
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/(\w{1000})",
+        RouteOptions::new().size_limit(1 << 10),
+        |_, _| Response::new(Body::empty()),
+    );
+    assert!(builder.finalize().is_err());
+}
+
+#[test]
+fn rejects_duplicate_routes() {
+    fn handler(_: Request<Body>, _: Captures) -> Response<Body> {
+        Response::new(Body::empty())
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", handler);
+    builder.post(r"/users/(\d+)", handler);
+    builder.get(r"/users/(\d+)", handler);
+    match builder.finalize() {
+        Err(Error::DuplicateRoute { method, pattern }) => {
+            assert_eq!(method, Method::GET);
+            assert_eq!(pattern, r"/users/(\d+)");
+        }
+        _ => panic!("duplicate route accepted"),
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.any(r"/proxy/.*", handler);
+    builder.get(r"/proxy/.*", handler);
+    assert!(builder.finalize().is_err());
+
+    // Routes that can turn requests away leave some for later ones.
+    let mut builder = RouterBuilder::new();
+    builder.get_guarded(r"/", |_| false, handler);
+    builder.get(r"/", handler);
+    assert!(builder.finalize().is_ok());
+}
+
+#[test]
+fn matches_literal_routes_directly() {
+    fn handler(name: &'static str) -> impl Fn(Request<Body>, Captures) -> Response<Body> {
+        move |_, _| Response::new(name.into())
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(me|\d+)", handler("user"));
+    builder.get(r"/users/me", handler("me"));
+    builder.post(r"/users/new", handler("new"));
+    builder.get(r"/about\.html", handler("about"));
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(router.literals.len(), 3);
+    assert_eq!(router.literals["/users/me"], [0, 1]);
+    assert_eq!(router.matching_routes("/about.html"), [3]);
+    assert_eq!(router.matching_routes("/aboutxhtml"), Vec::<usize>::new());
+    assert_eq!(
+        router.handle(testing::get("/users/new")).status(),
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    assert_eq!(
+        router.explain(&Method::GET, "/users/me").outcome,
+        Outcome::Dispatched(0)
+    );
+}
+
+#[test]
+fn routes_extension_methods() {
+    let mut builder = RouterBuilder::new();
+    builder.route_custom("PROPFIND", r"/dav/.*", |req, _| {
+        Response::new(req.method().as_str().to_owned().into())
+    });
+    builder.route_custom("MKCOL", r"/dav/.*", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let response = router.handle(testing::request(propfind, "/dav/a", Body::empty()));
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.handle(testing::get("/dav/a"));
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "PROPFIND, MKCOL, OPTIONS");
+}
+
+#[test]
+fn switches_routes_off() {
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/checkout",
+        RouteOptions::new().name("checkout_v2"),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/beta",
+        RouteOptions::new().name("beta"),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.get(r"/checkout", |_, _| {
+        Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .body(Body::empty())
+            .unwrap()
+    });
+    builder.disabled(|_, _| {
+        Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::empty())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    assert!(router.set_enabled("checkout_v2", false));
+    assert!(router.set_enabled("beta", false));
+    assert!(!router.set_enabled("missing", false));
+    assert_eq!(router.is_enabled("beta"), Some(false));
+    assert_eq!(
+        router.handle(testing::get("/checkout")).status(),
+        StatusCode::ACCEPTED
+    );
+    assert_eq!(
+        router.handle(testing::get("/beta")).status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    router.set_enabled("checkout_v2", true);
+    assert_eq!(
+        router.handle(testing::get("/checkout")).status(),
+        StatusCode::OK
+    );
+}
+
+#[test]
+fn head_requests_fall_back_to_get() {
+    let mut builder = RouterBuilder::new();
+    builder.head_fallback(true);
+    builder.get(r"/users", |req, _| {
+        assert_eq!(req.method(), Method::HEAD);
+        Response::builder()
+            .header("x-count", "2")
+            .body("alice, bob".into())
+            .unwrap()
+    });
+    builder.get(r"/files", |_, _| Response::new(Body::empty()));
+    builder.route(Method::HEAD, r"/files", |_, _| {
+        Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::request(Method::HEAD, "/users", Body::empty()));
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-count"], "2");
+    assert_eq!(response.headers()[CONTENT_LENGTH], "10");
+    let body = testing::body(response);
+    assert!(body.is_empty());
+    // Routes for HEAD itself still win.
+    let response = router.handle(testing::request(Method::HEAD, "/files", Body::empty()));
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(matches!(
+        router.explain(&Method::HEAD, "/users").outcome,
+        Outcome::Dispatched(0)
+    ));
+}
+
+#[test]
+fn routes_by_priority() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\w+)", |_, _| Response::new("user".into()));
+    builder.route_with(
+        Method::GET,
+        r"/users/me",
+        RouteOptions::new().priority(10),
+        |_, _| Response::new("me".into()),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/users/.*",
+        RouteOptions::new().priority(-1),
+        |_, _| Response::new("fallback".into()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let sources: Vec<&str> = router.sources.iter().map(String::as_str).collect();
+    assert_eq!(sources, vec![r"/users/me", r"/users/(\w+)", r"/users/.*"]);
+    match router.explain(&Method::GET, "/users/me").outcome {
+        Outcome::Dispatched(index) => assert_eq!(index, 0),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+}
+
+#[test]
+fn matches_the_path_only_by_default() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/search", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+    assert_eq!(
+        router.handle(testing::get("/search?q=rust")).status(),
+        StatusCode::OK
+    );
+
+    let mut builder = RouterBuilder::new();
+    builder.match_full_uri(true);
+    builder.get(r"/search\?q=(\w+)", |_, c| {
+        Response::new(c[1].to_owned().into())
+    });
+    let router = builder.finalize().unwrap();
+    assert_eq!(
+        router.handle(testing::get("/search?q=rust")).status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        router.handle(testing::get("/search")).status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn matches_without_case() {
+    let mut builder = RouterBuilder::new();
+    builder.case_insensitive(true);
+    builder.get(r"/users/(\d+)|/people/(\d+)", |_, c| {
+        Response::new(c.get(1).or_else(|| c.get(2)).unwrap().to_owned().into())
+    });
+    builder.route_with(
+        Method::GET,
+        r"/Exact",
+        RouteOptions::new().case_insensitive(false),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.handle(testing::get("/USERS/42")).status(),
+        StatusCode::OK
+    );
+    // The flag covers the whole pattern, not just its first alternative.
+    assert_eq!(
+        router.handle(testing::get("/People/42")).status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        router.handle(testing::get("/users/42/x")).status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        router.handle(testing::get("/exact")).status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn mounts_builders_under_prefixes() {
+    let mut users = RouterBuilder::new();
+    users.get(r"/users/(\d+)", |_, c| {
+        Response::new(c[1].to_owned().into())
+    });
+    users.openapi(r"/openapi\.json", OpenApi::new("Users", "1.0"));
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/", |_, _| Response::new(Body::empty()));
+    builder.mount("/api/", users);
+    let router = builder.finalize().unwrap();
+
+    let sources: Vec<&str> = router.sources.iter().map(String::as_str).collect();
+    assert_eq!(
+        sources,
+        vec![r"/", r"/api/users/(\d+)", r"/api/openapi\.json"]
+    );
+    assert_eq!(
+        router.handle(testing::get("/users/1")).status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        router.handle(testing::get("/api/users/1")).status(),
+        StatusCode::OK
+    );
+    // Built in handlers of the mounted builder see the whole router.
+    assert_eq!(
+        router.handle(testing::get("/api/openapi.json")).status(),
+        StatusCode::OK
+    );
+}
+
+#[test]
+fn builds_in_one_expression() {
+    let router = RouterBuilder::new()
+        .get(r"/users/(\d+)", |_, _| Response::new(Body::empty()))
+        .post(r"/users", |_, _| Response::new(Body::empty()))
+        .build()
+        .unwrap();
+
+    let response = router.handle(testing::post("/users", ""));
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn collects_generated_routes() {
+    let redirects = vec![("/old", "/new"), ("/older", "/new")];
+    let builder: RouterBuilder = redirects
+        .into_iter()
+        .map(|(from, to)| {
+            let handler: RouteHandler = Box::new(move |_, _| {
+                Response::builder()
+                    .status(StatusCode::MOVED_PERMANENTLY)
+                    .header(hyper::header::LOCATION, to)
+                    .body(Body::empty())
+                    .unwrap()
+            });
+            (Method::GET, from, handler)
+        })
+        .collect();
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/older"));
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+}
+
+#[test]
+fn collects_routes_returning_any_response() {
+    type Plain = fn(Request<Body>, Captures) -> (StatusCode, &'static str);
+
+    fn gone(_: Request<Body>, _: Captures) -> (StatusCode, &'static str) {
+        (StatusCode::GONE, "gone")
+    }
+
+    fn ok(_: Request<Body>, _: Captures) -> &'static str {
+        "ok"
+    }
+
+    let mut builder: RouterBuilder = vec![(Method::GET, "/old", gone as Plain)]
+        .into_iter()
+        .collect();
+    builder.extend(vec![(Method::GET, "/new", ok)]);
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.handle(testing::get("/old")).status(),
+        StatusCode::GONE
+    );
+    let response = router.handle(testing::get("/new"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = testing::body(response);
+    assert_eq!(body, "ok");
+}
+
+#[test]
+fn request_bodies_stream_through() {
+    use hyper::body::HttpBody;
+
+    let recorder = Recorder::new(|_| {}).max_body(4);
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::POST,
+        r"/upload",
+        RouteOptions::new()
+            .record(recorder)
+            .body_timeout(Duration::from_secs(60)),
+        |req, _| Response::new(req.into_body()),
+    );
+    let router = builder.finalize().unwrap();
+
+    // The handler gets the request before any of its body has been sent.
+    let (mut sender, body) = Body::channel();
+    let mut body = router.handle(testing::post("/upload", body)).into_body();
+
+    // Nothing reads ahead so the client can't get more than a chunk in.
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    assert!(sender.try_send_data("one".into()).is_ok());
+    assert!(sender.try_send_data("two".into()).is_err());
+    let chunk = runtime.block_on(body.data()).unwrap().unwrap();
+    assert_eq!(chunk, "one");
+
+    assert!(sender.try_send_data("two".into()).is_ok());
+    let chunk = runtime.block_on(body.data()).unwrap().unwrap();
+    assert_eq!(chunk, "two");
+    drop(sender);
+    assert!(runtime.block_on(body.data()).is_none());
+}
+
+#[test]
+fn merges_builders() {
+    let mut users = RouterBuilder::new();
+    users.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    users.not_found(|_, _| {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("users".into())
+            .unwrap()
+    });
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/", |_, _| Response::new(Body::empty()));
+    builder.merge(users);
+    let router = builder.finalize().unwrap();
+
+    let sources: Vec<&str> = router.sources.iter().map(String::as_str).collect();
+    assert_eq!(sources, vec![r"/", r"/users/(\d+)"]);
+    assert_eq!(
+        router.handle(testing::get("/users/1")).status(),
+        StatusCode::OK
+    );
+    let response = router.handle(testing::get("/missing"));
+    let body = testing::body(response);
+    assert_eq!(body, "users");
+}
+
+#[test]
+fn removes_and_replaces_routes() {
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users", |_, _| Response::new("default".into()));
+    builder.post(r"/users", |_, _| Response::new(Body::empty()));
+    builder.get(r"/admin", |_, _| Response::new(Body::empty()));
+    builder.remove(Method::GET, r"/admin");
+    builder.replace(Method::GET, r"/users", |_, _| {
+        Response::new("custom".into())
+    });
+    builder.replace(Method::GET, r"/health", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let sources: Vec<&str> = router.sources.iter().map(String::as_str).collect();
+    assert_eq!(sources, vec![r"/users", r"/users", r"/health"]);
+    assert_eq!(
+        router.handle(testing::get("/admin")).status(),
+        StatusCode::NOT_FOUND
+    );
+    let response = router.handle(testing::get("/users"));
+    let body = testing::body(response);
+    assert_eq!(body, "custom");
+}
@@ -0,0 +1,235 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures_core::Stream;
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, CONTENT_LENGTH};
+use hyper::{Body, StatusCode};
+use tokio::time::Delay;
+
+/// The error a request body gives when the client takes longer to send it
+/// than the route's `RouteOptions::body_timeout` allows.
+///
+/// Handlers read request bodies themselves so the router can't answer for
+/// them. Instead the body ends with this error, which handlers can check
+/// for with `is_cause_of` and answer with `status`.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyTimeout;
+
+impl BodyTimeout {
+    /// Whether an error from reading a request body was caused by a timeout.
+    /// The whole chain of causes is checked since the body may have been
+    /// wrapped on its way to the handler.
+    pub fn is_cause_of(error: &hyper::Error) -> bool {
+        caused_by::<BodyTimeout>(error)
+    }
+
+    /// The status to respond with, 408 Request Timeout.
+    pub fn status() -> StatusCode {
+        StatusCode::REQUEST_TIMEOUT
+    }
+}
+
+impl fmt::Display for BodyTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out reading the request body")
+    }
+}
+
+impl StdError for BodyTimeout {}
+
+/// The error a request body gives when the client sends more of it than the
+/// route's `RouteOptions::max_body_size` allows.
+///
+/// Requests whose `Content-Length` is already too large are answered with
+/// `413 Payload Too Large` without calling the handler. Bodies without a
+/// length are cut off with this error once they pass the limit, which
+/// handlers can check for with `is_cause_of` and answer with `status`.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyTooLarge;
+
+impl BodyTooLarge {
+    /// Whether an error from reading a request body was caused by it being
+    /// too large.
+    pub fn is_cause_of(error: &hyper::Error) -> bool {
+        caused_by::<BodyTooLarge>(error)
+    }
+
+    /// The status to respond with, 413 Payload Too Large.
+    pub fn status() -> StatusCode {
+        StatusCode::PAYLOAD_TOO_LARGE
+    }
+}
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "request body too large")
+    }
+}
+
+impl StdError for BodyTooLarge {}
+
+// Whether an `E` is anywhere in the chain of causes of `error`.
+fn caused_by<E: StdError + 'static>(error: &hyper::Error) -> bool {
+    let mut cause = error.source();
+    while let Some(error) = cause {
+        if error.is::<E>() {
+            return true;
+        }
+        cause = error.source();
+    }
+    false
+}
+
+// Fail `body` if it hasn't finished by `deadline`. The body still streams
+// through as it arrives rather than being collected first.
+pub(crate) fn timeout(body: Body, deadline: Instant) -> Body {
+    Body::wrap_stream(Deadline {
+        body,
+        deadline,
+        delay: None,
+        expired: false,
+    })
+}
+
+// Fail `body` once more than `max` bytes of it have arrived.
+pub(crate) fn limit(body: Body, max: u64) -> Body {
+    Body::wrap_stream(Limit {
+        body,
+        remaining: max,
+        exceeded: false,
+    })
+}
+
+// Whether the `Content-Length` of a request says its body is over `max`.
+pub(crate) fn too_large(headers: &HeaderMap, max: u64) -> bool {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<u64>().ok())
+        .is_some_and(|length| length > max)
+}
+
+struct Limit {
+    body: Body,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl Stream for Limit {
+    type Item = Result<Bytes, Box<dyn StdError + Send + Sync>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.exceeded {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut self.body).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => match self.remaining.checked_sub(chunk.len() as u64) {
+                Some(remaining) => {
+                    self.remaining = remaining;
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                None => {
+                    self.exceeded = true;
+                    Poll::Ready(Some(Err(Box::new(BodyTooLarge))))
+                }
+            },
+            Poll::Ready(chunk) => Poll::Ready(chunk.map(|chunk| chunk.map_err(Into::into))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+struct Deadline {
+    body: Body,
+    deadline: Instant,
+    // Created on the first poll since it needs the runtime's timer, which
+    // isn't around when the router is called outside of one.
+    delay: Option<Pin<Box<Delay>>>,
+    expired: bool,
+}
+
+impl Stream for Deadline {
+    type Item = Result<Bytes, Box<dyn StdError + Send + Sync>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if self.expired {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(chunk) = Pin::new(&mut self.body).poll_next(cx) {
+            return Poll::Ready(chunk.map(|chunk| chunk.map_err(Into::into)));
+        }
+
+        let deadline = tokio::time::Instant::from_std(self.deadline);
+        let delay = self
+            .delay
+            .get_or_insert_with(|| Box::pin(tokio::time::delay_until(deadline)));
+        match delay.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.expired = true;
+                Poll::Ready(Some(Err(Box::new(BodyTimeout))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[test]
+fn slow_bodies_time_out() {
+    use std::time::Duration;
+
+    use hyper::{Method, Response};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::POST,
+        r"/upload",
+        RouteOptions::new().body_timeout(Duration::from_millis(10)),
+        |req, _| Response::new(req.into_body()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let (_sender, body) = Body::channel();
+    let response = router.handle(testing::post("/upload", body));
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let error = runtime
+        .block_on(hyper::body::to_bytes(response.into_body()))
+        .unwrap_err();
+    assert!(BodyTimeout::is_cause_of(&error));
+}
+
+#[test]
+fn large_bodies_are_refused() {
+    use hyper::{Method, Response};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::POST,
+        r"/comments",
+        RouteOptions::new().max_body_size(4),
+        |req, _| Response::new(req.into_body()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::post("/comments", "hello");
+    req.headers_mut()
+        .insert(CONTENT_LENGTH, "5".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut read = |body: &'static str| {
+        let response = router.handle(testing::post("/comments", body));
+        runtime.block_on(hyper::body::to_bytes(response.into_body()))
+    };
+    assert_eq!(read("hi").unwrap(), "hi");
+    assert!(BodyTooLarge::is_cause_of(&read("hello").unwrap_err()));
+}
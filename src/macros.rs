@@ -0,0 +1,115 @@
+/// Build a `RouterBuilder` from a list of methods, routes and handlers:
+///
+/// ```ignore
+/// let mut builder = router! {
+///     GET r"/users/(\d+)" => show_user,
+///     POST r"/users" => create_user,
+/// };
+/// builder.not_found(not_found);
+/// let router = builder.finalize()?;
+/// ```
+///
+/// Routes must be string literals. Using the same method and route twice or
+/// a method other than the standard ones fails to compile.
+#[macro_export]
+macro_rules! router {
+    ($($method:ident $route:literal => $handler:expr),* $(,)?) => {{
+        const _: () = assert!(
+            !$crate::__duplicate_route(&[$((stringify!($method), $route)),*]),
+            "router! has the same method and route more than once"
+        );
+        let mut builder = $crate::RouterBuilder::new();
+        $(builder.route($crate::__method!($method), $route, $handler);)*
+        builder
+    }};
+}
+
+// Only the standard methods have a rule so anything else, like a typo, is a
+// compile error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __method {
+    (GET) => {
+        $crate::__private::Method::GET
+    };
+    (POST) => {
+        $crate::__private::Method::POST
+    };
+    (PUT) => {
+        $crate::__private::Method::PUT
+    };
+    (PATCH) => {
+        $crate::__private::Method::PATCH
+    };
+    (DELETE) => {
+        $crate::__private::Method::DELETE
+    };
+    (HEAD) => {
+        $crate::__private::Method::HEAD
+    };
+    (OPTIONS) => {
+        $crate::__private::Method::OPTIONS
+    };
+    (CONNECT) => {
+        $crate::__private::Method::CONNECT
+    };
+    (TRACE) => {
+        $crate::__private::Method::TRACE
+    };
+}
+
+// Whether any method and route pair appears twice. This is evaluated at
+// compile time by `router!`, which is why it compares bytes by hand.
+#[doc(hidden)]
+pub const fn __duplicate_route(routes: &[(&str, &str)]) -> bool {
+    let mut i = 0;
+    while i < routes.len() {
+        let mut j = i + 1;
+        while j < routes.len() {
+            if equal(routes[i].0, routes[j].0) && equal(routes[i].1, routes[j].1) {
+                return true;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn equal(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[test]
+fn router_macro_registers_routes() {
+    use hyper::{Body, Response, StatusCode};
+
+    use crate::testing;
+
+    let router = router! {
+        GET r"/users/(\d+)" => |_, _| Response::new(Body::empty()),
+        POST r"/users" => |_, _| Response::new(Body::empty()),
+    }
+    .finalize()
+    .unwrap();
+
+    let response = router.handle(testing::post("/users", ""));
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(__duplicate_route(&[
+        ("GET", "/a"),
+        ("POST", "/a"),
+        ("GET", "/a")
+    ]));
+    assert!(!__duplicate_route(&[("GET", "/a"), ("POST", "/a")]));
+}
@@ -0,0 +1,68 @@
+use hyper::{Method, Request};
+
+const OVERRIDE_HEADER: &str = "x-http-method-override";
+const OVERRIDE_PARAM: &str = "_method";
+
+/// The method a request was sent with before `RouterBuilder::method_override`
+/// replaced it, added to the request's extensions when it does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverriddenMethod(pub Method);
+
+// Replace the method of a POST request with the one it asks for in its
+// `X-HTTP-Method-Override` header or `_method` query parameter. Only PUT,
+// PATCH and DELETE can be asked for, so a form can't be turned into a
+// request that is meant to be safe.
+pub(crate) fn apply<B>(req: &mut Request<B>) {
+    if req.method() != Method::POST {
+        return;
+    }
+    let header = req
+        .headers()
+        .get(OVERRIDE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let param = req.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|&(name, _)| name == OVERRIDE_PARAM)
+            .map(|(_, value)| value)
+    });
+    let method = match header.or(param).map(str::trim) {
+        Some(method) if method.eq_ignore_ascii_case("put") => Method::PUT,
+        Some(method) if method.eq_ignore_ascii_case("patch") => Method::PATCH,
+        Some(method) if method.eq_ignore_ascii_case("delete") => Method::DELETE,
+        _ => return,
+    };
+    let original = std::mem::replace(req.method_mut(), method);
+    req.extensions_mut().insert(OverriddenMethod(original));
+}
+
+#[test]
+fn overrides_methods() {
+    use hyper::{Body, Response, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.method_override(true);
+    builder.delete(r"/posts/(\d+)", |req, _| {
+        assert_eq!(
+            req.extensions().get::<OverriddenMethod>(),
+            Some(&OverriddenMethod(Method::POST))
+        );
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::post("/posts/1", "");
+    req.headers_mut()
+        .insert(OVERRIDE_HEADER, "DELETE".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+    let req = testing::post("/posts/1?_method=delete", "");
+    assert_eq!(router.handle(req).status(), StatusCode::OK);
+    // Only unsafe methods can be asked for.
+    let req = testing::post("/posts/1?_method=GET", "");
+    assert_eq!(router.handle(req).status(), StatusCode::METHOD_NOT_ALLOWED);
+    let req = testing::get("/posts/1?_method=delete");
+    assert_eq!(router.handle(req).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
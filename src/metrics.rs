@@ -0,0 +1,340 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyper::{Method, StatusCode};
+
+use crate::options::{Metadata, RouteOptions};
+use crate::slo::{SloStatus, SloTracker};
+
+// Upper bounds of the latency histogram buckets in microseconds. Anything slower
+// than the last bound is only reflected in the total count.
+const BUCKETS: [u64; 12] = [
+    1_000, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000,
+    5_000_000, 10_000_000,
+];
+
+/// A point in time copy of the metrics a `Router` has collected.
+#[derive(Clone, Debug)]
+pub struct RouterMetrics {
+    /// Metrics for every route, in the order they were registered.
+    pub routes: Vec<RouteMetrics>,
+    /// The number of requests that did not match any route.
+    pub not_found: u64,
+    /// The number of requests that matched a route but not its method.
+    pub method_not_allowed: u64,
+    /// The number of handlers currently executing.
+    pub in_flight: u64,
+    /// How long the router has existed, for turning the totals into rates.
+    pub uptime: Duration,
+}
+
+/// Metrics for a single route. These are keyed by the route pattern rather
+/// than the request URI so the number of series stays bounded.
+#[derive(Clone, Debug)]
+pub struct RouteMetrics {
+    /// The method the route was registered for.
+    pub method: Method,
+    /// The pattern the route was registered with.
+    pub pattern: String,
+    /// The name given to the route with `RouteOptions::name`.
+    pub name: Option<String>,
+    /// The tags given to the route with `RouteOptions::tag`.
+    pub tags: Vec<String>,
+    /// The number of requests dispatched to the route's handler.
+    pub requests: u64,
+    /// The number of requests the route's handler is currently executing.
+    pub in_flight: u64,
+    /// The number of response body bytes written by the route.
+    pub bytes_out: u64,
+    /// When the route's handler was last called, if it has been.
+    pub last_used: Option<SystemTime>,
+    /// Response counts by status class, from 1xx at index 0 to 5xx at index 4.
+    pub status_classes: [u64; 5],
+    /// How long the route's handler took to return a response.
+    pub latency: LatencyHistogram,
+    /// Compliance with the route's objective, if it has one.
+    pub slo: Option<SloStatus>,
+}
+
+/// A cumulative histogram of handler latencies.
+#[derive(Clone, Debug)]
+pub struct LatencyHistogram {
+    /// Pairs of an upper bound and the number of observations at or below it.
+    pub buckets: Vec<(Duration, u64)>,
+    /// The number of observations, including any slower than the last bucket.
+    pub count: u64,
+    /// The total of all observed latencies, kept in whole microseconds.
+    pub sum: Duration,
+}
+
+impl RouterMetrics {
+    /// The response bytes per second written by each route between an
+    /// `earlier` snapshot and this one, in registration order.
+    pub fn bytes_out_rates(&self, earlier: &RouterMetrics) -> Vec<f64> {
+        let elapsed = self
+            .uptime
+            .checked_sub(earlier.uptime)
+            .unwrap_or_default()
+            .as_secs_f64();
+        self.routes
+            .iter()
+            .zip(&earlier.routes)
+            .map(|(now, then)| {
+                if elapsed == 0.0 {
+                    0.0
+                } else {
+                    now.bytes_out.saturating_sub(then.bytes_out) as f64 / elapsed
+                }
+            })
+            .collect()
+    }
+}
+
+// The live counters behind a `Router`. Everything is atomic so handlers can
+// run concurrently without any locking.
+pub(crate) struct Metrics {
+    routes: Vec<RouteStats>,
+    not_found: AtomicU64,
+    method_not_allowed: AtomicU64,
+    in_flight: AtomicU64,
+    // Tasks waiting on `Router::drained` for the in flight count to reach zero.
+    drain_waiters: Mutex<Vec<Waker>>,
+    created: Instant,
+}
+
+struct RouteStats {
+    method: Method,
+    pattern: String,
+    metadata: Metadata,
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+    bytes_out: AtomicU64,
+    // Milliseconds since the Unix epoch, or zero if the route was never used.
+    last_used: AtomicU64,
+    status_classes: [AtomicU64; 5],
+    buckets: [AtomicU64; 12],
+    sum_micros: AtomicU64,
+    slo: Option<SloTracker>,
+}
+
+impl Metrics {
+    pub(crate) fn new<'a, I, B: 'a>(routes: I) -> Metrics
+    where
+        I: Iterator<Item = (&'a Method, &'a str, &'a RouteOptions<B>)>,
+    {
+        Metrics {
+            routes: routes
+                .map(|(method, pattern, options)| RouteStats {
+                    method: method.clone(),
+                    pattern: pattern.to_owned(),
+                    metadata: options.metadata.clone(),
+                    requests: AtomicU64::default(),
+                    in_flight: AtomicU64::default(),
+                    bytes_out: AtomicU64::default(),
+                    last_used: AtomicU64::default(),
+                    status_classes: Default::default(),
+                    buckets: Default::default(),
+                    sum_micros: AtomicU64::default(),
+                    slo: options.slo.clone().map(SloTracker::new),
+                })
+                .collect(),
+            not_found: AtomicU64::default(),
+            method_not_allowed: AtomicU64::default(),
+            in_flight: AtomicU64::default(),
+            drain_waiters: Mutex::default(),
+            created: Instant::now(),
+        }
+    }
+
+    // Mark the handler at `index` as running until the returned guard is dropped.
+    pub(crate) fn start(&self, index: usize) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.routes[index].in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight {
+            metrics: self,
+            index,
+        }
+    }
+
+    fn finish(&self, index: usize) {
+        self.routes[index].in_flight.fetch_sub(1, Ordering::Relaxed);
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            for waker in self.drain_waiters.lock().unwrap().drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    // Record a handled request. If the request pushed the route's error budget
+    // burn over its alert rate, the route's objective status is returned.
+    pub(crate) fn record(
+        &self,
+        index: usize,
+        status: StatusCode,
+        elapsed: Duration,
+    ) -> Option<SloStatus> {
+        let stats = &self.routes[index];
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        stats
+            .last_used
+            .fetch_max(now.as_millis() as u64, Ordering::Relaxed);
+
+        let class = (status.as_u16() / 100) as usize;
+        if (1..=5).contains(&class) {
+            stats.status_classes[class - 1].fetch_add(1, Ordering::Relaxed);
+        }
+
+        let micros = elapsed.as_micros() as u64;
+        stats.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        if let Some(bucket) = BUCKETS.iter().position(|&bound| micros <= bound) {
+            stats.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+
+        stats
+            .slo
+            .as_ref()
+            .and_then(|slo| slo.record(status, elapsed))
+    }
+
+    // Every route's method, pattern and metadata in registration order.
+    pub(crate) fn routes(&self) -> impl Iterator<Item = (&Method, &str, &Metadata)> {
+        self.routes
+            .iter()
+            .map(|stats| (&stats.method, stats.pattern.as_str(), &stats.metadata))
+    }
+
+    pub(crate) fn record_bytes_out(&self, index: usize, bytes: u64) {
+        self.routes[index]
+            .bytes_out
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_not_found(&self) {
+        self.not_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_method_not_allowed(&self) {
+        self.method_not_allowed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> RouterMetrics {
+        RouterMetrics {
+            routes: self.routes.iter().map(RouteStats::snapshot).collect(),
+            not_found: self.not_found.load(Ordering::Relaxed),
+            method_not_allowed: self.method_not_allowed.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            uptime: self.created.elapsed(),
+        }
+    }
+}
+
+// Decrements the in flight gauges when dropped, even if the handler panics.
+pub(crate) struct InFlight<'a> {
+    metrics: &'a Metrics,
+    index: usize,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics.finish(self.index);
+    }
+}
+
+/// A future that resolves once a `Router` has no handlers executing. It is
+/// returned by `Router::drained`.
+pub struct Drained<'a> {
+    pub(crate) metrics: &'a Metrics,
+}
+
+impl Future for Drained<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let metrics = self.metrics;
+        if metrics.in_flight.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+
+        // Check again while holding the lock so a handler finishing in between
+        // can't be missed.
+        let mut waiters = metrics.drain_waiters.lock().unwrap();
+        if metrics.in_flight.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        // A future polled again, such as from a `select!` loop, replaces its
+        // waker rather than adding another.
+        if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+            waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl RouteStats {
+    fn snapshot(&self) -> RouteMetrics {
+        let mut status_classes = [0; 5];
+        for (count, class) in status_classes.iter_mut().zip(&self.status_classes) {
+            *count = class.load(Ordering::Relaxed);
+        }
+
+        // The buckets are stored individually so make them cumulative here.
+        let mut total = 0;
+        let buckets = BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&bound, count)| {
+                total += count.load(Ordering::Relaxed);
+                (Duration::from_micros(bound), total)
+            })
+            .collect();
+
+        RouteMetrics {
+            method: self.method.clone(),
+            pattern: self.pattern.clone(),
+            name: self.metadata.name.clone(),
+            tags: self.metadata.tags.clone(),
+            requests: self.requests.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            last_used: match self.last_used.load(Ordering::Relaxed) {
+                0 => None,
+                millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+            },
+            status_classes,
+            latency: LatencyHistogram {
+                buckets,
+                count: self.requests.load(Ordering::Relaxed),
+                sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+            },
+            slo: self.slo.as_ref().map(SloTracker::status),
+        }
+    }
+}
+
+#[test]
+fn drained_keeps_one_waker_per_task() {
+    use futures_util::task::noop_waker;
+
+    let options: RouteOptions = RouteOptions::new();
+    let metrics = Metrics::new(std::iter::once((&Method::GET, "/", &options)));
+    let in_flight = metrics.start(0);
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut drained = Drained { metrics: &metrics };
+    for _ in 0..3 {
+        assert!(Pin::new(&mut drained).poll(&mut cx).is_pending());
+    }
+    assert_eq!(metrics.drain_waiters.lock().unwrap().len(), 1);
+
+    drop(in_flight);
+    assert!(metrics.drain_waiters.lock().unwrap().is_empty());
+    assert!(Pin::new(&mut drained).poll(&mut cx).is_ready());
+}
@@ -0,0 +1,153 @@
+use hyper::header::{HeaderValue, ACCEPT, CONTENT_TYPE, VARY};
+use hyper::{Request, Response};
+
+use crate::client_hints;
+
+// How much the request prefers `media`, such as `application/json`, from
+// zero for not at all to one. The most specific range in `Accept` that
+// covers the type decides, and requests without the header take anything.
+pub(crate) fn quality<B>(req: &Request<B>, media: &str) -> f32 {
+    let accept: Vec<&str> = req
+        .headers()
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if accept.is_empty() {
+        return 1.0;
+    }
+    let (kind, subtype) = split(media);
+
+    let mut best: Option<(u8, f32)> = None;
+    for range in accept.iter().flat_map(|value| value.split(',')) {
+        let mut params = range.split(';');
+        let (range_kind, range_subtype) = split(params.next().unwrap_or("").trim());
+        let specificity = match (range_kind, range_subtype) {
+            ("*", "*") => 0,
+            (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => 2,
+            _ => continue,
+        };
+        let q = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .and_then(|(_, q)| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        if best.is_none_or(|(most, _)| specificity > most) {
+            best = Some((specificity, q));
+        }
+    }
+    best.map_or(0.0, |(_, q)| q)
+}
+
+// Whether the request's body is of the media type `media` or one it covers.
+pub(crate) fn consumes<B>(req: &Request<B>, media: &str) -> bool {
+    let content_type = match req.headers().get(CONTENT_TYPE) {
+        Some(value) => value.to_str().unwrap_or(""),
+        None => return false,
+    };
+    let (kind, subtype) = split(content_type);
+    match split(media) {
+        ("*", "*") => true,
+        (k, "*") => k.eq_ignore_ascii_case(kind),
+        (k, s) => k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype),
+    }
+}
+
+// Mark a response from a route that was picked by its media type as
+// depending on `Accept`, and give it that type if the handler set none.
+pub(crate) fn apply<B>(media: &str, response: &mut Response<B>) {
+    let headers = response.headers_mut();
+    if !headers.contains_key(CONTENT_TYPE) {
+        if let Ok(value) = HeaderValue::from_str(media) {
+            headers.insert(CONTENT_TYPE, value);
+        }
+    }
+    client_hints::extend_list(headers, VARY.as_str(), &["Accept".to_owned()]);
+}
+
+fn split(media: &str) -> (&str, &str) {
+    let media = media.split(';').next().unwrap_or("").trim();
+    media.split_once('/').unwrap_or((media, ""))
+}
+
+#[test]
+fn negotiates_media_types() {
+    use hyper::{Body, Method, StatusCode};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/report",
+        RouteOptions::new().produces("application/json"),
+        |_, _| Response::new("{}".into()),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/report",
+        RouteOptions::new().produces("text/html"),
+        |_, _| Response::new("<p>".into()),
+    );
+    builder.not_acceptable(|_, available| {
+        assert_eq!(available, ["application/json", "text/html"]);
+        Response::builder()
+            .status(StatusCode::NOT_ACCEPTABLE)
+            .body(Body::empty())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    let send = |accept: Option<&str>| {
+        let mut req = testing::get("/report");
+        if let Some(accept) = accept {
+            req.headers_mut().insert(ACCEPT, accept.parse().unwrap());
+        }
+        router.handle(req)
+    };
+    let response = send(None);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    assert_eq!(response.headers()[VARY], "Accept");
+    let response = send(Some("text/html"));
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/html");
+    let response = send(Some("application/json;q=0.5, text/*;q=0.9"));
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/html");
+    let response = send(Some("*/*, text/html;q=0"));
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    assert_eq!(send(Some("image/png")).status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[test]
+fn dispatches_by_content_type() {
+    use hyper::StatusCode;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.post_consuming(r"/upload", "multipart/form-data", |_, _| {
+        Response::new("multipart".into())
+    });
+    builder.post_consuming(r"/upload", "text/*", |_, _| Response::new("text".into()));
+    let router = builder.finalize().unwrap();
+
+    let send = |content_type: Option<&str>| {
+        let mut req = testing::post("/upload", "");
+        if let Some(content_type) = content_type {
+            req.headers_mut()
+                .insert(CONTENT_TYPE, content_type.parse().unwrap());
+        }
+        let response = router.handle(req);
+        let status = response.status();
+        let body = testing::body(response);
+        (status, body)
+    };
+    assert_eq!(send(Some("multipart/form-data; boundary=x")).1, "multipart");
+    assert_eq!(send(Some("text/csv")).1, "text");
+    assert_eq!(
+        send(Some("application/json")).0,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    assert_eq!(send(None).0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
@@ -0,0 +1,342 @@
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Method, Response, StatusCode};
+
+use crate::json::{array, object, quoted};
+use crate::metrics::Metrics;
+use crate::options::Metadata;
+use crate::Router;
+
+/// The API wide information for an OpenAPI document generated from a
+/// router's routes with `Router::openapi` or `RouterBuilder::openapi`.
+///
+/// Route patterns are turned into path templates on a best effort basis.
+/// Each capture group becomes a path parameter, named after the group if it
+/// has a name and `param1`, `param2` and so on if it doesn't. Other regular
+/// expression syntax outside of groups is dropped. Route names, tags,
+/// descriptions, schemas and deprecations set with `RouteOptions` fill in
+/// each operation.
+#[derive(Clone, Debug)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    servers: Vec<String>,
+}
+
+// A path parameter taken from a capture group.
+struct Parameter {
+    name: String,
+    pattern: String,
+}
+
+impl OpenApi {
+    /// Describe an API with a title and its version.
+    pub fn new(title: &str, version: &str) -> OpenApi {
+        OpenApi {
+            title: title.to_owned(),
+            version: version.to_owned(),
+            description: None,
+            servers: Vec::new(),
+        }
+    }
+
+    /// Describe the API as a whole.
+    pub fn description(mut self, description: &str) -> OpenApi {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    /// Add a base URL the API is served from. This can be called more than
+    /// once.
+    pub fn server(mut self, url: &str) -> OpenApi {
+        self.servers.push(url.to_owned());
+        self
+    }
+
+    pub(crate) fn document(&self, metrics: &Metrics) -> String {
+        // Operations are grouped by path, keeping the order routes were added.
+        let mut paths: Vec<(String, Vec<(&str, String)>)> = Vec::new();
+        for (method, pattern, metadata) in metrics.routes() {
+            let name = match method_name(method) {
+                Some(name) => name,
+                None => continue,
+            };
+            let (path, parameters) = template(pattern);
+            let operation = operation(&parameters, metadata);
+            match paths.iter_mut().find(|(existing, _)| *existing == path) {
+                Some((_, operations)) => operations.push((name, operation)),
+                None => paths.push((path, vec![(name, operation)])),
+            }
+        }
+
+        let mut info = vec![
+            ("title", quoted(&self.title)),
+            ("version", quoted(&self.version)),
+        ];
+        if let Some(description) = &self.description {
+            info.push(("description", quoted(description)));
+        }
+        let mut document = vec![
+            ("openapi", quoted("3.0.3")),
+            ("info", object(info.into_iter())),
+        ];
+        if !self.servers.is_empty() {
+            let servers = self
+                .servers
+                .iter()
+                .map(|url| object(vec![("url", quoted(url))].into_iter()));
+            document.push(("servers", array(servers)));
+        }
+        let paths: Vec<(&str, String)> = paths
+            .iter()
+            .map(|(path, operations)| {
+                (
+                    path.as_str(),
+                    object(operations.iter().map(|(method, op)| (*method, op.clone()))),
+                )
+            })
+            .collect();
+        document.push(("paths", object(paths.into_iter())));
+        object(document.into_iter())
+    }
+}
+
+impl Router {
+    /// Generate an OpenAPI 3 document describing this router's routes, as
+    /// JSON.
+    pub fn openapi(&self, api: &OpenApi) -> String {
+        api.document(&self.metrics)
+    }
+}
+
+// The handler installed by `RouterBuilder::openapi`.
+pub(crate) fn response(api: &OpenApi, metrics: &Metrics) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(api.document(metrics).into())
+        .unwrap()
+}
+
+// The handler installed by `RouterBuilder::swagger_ui`. The page loads Swagger
+// UI itself from a CDN.
+pub(crate) fn swagger_ui(spec: &str) -> Response<Body> {
+    let page = format!(
+        r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>API documentation</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>SwaggerUIBundle({{ url: {}, dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##,
+        quoted(spec)
+    );
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(page.into())
+        .unwrap()
+}
+
+fn operation(parameters: &[Parameter], metadata: &Metadata) -> String {
+    let mut fields = Vec::new();
+    if let Some(name) = &metadata.name {
+        fields.push(("operationId", quoted(name)));
+    }
+    if let Some(description) = &metadata.description {
+        fields.push(("description", quoted(description)));
+    }
+    if !metadata.tags.is_empty() {
+        fields.push(("tags", array(metadata.tags.iter().map(|tag| quoted(tag)))));
+    }
+    if !parameters.is_empty() {
+        fields.push(("parameters", array(parameters.iter().map(parameter))));
+    }
+    if let Some(schema) = &metadata.request_schema {
+        fields.push(("requestBody", content(schema)));
+    }
+    if metadata.deprecated {
+        fields.push(("deprecated", "true".to_owned()));
+    }
+
+    let mut ok = vec![("description", quoted("OK"))];
+    if let Some(schema) = &metadata.response_schema {
+        ok.push(("content", json_schema(schema)));
+    }
+    fields.push((
+        "responses",
+        object(vec![("200", object(ok.into_iter()))].into_iter()),
+    ));
+    object(fields.into_iter())
+}
+
+fn parameter(parameter: &Parameter) -> String {
+    // Digits only captures are common enough to be worth typing.
+    let schema = if parameter.pattern == r"\d+" {
+        vec![("type", quoted("integer"))]
+    } else {
+        vec![
+            ("type", quoted("string")),
+            ("pattern", quoted(&parameter.pattern)),
+        ]
+    };
+    object(
+        vec![
+            ("name", quoted(&parameter.name)),
+            ("in", quoted("path")),
+            ("required", "true".to_owned()),
+            ("schema", object(schema.into_iter())),
+        ]
+        .into_iter(),
+    )
+}
+
+fn content(schema: &str) -> String {
+    object(vec![("content", json_schema(schema))].into_iter())
+}
+
+fn json_schema(schema: &str) -> String {
+    let media = object(vec![("schema", schema.to_owned())].into_iter());
+    object(vec![("application/json", media)].into_iter())
+}
+
+// OpenAPI can only describe the standard methods other than CONNECT, so
+// routes for any others are left out.
+fn method_name(method: &Method) -> Option<&'static str> {
+    match *method {
+        Method::GET => Some("get"),
+        Method::PUT => Some("put"),
+        Method::POST => Some("post"),
+        Method::DELETE => Some("delete"),
+        Method::OPTIONS => Some("options"),
+        Method::HEAD => Some("head"),
+        Method::PATCH => Some("patch"),
+        Method::TRACE => Some("trace"),
+        _ => None,
+    }
+}
+
+// Turn a route pattern into an OpenAPI path template and its parameters.
+fn template(pattern: &str) -> (String, Vec<Parameter>) {
+    let mut path = String::new();
+    let mut parameters = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => path.extend(chars.next()),
+            '^' | '$' | '?' | '*' | '+' => {}
+            '(' => {
+                let group = group(&mut chars);
+                let named = group
+                    .strip_prefix("?P<")
+                    .or_else(|| group.strip_prefix("?<"))
+                    .and_then(|rest| rest.split_once('>'));
+                let (name, pattern) = match named {
+                    Some((name, pattern)) => (name.to_owned(), pattern.to_owned()),
+                    // Other groups starting with `?` don't capture anything.
+                    None if group.starts_with('?') => continue,
+                    None => (format!("param{}", parameters.len() + 1), group),
+                };
+                path.push('{');
+                path.push_str(&name);
+                path.push('}');
+                parameters.push(Parameter { name, pattern });
+            }
+            c => path.push(c),
+        }
+    }
+    (path, parameters)
+}
+
+// Take the contents of a group whose opening parenthesis was just consumed.
+fn group(chars: &mut std::str::Chars) -> String {
+    let mut group = String::new();
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                group.push(c);
+                group.extend(chars.next());
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        group.push(c);
+    }
+    group
+}
+
+#[test]
+fn documents_routes() {
+    use crate::{RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/users/(\d+)/files/(?P<file>[a-z]+)\.txt",
+        RouteOptions::new()
+            .name("get_file")
+            .tag("files")
+            .deprecated(crate::Deprecation::new())
+            .response_schema(r#"{"type":"string"}"#),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.route(Method::CONNECT, r"/tunnel", |_, _| {
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+
+    let document = router.openapi(&OpenApi::new("Files", "1.0"));
+    assert_eq!(
+        document,
+        concat!(
+            r#"{"openapi":"3.0.3","info":{"title":"Files","version":"1.0"},"paths":{"#,
+            r#""/users/{param1}/files/{file}.txt":{"get":{"operationId":"get_file","#,
+            r#""tags":["files"],"parameters":["#,
+            r#"{"name":"param1","in":"path","required":true,"schema":{"type":"integer"}},"#,
+            r#"{"name":"file","in":"path","required":true,"#,
+            r#""schema":{"type":"string","pattern":"[a-z]+"}}],"deprecated":true,"#,
+            r#""responses":{"200":{"description":"OK","#,
+            r#""content":{"application/json":{"schema":{"type":"string"}}}}}}}}}"#
+        )
+    );
+}
+
+#[test]
+fn marks_deprecated_operations() {
+    use crate::{Deprecation, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/v1/users",
+        RouteOptions::new().deprecated(Deprecation::new()),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.get(r"/v2/users", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let document = router.openapi(&OpenApi::new("Users", "2.0"));
+    assert_eq!(
+        document,
+        concat!(
+            r#"{"openapi":"3.0.3","info":{"title":"Users","version":"2.0"},"paths":{"#,
+            r#""/v1/users":{"get":{"deprecated":true,"responses":{"200":{"description":"OK"}}}},"#,
+            r#""/v2/users":{"get":{"responses":{"200":{"description":"OK"}}}}}}"#
+        )
+    );
+}
@@ -0,0 +1,595 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::http::Extensions;
+use hyper::{Body, Request};
+
+use crate::{Canary, Deprecation, Device, Quota, Recorder, Slo};
+
+/// Settings for a single route, passed to `RouterBuilder::route_with`.
+///
+/// Every setting is optional and falls back to the router wide behaviour when
+/// it is not given.
+pub struct RouteOptions<B = Body> {
+    pub(crate) slow_threshold: Option<Duration>,
+    pub(crate) body_timeout: Option<Duration>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) max_body_size: Option<u64>,
+    pub(crate) redact_captures: bool,
+    pub(crate) slo: Option<Slo>,
+    pub(crate) deprecation: Option<Deprecation>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) canary: Option<Arc<Canary<B>>>,
+    pub(crate) quota: Option<Quota<B>>,
+    pub(crate) regions: Option<Vec<String>>,
+    pub(crate) devices: Option<Vec<Device>>,
+    pub(crate) host: Option<String>,
+    pub(crate) host_pattern: Option<String>,
+    pub(crate) scheme: Option<String>,
+    pub(crate) variant: Option<String>,
+    pub(crate) guards: Vec<Guard<B>>,
+    pub(crate) data: Vec<Data>,
+    pub(crate) produces: Option<String>,
+    pub(crate) consumes: Option<String>,
+    pub(crate) case_insensitive: Option<bool>,
+    pub(crate) regex: RegexOptions,
+    pub(crate) priority: i32,
+    pub(crate) client_hints: Vec<String>,
+    pub(crate) metadata: Metadata,
+}
+
+type Predicate<B> = dyn Fn(&Request<B>) -> bool + Send + Sync;
+type Insert = dyn Fn(&mut Extensions) + Send + Sync;
+
+// A predicate a request must pass to be routed to a route.
+pub(crate) struct Guard<B>(Arc<Predicate<B>>);
+
+impl<B> Guard<B> {
+    pub(crate) fn admits(&self, req: &Request<B>) -> bool {
+        (self.0)(req)
+    }
+}
+
+impl<B> Clone for Guard<B> {
+    fn clone(&self) -> Guard<B> {
+        Guard(self.0.clone())
+    }
+}
+
+impl<B> fmt::Debug for Guard<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Guard")
+    }
+}
+
+// A value added to the extensions of every request to a route.
+#[derive(Clone)]
+pub(crate) struct Data(Arc<Insert>);
+
+impl Data {
+    pub(crate) fn insert(&self, extensions: &mut Extensions) {
+        (self.0)(extensions)
+    }
+}
+
+impl fmt::Debug for Data {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Data")
+    }
+}
+
+// Settings for compiling a route's pattern.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RegexOptions {
+    pub(crate) dot_matches_new_line: bool,
+    pub(crate) ignore_whitespace: bool,
+    pub(crate) size_limit: Option<usize>,
+}
+
+impl RegexOptions {
+    // Wrap `route` in a group setting its flags. Patterns that ignore
+    // whitespace may end in a comment, so the group is closed on a new line.
+    pub(crate) fn apply(&self, route: &str, case_insensitive: bool) -> String {
+        let mut flags = String::new();
+        if case_insensitive {
+            flags.push('i');
+        }
+        if self.dot_matches_new_line {
+            flags.push('s');
+        }
+        if self.ignore_whitespace {
+            flags.push('x');
+        }
+        match (flags.is_empty(), self.ignore_whitespace) {
+            (true, _) => route.to_owned(),
+            (false, false) => format!("(?{}:{})", flags, route),
+            (false, true) => format!("(?{}:{}\n)", flags, route),
+        }
+    }
+}
+
+// Descriptive information about a route that doesn't change how it is routed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Metadata {
+    pub(crate) name: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) request_schema: Option<String>,
+    pub(crate) response_schema: Option<String>,
+    pub(crate) deprecated: bool,
+}
+
+// Derived, these would only be for bodies that are themselves `Clone`,
+// `Debug` and `Default`.
+impl<B> Clone for RouteOptions<B> {
+    fn clone(&self) -> RouteOptions<B> {
+        RouteOptions {
+            slow_threshold: self.slow_threshold,
+            body_timeout: self.body_timeout,
+            timeout: self.timeout,
+            max_body_size: self.max_body_size,
+            redact_captures: self.redact_captures,
+            slo: self.slo.clone(),
+            deprecation: self.deprecation.clone(),
+            recorder: self.recorder.clone(),
+            canary: self.canary.clone(),
+            quota: self.quota.clone(),
+            regions: self.regions.clone(),
+            devices: self.devices.clone(),
+            host: self.host.clone(),
+            host_pattern: self.host_pattern.clone(),
+            scheme: self.scheme.clone(),
+            variant: self.variant.clone(),
+            guards: self.guards.clone(),
+            data: self.data.clone(),
+            produces: self.produces.clone(),
+            consumes: self.consumes.clone(),
+            case_insensitive: self.case_insensitive,
+            regex: self.regex.clone(),
+            priority: self.priority,
+            client_hints: self.client_hints.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl<B> fmt::Debug for RouteOptions<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RouteOptions")
+            .field("slow_threshold", &self.slow_threshold)
+            .field("body_timeout", &self.body_timeout)
+            .field("timeout", &self.timeout)
+            .field("max_body_size", &self.max_body_size)
+            .field("redact_captures", &self.redact_captures)
+            .field("slo", &self.slo)
+            .field("deprecation", &self.deprecation)
+            .field("recorder", &self.recorder)
+            .field("canary", &self.canary)
+            .field("quota", &self.quota)
+            .field("regions", &self.regions)
+            .field("devices", &self.devices)
+            .field("host", &self.host)
+            .field("host_pattern", &self.host_pattern)
+            .field("scheme", &self.scheme)
+            .field("variant", &self.variant)
+            .field("guards", &self.guards)
+            .field("data", &self.data)
+            .field("produces", &self.produces)
+            .field("consumes", &self.consumes)
+            .field("case_insensitive", &self.case_insensitive)
+            .field("regex", &self.regex)
+            .field("priority", &self.priority)
+            .field("client_hints", &self.client_hints)
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
+
+impl<B> Default for RouteOptions<B> {
+    fn default() -> RouteOptions<B> {
+        RouteOptions {
+            slow_threshold: Default::default(),
+            body_timeout: Default::default(),
+            timeout: Default::default(),
+            max_body_size: Default::default(),
+            redact_captures: Default::default(),
+            slo: Default::default(),
+            deprecation: Default::default(),
+            recorder: Default::default(),
+            canary: Default::default(),
+            quota: Default::default(),
+            regions: Default::default(),
+            devices: Default::default(),
+            host: Default::default(),
+            host_pattern: Default::default(),
+            scheme: Default::default(),
+            variant: Default::default(),
+            guards: Default::default(),
+            data: Default::default(),
+            produces: Default::default(),
+            consumes: Default::default(),
+            case_insensitive: Default::default(),
+            regex: Default::default(),
+            priority: Default::default(),
+            client_hints: Default::default(),
+            metadata: Default::default(),
+        }
+    }
+}
+
+impl<B> RouteOptions<B> {
+    /// Create options that leave everything at the router's defaults.
+    pub fn new() -> RouteOptions<B> {
+        RouteOptions::default()
+    }
+
+    // Whether the route can turn away requests its method and pattern match.
+    // Named routes can be disabled while the router runs.
+    pub(crate) fn conditional(&self) -> bool {
+        self.metadata.name.is_some()
+            || self.host.is_some()
+            || self.host_pattern.is_some()
+            || self.scheme.is_some()
+            || self.variant.is_some()
+            || !self.guards.is_empty()
+            || self.regions.is_some()
+            || self.devices.is_some()
+            || self.consumes.is_some()
+            || self.produces.is_some()
+    }
+
+    /// Name the route. The name is added to the route's metrics labels and
+    /// shown in the route table. Named routes can be switched off with
+    /// `Router::set_enabled` and have URLs built with `Router::url_for`.
+    pub fn name(mut self, name: &str) -> RouteOptions<B> {
+        self.metadata.name = Some(name.to_owned());
+        self
+    }
+
+    /// Tag the route for grouping it with others in metrics and generated
+    /// documentation. This can be called more than once.
+    pub fn tag(mut self, tag: &str) -> RouteOptions<B> {
+        self.metadata.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Describe what the route does, for generated documentation.
+    pub fn description(mut self, description: &str) -> RouteOptions<B> {
+        self.metadata.description = Some(description.to_owned());
+        self
+    }
+
+    /// Document the route's JSON request body with a JSON Schema, given as
+    /// encoded JSON. It is copied verbatim into generated OpenAPI documents.
+    pub fn request_schema(mut self, schema: &str) -> RouteOptions<B> {
+        self.metadata.request_schema = Some(schema.to_owned());
+        self
+    }
+
+    /// Document the route's successful JSON response with a JSON Schema,
+    /// given as encoded JSON.
+    pub fn response_schema(mut self, schema: &str) -> RouteOptions<B> {
+        self.metadata.response_schema = Some(schema.to_owned());
+        self
+    }
+
+    /// Report requests to this route that take longer than `threshold`,
+    /// overriding `RouterBuilder::slow_request_threshold`.
+    pub fn slow_threshold(mut self, threshold: Duration) -> RouteOptions<B> {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Fail reading the request body if the client hasn't sent all of it
+    /// within `timeout` of the request being routed. See `BodyTimeout`.
+    pub fn body_timeout(mut self, timeout: Duration) -> RouteOptions<B> {
+        self.body_timeout = Some(timeout);
+        self
+    }
+
+    /// Give requests to this route `timeout` to be handled, from when they
+    /// are received. Handlers find the time they have left in the request's
+    /// `Deadline`, which is the earlier of this and any deadline the caller
+    /// sent, and should give up once it passes.
+    ///
+    /// The timeout is advisory: handlers are called synchronously, so the
+    /// router can't cut one short and what a handler returns is always sent,
+    /// even if it runs late. The router itself only answers with
+    /// `504 Gateway Timeout` when the deadline has already passed before the
+    /// handler is called, such as when the caller's deadline had. Reading a
+    /// body that arrives after the deadline fails with `BodyTimeout`, which
+    /// the handler answers for.
+    pub fn timeout(mut self, timeout: Duration) -> RouteOptions<B> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Refuse request bodies larger than `bytes`. See `BodyTooLarge`.
+    pub fn max_body_size(mut self, bytes: u64) -> RouteOptions<B> {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    /// Replace this route's captures with a placeholder when reporting slow
+    /// requests, for routes whose paths carry tokens or personal data.
+    pub fn redact_captures(mut self) -> RouteOptions<B> {
+        self.redact_captures = true;
+        self
+    }
+
+    /// Track this route's compliance with a service level objective. The
+    /// result is reported in the route's metrics.
+    pub fn slo(mut self, slo: Slo) -> RouteOptions<B> {
+        self.slo = Some(slo);
+        self
+    }
+
+    /// Mark this route as deprecated so its responses advertise it. Its
+    /// operation is marked deprecated in generated OpenAPI documents too.
+    pub fn deprecated(mut self, deprecation: Deprecation) -> RouteOptions<B> {
+        self.deprecation = Some(deprecation);
+        self.metadata.deprecated = true;
+        self
+    }
+
+    /// Only match requests from one of `regions`, as named by the resolver
+    /// given to `RouterBuilder::geo_resolver`. Other requests fall through
+    /// to later routes, so a route for the same path without regions can
+    /// serve or block everyone else.
+    pub fn regions(mut self, regions: &[&str]) -> RouteOptions<B> {
+        self.regions = Some(regions.iter().map(|&region| region.to_owned()).collect());
+        self
+    }
+
+    /// Only match requests from one of `devices`. As with `regions`, other
+    /// requests fall through to later routes.
+    pub fn devices(mut self, devices: &[Device]) -> RouteOptions<B> {
+        self.devices = Some(devices.to_vec());
+        self
+    }
+
+    /// Try this route before routes of a lower priority, whatever order they
+    /// were added in. Routes have a priority of zero unless given one, and
+    /// routes of the same priority are tried in the order they were added.
+    pub fn priority(mut self, priority: i32) -> RouteOptions<B> {
+        self.priority = priority;
+        self
+    }
+
+    /// Match this route's pattern with or without regard to case,
+    /// overriding `RouterBuilder::case_insensitive`.
+    pub fn case_insensitive(mut self, insensitive: bool) -> RouteOptions<B> {
+        self.case_insensitive = Some(insensitive);
+        self
+    }
+
+    /// Let `.` in this route's pattern match new lines, which can appear in
+    /// paths as `%0A` once decoded by the client.
+    pub fn dot_matches_new_line(mut self, enabled: bool) -> RouteOptions<B> {
+        self.regex.dot_matches_new_line = enabled;
+        self
+    }
+
+    /// Ignore whitespace in this route's pattern and allow `#` comments, so
+    /// long patterns can be spread over several lines.
+    pub fn ignore_whitespace(mut self, enabled: bool) -> RouteOptions<B> {
+        self.regex.ignore_whitespace = enabled;
+        self
+    }
+
+    /// Allow this route's pattern to compile to `bytes` of memory instead
+    /// of the regex crate's default of 10 MiB, for very large patterns. The
+    /// router's set of all patterns is given the extra room too.
+    pub fn size_limit(mut self, bytes: usize) -> RouteOptions<B> {
+        self.regex.size_limit = Some(bytes);
+        self
+    }
+
+    /// Only match requests sent to `host`, ignoring any port. As with
+    /// `regions`, other requests fall through to later routes.
+    /// `RouterBuilder::host` adds several routes for the same host.
+    pub fn host(mut self, host: &str) -> RouteOptions<B> {
+        self.host = Some(host.to_owned());
+        self
+    }
+
+    /// Only match requests sent with `scheme`, such as `https`, as decided
+    /// by the router's `SchemePolicy`. As with `regions`, other requests
+    /// fall through to later routes, such as one that redirects to HTTPS.
+    pub fn scheme(mut self, scheme: &str) -> RouteOptions<B> {
+        self.scheme = Some(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Only match requests in the experiment variant `variant`, as read by
+    /// the router's `Experiment`. As with `regions`, other requests fall
+    /// through to later routes, such as the default variant's.
+    pub fn variant(mut self, variant: &str) -> RouteOptions<B> {
+        self.variant = Some(variant.to_owned());
+        self
+    }
+
+    /// Only match requests that `guard` returns true for, such as those
+    /// with a certain header. As with `regions`, other requests fall through
+    /// to later routes. This can be called more than once and a request has
+    /// to pass every guard.
+    pub fn guard<F>(mut self, guard: F) -> RouteOptions<B>
+    where
+        F: Fn(&Request<B>) -> bool + Send + Sync + 'static,
+    {
+        self.guards.push(Guard(Arc::new(guard)));
+        self
+    }
+
+    /// Only match requests with a `name` header of `value`, such as
+    /// `X-Client: mobile`. This is a guard, so it combines with any others.
+    pub fn header(self, name: &str, value: &str) -> RouteOptions<B> {
+        let name = name.to_ascii_lowercase();
+        let value = value.to_owned();
+        self.guard(move |req| {
+            req.headers()
+                .get_all(name.as_str())
+                .iter()
+                .any(|sent| sent.to_str().is_ok_and(|sent| sent.trim() == value))
+        })
+    }
+
+    /// Only match requests with an extension of type `T`, such as one added
+    /// by middleware for users in a feature flag's cohort. This is a guard,
+    /// so it combines with any others.
+    pub fn extension<T>(self) -> RouteOptions<B>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.guard(|req| req.extensions().get::<T>().is_some())
+    }
+
+    /// Add a copy of `data` to the extensions of every request to this route
+    /// before its handler is called, so one handler can behave differently
+    /// for each route it is used for, such as a proxy given the upstream of
+    /// each route. Data of the same type given more than once replaces what
+    /// was given before.
+    pub fn data<T>(mut self, data: T) -> RouteOptions<B>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.data.push(Data(Arc::new(move |extensions| {
+            extensions.insert(data.clone());
+        })));
+        self
+    }
+
+    /// Mark this route as responding with the media type `media`, such as
+    /// `application/json`. Routes for the same method and pattern that each
+    /// produce a different type are negotiated between with the request's
+    /// `Accept` header, and requests that accept none of them are answered
+    /// by `RouterBuilder::not_acceptable`. Responses get `Vary: Accept`, and
+    /// `media` as their `Content-Type` if the handler sets none.
+    pub fn produces(mut self, media: &str) -> RouteOptions<B> {
+        self.produces = Some(media.to_owned());
+        self
+    }
+
+    /// Only match requests whose `Content-Type` is the media type `media`,
+    /// such as `multipart/form-data`, or one it covers, like `text/*` does
+    /// `text/csv`. Requests that no route for their path consumes are
+    /// answered with `415 Unsupported Media Type`.
+    pub fn consumes(mut self, media: &str) -> RouteOptions<B> {
+        self.consumes = Some(media.to_owned());
+        self
+    }
+
+    /// Only match requests whose host, without any port, matches the
+    /// pattern `pattern`, such as `(?P<tenant>[a-z0-9]+)\.example\.com`.
+    /// The pattern must match the whole host and ignores case, and its
+    /// groups are added to the route's captures after those of the path. As
+    /// with `regions`, other requests fall through to later routes.
+    pub fn host_pattern(mut self, pattern: &str) -> RouteOptions<B> {
+        self.host_pattern = Some(pattern.to_owned());
+        self
+    }
+
+    /// Ask browsers for the named Client Hints, such as `Sec-CH-DPR`, with
+    /// `Accept-CH` on this route's responses and add them to `Vary`. The
+    /// hints a request carries are given to the handler as a `ClientHints`
+    /// extension.
+    pub fn client_hints(mut self, hints: &[&str]) -> RouteOptions<B> {
+        self.client_hints = hints.iter().map(|&hint| hint.to_owned()).collect();
+        self
+    }
+
+    /// Record a sample of this route's requests and responses.
+    pub fn record(mut self, recorder: Recorder) -> RouteOptions<B> {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Count this route's requests against the API key quotas of `quota`,
+    /// rejecting keys that have used theirs up.
+    pub fn quota(mut self, quota: Quota<B>) -> RouteOptions<B> {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Send some of this route's requests to a canary handler instead.
+    /// Copies of these options share the canary and how many requests it
+    /// has seen.
+    pub fn canary(mut self, canary: Canary<B>) -> RouteOptions<B> {
+        self.canary = Some(Arc::new(canary));
+        self
+    }
+}
+
+#[test]
+fn matches_headers_and_extensions() {
+    use hyper::{Method, Response, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    struct Beta;
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/home",
+        RouteOptions::new().extension::<Beta>(),
+        |_, _| Response::new("beta".into()),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/home",
+        RouteOptions::new().header("X-Client", "mobile"),
+        |_, _| Response::new("mobile".into()),
+    );
+    builder.get(r"/home", |_, _| Response::new("web".into()));
+    let router = builder.finalize().unwrap();
+
+    let body = |req: Request<Body>| {
+        let response = router.handle(req);
+        assert_eq!(response.status(), StatusCode::OK);
+        testing::body(response)
+    };
+    assert_eq!(body(testing::get("/home")), "web");
+    let mut req = testing::get("/home");
+    req.headers_mut()
+        .insert("x-client", "mobile".parse().unwrap());
+    assert_eq!(body(req), "mobile");
+    let mut req = testing::get("/home");
+    req.extensions_mut().insert(Beta);
+    assert_eq!(body(req), "beta");
+}
+
+#[test]
+fn attaches_data() {
+    use hyper::{Method, Response};
+
+    use crate::{testing, Captures, RouterBuilder};
+
+    #[derive(Clone)]
+    struct Upstream(&'static str);
+
+    fn proxy(req: Request<Body>, _: Captures) -> Response<Body> {
+        let upstream = req.extensions().get::<Upstream>().unwrap();
+        Response::new(upstream.0.into())
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/users/.*",
+        RouteOptions::new().data(Upstream("http://users")),
+        proxy,
+    );
+    builder.route_with(
+        Method::GET,
+        r"/orders/.*",
+        RouteOptions::new().data(Upstream("http://orders")),
+        proxy,
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/orders/7"));
+    let body = testing::body(response);
+    assert_eq!(body, "http://orders");
+}
@@ -0,0 +1,229 @@
+use hyper::{Request, StatusCode};
+use opentelemetry::global;
+use opentelemetry::trace::{
+    SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId, TraceState, Tracer,
+};
+use opentelemetry::{Context, KeyValue};
+
+use crate::TraceContext;
+
+// Start a server span for a request dispatched to `route`. The span is named
+// and labeled with the route pattern rather than the path so that backends
+// can aggregate requests to the same route. Requests carrying a trace
+// context continue the caller's trace.
+pub(crate) fn start<B>(req: &Request<B>, route: &str) -> Context {
+    let tracer = global::tracer("reroute");
+    let parent = match req.extensions().get::<TraceContext>().and_then(remote) {
+        Some(remote) => Context::current().with_remote_span_context(remote),
+        None => Context::current(),
+    };
+    let span = tracer
+        .span_builder(format!("{} {}", req.method(), route))
+        .with_kind(SpanKind::Server)
+        .with_attributes(vec![
+            KeyValue::new("http.method", req.method().to_string()),
+            KeyValue::new("http.route", route.to_owned()),
+            KeyValue::new("url.path", req.uri().path().to_owned()),
+        ])
+        .start_with_context(&tracer, &parent);
+
+    parent.with_span(span)
+}
+
+// Record the response status on the span started for a request and end it.
+pub(crate) fn finish(cx: &Context, status: StatusCode) {
+    let span = cx.span();
+    span.set_attribute(KeyValue::new(
+        "http.status_code",
+        i64::from(status.as_u16()),
+    ));
+    if status.is_server_error() {
+        span.set_status(Status::error(status.to_string()));
+    }
+    span.end();
+}
+
+// The caller's span as a parent for the server span.
+fn remote(context: &TraceContext) -> Option<SpanContext> {
+    let state = match &context.tracestate {
+        Some(state) => state.parse().ok()?,
+        None => TraceState::default(),
+    };
+    Some(SpanContext::new(
+        TraceId::from_hex(&context.trace_id).ok()?,
+        SpanId::from_hex(&context.parent_id).ok()?,
+        TraceFlags::new(context.flags),
+        true,
+        state,
+    ))
+}
+
+#[test]
+fn exports_route_spans() {
+    use std::borrow::Cow;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    use opentelemetry::trace::{Span, TracerProvider};
+    use opentelemetry::InstrumentationScope;
+
+    use crate::{testing, RouterBuilder};
+
+    // A span as it was when it ended.
+    #[derive(Clone, Debug)]
+    struct Exported {
+        name: String,
+        parent: SpanContext,
+        attributes: Vec<KeyValue>,
+        status: Status,
+    }
+
+    impl Exported {
+        fn attribute(&self, key: &str) -> Option<String> {
+            self.attributes
+                .iter()
+                .find(|attribute| attribute.key.as_str() == key)
+                .map(|attribute| attribute.value.to_string())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Exporter(Arc<Mutex<Vec<Exported>>>);
+
+    impl TracerProvider for Exporter {
+        type Tracer = Exporter;
+
+        fn tracer_with_scope(&self, _: InstrumentationScope) -> Exporter {
+            self.clone()
+        }
+    }
+
+    impl Tracer for Exporter {
+        type Span = Recording;
+
+        fn build_with_context(
+            &self,
+            builder: opentelemetry::trace::SpanBuilder,
+            parent_cx: &Context,
+        ) -> Recording {
+            let parent = parent_cx.span().span_context().clone();
+            let trace_id = if parent.is_valid() {
+                parent.trace_id()
+            } else {
+                TraceId::from_bytes([1; 16])
+            };
+            Recording {
+                exporter: self.clone(),
+                context: SpanContext::new(
+                    trace_id,
+                    SpanId::from_bytes([1; 8]),
+                    TraceFlags::SAMPLED,
+                    false,
+                    TraceState::default(),
+                ),
+                span: Exported {
+                    name: builder.name.into_owned(),
+                    parent,
+                    attributes: builder.attributes.unwrap_or_default(),
+                    status: Status::Unset,
+                },
+            }
+        }
+    }
+
+    struct Recording {
+        exporter: Exporter,
+        context: SpanContext,
+        span: Exported,
+    }
+
+    impl Span for Recording {
+        fn add_event_with_timestamp<T>(&mut self, _: T, _: SystemTime, _: Vec<KeyValue>)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+        }
+
+        fn span_context(&self) -> &SpanContext {
+            &self.context
+        }
+
+        fn is_recording(&self) -> bool {
+            true
+        }
+
+        fn set_attribute(&mut self, attribute: KeyValue) {
+            self.span.attributes.push(attribute);
+        }
+
+        fn set_status(&mut self, status: Status) {
+            self.span.status = status;
+        }
+
+        fn update_name<T>(&mut self, name: T)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+            self.span.name = name.into().into_owned();
+        }
+
+        fn add_link(&mut self, _: SpanContext, _: Vec<KeyValue>) {}
+
+        fn end_with_timestamp(&mut self, _: SystemTime) {
+            self.exporter.0.lock().unwrap().push(self.span.clone());
+        }
+    }
+
+    let exporter = Exporter::default();
+    global::set_tracer_provider(exporter.clone());
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/otel/users/(\d+)", |_, _| StatusCode::OK);
+    builder.get(r"/otel/fail", |_, _| StatusCode::BAD_GATEWAY);
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::get("/otel/users/7");
+    req.headers_mut().insert(
+        "traceparent",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+            .parse()
+            .unwrap(),
+    );
+    router.handle(req);
+    router.handle(testing::get("/otel/fail"));
+
+    // Other tests may be exporting spans to the same global provider.
+    let spans: Vec<Exported> = exporter
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|span| span.name.starts_with("GET /otel/"))
+        .cloned()
+        .collect();
+    assert_eq!(spans.len(), 2);
+
+    let span = &spans[0];
+    assert_eq!(span.name, r"GET /otel/users/(\d+)");
+    assert_eq!(span.attribute("http.method").as_deref(), Some("GET"));
+    assert_eq!(
+        span.attribute("http.route").as_deref(),
+        Some(r"/otel/users/(\d+)")
+    );
+    assert_eq!(span.attribute("http.status_code").as_deref(), Some("200"));
+    assert_eq!(
+        span.parent.trace_id(),
+        TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+    );
+    assert_eq!(
+        span.parent.span_id(),
+        SpanId::from_hex("00f067aa0ba902b7").unwrap()
+    );
+    assert!(span.parent.is_remote());
+    assert_eq!(span.status, Status::Unset);
+
+    let span = &spans[1];
+    assert_eq!(span.attribute("http.status_code").as_deref(), Some("502"));
+    assert!(!span.parent.is_valid());
+    assert_eq!(span.status, Status::error("502 Bad Gateway"));
+}
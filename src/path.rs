@@ -0,0 +1,169 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::patterns;
+
+// Compile a Sinatra style path such as `/users/:id/files/*rest` into a
+// pattern. `:name` captures one path segment and `*name` the rest of the
+// path, slashes and all, each in a group of that name. A bare `*` captures
+// the rest of the path in an unnamed group. Either can be followed by what
+// to match in braces, the name of a fragment in `patterns` such as
+// `:id{uuid}` or else a regex such as `:year{\d{4}}`. Everything else is
+// matched literally.
+pub(crate) fn compile(path: &str) -> String {
+    let mut pattern = String::with_capacity(path.len());
+    let mut literal = String::new();
+    let mut chars = path.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != ':' && c != '*' {
+            literal.push(c);
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, next)) = chars.peek() {
+            if !(next.is_ascii_alphanumeric() || next == '_') {
+                break;
+            }
+            end = i + next.len_utf8();
+            chars.next();
+        }
+        let name = &path[start + 1..end];
+        if c == ':' && name.is_empty() {
+            // A colon on its own isn't a parameter.
+            literal.push(c);
+            continue;
+        }
+
+        pattern.push_str(&regex::escape(&literal));
+        literal.clear();
+        let group = match constraint(path, &mut chars) {
+            Some(constraint) => patterns::named(constraint).unwrap_or(constraint),
+            None if c == ':' => "[^/]+",
+            None => ".*",
+        };
+        if name.is_empty() {
+            pattern.push_str(&format!("({})", group));
+        } else {
+            pattern.push_str(&format!("(?P<{}>{})", name, group));
+        }
+    }
+    pattern.push_str(&regex::escape(&literal));
+    pattern
+}
+
+// Take what is between the braces following a parameter, if there are any,
+// allowing for braces nested inside them. Unclosed braces are left alone.
+fn constraint<'p>(path: &'p str, chars: &mut Peekable<CharIndices<'p>>) -> Option<&'p str> {
+    let &(open, _) = chars.peek().filter(|&&(_, c)| c == '{')?;
+    let mut depth = 0;
+    let close = path[open..].char_indices().find_map(|(i, c)| {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => return None,
+        }
+        if depth == 0 {
+            Some(open + i)
+        } else {
+            None
+        }
+    })?;
+    while chars.next_if(|&(i, _)| i <= close).is_some() {}
+    Some(&path[open + 1..close])
+}
+
+// The name of the group holding the rest of the path for prefix routes.
+pub(crate) const REST: &str = "rest";
+
+// A pattern matching `prefix` literally followed by anything, which is
+// captured in a group called `rest`.
+pub(crate) fn prefix(prefix: &str) -> String {
+    format!("{}(?P<{}>.*)", regex::escape(prefix), REST)
+}
+
+// The only path a pattern can match, if it has no regular expression syntax
+// other than escaped characters.
+pub(crate) fn literal(pattern: &str) -> Option<String> {
+    let mut path = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) if c.is_ascii_punctuation() => path.push(c),
+                _ => return None,
+            },
+            '.' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' => {
+                return None
+            }
+            c => path.push(c),
+        }
+    }
+    Some(path)
+}
+
+#[test]
+fn compiles_parameters() {
+    use hyper::Response;
+
+    use crate::{testing, RouterBuilder};
+
+    assert_eq!(
+        compile("/users/:id/files/*rest"),
+        r"/users/(?P<id>[^/]+)/files/(?P<rest>.*)"
+    );
+    assert_eq!(compile("/v1.0/:/*"), r"/v1\.0/:/(.*)");
+    assert_eq!(
+        compile(r"/posts/:year{\d{4}}/:slug{slug}"),
+        r"/posts/(?P<year>\d{4})/(?P<slug>[a-z0-9]+(?:-[a-z0-9]+)*)"
+    );
+    assert_eq!(compile("/:id{int"), r"/(?P<id>[^/]+)\{int");
+
+    let mut builder = RouterBuilder::new();
+    builder.get_path("/users/:id/files/*rest", |_, c| {
+        Response::new(format!("{} {}", c.name("id").unwrap(), c.name("rest").unwrap()).into())
+    });
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/users/7/files/a/b.txt"));
+    let body = testing::body(response);
+    assert_eq!(body, "7 a/b.txt");
+    assert_eq!(
+        router.handle(testing::get("/users/7")).status(),
+        hyper::StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn matches_prefixes() {
+    use hyper::{Body, Response, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    assert_eq!(prefix("/v1.0/"), r"/v1\.0/(?P<rest>.*)");
+
+    let mut builder = RouterBuilder::new();
+    builder.get_prefix("/static/", |_, c| {
+        assert_eq!(c.rest(), Some("css/site.css"));
+        assert_eq!(&c[1], "css/site.css");
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+
+    assert_eq!(
+        router.handle(testing::get("/static/css/site.css")).status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        router.handle(testing::get("/staticx")).status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn finds_literal_paths() {
+    assert_eq!(literal(r"/users/me").as_deref(), Some("/users/me"));
+    assert_eq!(literal(r"/robots\.txt").as_deref(), Some("/robots.txt"));
+    assert_eq!(literal(r"/users/(\d+)"), None);
+    assert_eq!(literal(r"/files/\d"), None);
+    assert_eq!(literal(r"/a|/b"), None);
+}
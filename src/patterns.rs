@@ -0,0 +1,74 @@
+//! Regex fragments for commonly captured path segments.
+//!
+//! The constants match a value without capturing it and the functions wrap
+//! the same fragment in a group, so a route for a user by id can be written
+//! as `&format!("/users/{}", patterns::uuid())`. Sinatra style paths can use
+//! them by name too, as in `/users/:id{uuid}`.
+
+/// A UUID in its hyphenated form, in either case.
+pub const UUID: &str =
+    "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}";
+
+/// A whole number without a sign, such as a database id.
+pub const INT: &str = "[0-9]+";
+
+/// Lowercase words of letters and digits joined by single hyphens, such as
+/// `hello-world-2`.
+pub const SLUG: &str = "[a-z0-9]+(?:-[a-z0-9]+)*";
+
+/// A calendar date such as `2024-02-29`. Months and days are checked to be in
+/// range but not against each other.
+pub const DATE: &str = "[0-9]{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])";
+
+/// A group capturing a `UUID`.
+pub fn uuid() -> String {
+    group(UUID)
+}
+
+/// A group capturing an `INT`.
+pub fn int() -> String {
+    group(INT)
+}
+
+/// A group capturing a `SLUG`.
+pub fn slug() -> String {
+    group(SLUG)
+}
+
+/// A group capturing a `DATE`.
+pub fn date() -> String {
+    group(DATE)
+}
+
+fn group(fragment: &str) -> String {
+    format!("({})", fragment)
+}
+
+// The fragment called `name` in Sinatra style paths.
+pub(crate) fn named(name: &str) -> Option<&'static str> {
+    match name {
+        "uuid" => Some(UUID),
+        "int" => Some(INT),
+        "slug" => Some(SLUG),
+        "date" => Some(DATE),
+        _ => None,
+    }
+}
+
+#[test]
+fn matches_common_values() {
+    use regex::Regex;
+
+    let matches = |fragment: &str, value: &str| {
+        Regex::new(&format!(r"\A{}\z", fragment))
+            .unwrap()
+            .is_match(value)
+    };
+    assert!(matches(&uuid(), "67e55044-10b1-426f-9247-bb680e5fe0c8"));
+    assert!(!matches(UUID, "67e55044-10b1-426f-9247-bb680e5fe0c"));
+    assert!(matches(INT, "42") && !matches(INT, "-1"));
+    assert!(matches(SLUG, "hello-world-2"));
+    assert!(!matches(SLUG, "hello--world") && !matches(SLUG, "-hello"));
+    assert!(matches(DATE, "2024-02-29"));
+    assert!(!matches(DATE, "2024-13-01") && !matches(DATE, "2024-01-32"));
+}
@@ -0,0 +1,156 @@
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+use crate::body::RouteBody;
+use crate::json::{object, quoted};
+use crate::{Captures, IntoResponse};
+
+/// An `application/problem+json` error response, as described by RFC 7807.
+///
+/// Handlers and `RouterBuilder::map_error` can return one to describe an
+/// error in a form clients can read. With
+/// `RouterBuilder::problem_details` the router also answers unmatched
+/// requests, disallowed methods and failed handlers with them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    status: StatusCode,
+    kind: Option<String>,
+    title: Option<String>,
+    detail: Option<String>,
+    instance: Option<String>,
+}
+
+impl Problem {
+    /// A problem answered with `status`, titled with its reason phrase.
+    pub fn new(status: StatusCode) -> Problem {
+        Problem {
+            status,
+            kind: None,
+            title: None,
+            detail: None,
+            instance: None,
+        }
+    }
+
+    /// A URI identifying the kind of problem, sent as `type`. Without one
+    /// it is `about:blank`, meaning the status says all there is to say.
+    pub fn kind(mut self, uri: &str) -> Problem {
+        self.kind = Some(uri.to_owned());
+        self
+    }
+
+    /// A short summary of the kind of problem, in place of the reason
+    /// phrase of the status.
+    pub fn title(mut self, title: &str) -> Problem {
+        self.title = Some(title.to_owned());
+        self
+    }
+
+    /// An explanation of this occurrence of the problem.
+    pub fn detail(mut self, detail: &str) -> Problem {
+        self.detail = Some(detail.to_owned());
+        self
+    }
+
+    /// A URI identifying this occurrence of the problem, such as the path
+    /// that was requested.
+    pub fn instance(mut self, uri: &str) -> Problem {
+        self.instance = Some(uri.to_owned());
+        self
+    }
+}
+
+impl<B: RouteBody> IntoResponse<B> for Problem {
+    fn into_response(self) -> Response<B> {
+        let title = self
+            .title
+            .as_deref()
+            .or_else(|| self.status.canonical_reason())
+            .unwrap_or("");
+        let mut fields = vec![
+            (
+                "type",
+                quoted(self.kind.as_deref().unwrap_or("about:blank")),
+            ),
+            ("title", quoted(title)),
+            ("status", self.status.as_u16().to_string()),
+        ];
+        if let Some(detail) = &self.detail {
+            fields.push(("detail", quoted(detail)));
+        }
+        if let Some(instance) = &self.instance {
+            fields.push(("instance", quoted(instance)));
+        }
+        Response::builder()
+            .status(self.status)
+            .header(CONTENT_TYPE, "application/problem+json")
+            .body(Body::from(object(fields.into_iter())).into())
+            .unwrap()
+    }
+}
+
+// The 404 handler when problem details are switched on.
+pub(crate) fn not_found<B: RouteBody>(req: Request<B>, _: Captures) -> Response<B> {
+    Problem::new(StatusCode::NOT_FOUND)
+        .instance(req.uri().path())
+        .into_response()
+}
+
+// The 405 handler when problem details are switched on.
+pub(crate) fn not_allowed<B: RouteBody>(req: Request<B>, allowed: &[Method]) -> Response<B> {
+    let allowed: Vec<&str> = allowed.iter().map(Method::as_str).collect();
+    Problem::new(StatusCode::METHOD_NOT_ALLOWED)
+        .detail(&format!("allowed methods: {}", allowed.join(", ")))
+        .instance(req.uri().path())
+        .into_response()
+}
+
+// The response for a failed handler when problem details are switched on.
+// The error itself is left out since it may reveal internals.
+pub(crate) fn internal_error<B: RouteBody>(req: &Request<()>) -> Response<B> {
+    Problem::new(StatusCode::INTERNAL_SERVER_ERROR)
+        .instance(req.uri().path())
+        .into_response()
+}
+
+#[test]
+fn describes_problems() {
+    use std::io;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.problem_details(true);
+    builder.get(r"/users/(\d+)", |_, c| {
+        if &c[1] == "0" {
+            return Err(io::Error::other("database unavailable"));
+        }
+        Ok(Problem::new(StatusCode::CONFLICT)
+            .kind("https://example.com/problems/locked")
+            .title("Account locked")
+            .detail("too many attempts"))
+    });
+    let router = builder.finalize().unwrap();
+
+    let send = |req| {
+        let response = router.handle(req);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        testing::body(response)
+    };
+    assert_eq!(
+        send(testing::get("/users/1")),
+        r#"{"type":"https://example.com/problems/locked","title":"Account locked","status":409,"detail":"too many attempts"}"#
+    );
+    assert_eq!(
+        send(testing::get("/missing")),
+        r#"{"type":"about:blank","title":"Not Found","status":404,"instance":"/missing"}"#
+    );
+    assert_eq!(
+        send(testing::post("/users/1", "")),
+        r#"{"type":"about:blank","title":"Method Not Allowed","status":405,"detail":"allowed methods: GET, OPTIONS","instance":"/users/1"}"#
+    );
+    assert_eq!(
+        send(testing::get("/users/0")),
+        r#"{"type":"about:blank","title":"Internal Server Error","status":500,"instance":"/users/0"}"#
+    );
+}
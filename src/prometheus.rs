@@ -0,0 +1,296 @@
+use std::fmt::Write;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Response, StatusCode};
+
+use crate::metrics::{Metrics, RouteMetrics, RouterMetrics};
+
+const CLASSES: [&str; 5] = ["1xx", "2xx", "3xx", "4xx", "5xx"];
+
+// The handler installed by `RouterBuilder::metrics_endpoint`.
+pub(crate) fn response(metrics: &Metrics) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(render(&metrics.snapshot()).into())
+        .unwrap()
+}
+
+// Render a snapshot in the Prometheus text exposition format.
+pub(crate) fn render(metrics: &RouterMetrics) -> String {
+    // Writing to a `String` can't fail so the results are ignored throughout.
+    let mut out = String::new();
+    let labels = labels(&metrics.routes);
+
+    header(
+        &mut out,
+        "reroute_requests_total",
+        "counter",
+        "Requests dispatched to each route.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        let _ = writeln!(
+            out,
+            "reroute_requests_total{{{}}} {}",
+            labels, route.requests
+        );
+    }
+
+    header(
+        &mut out,
+        "reroute_responses_total",
+        "counter",
+        "Responses from each route by status class.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        for (class, count) in CLASSES.iter().zip(&route.status_classes) {
+            let _ = writeln!(
+                out,
+                "reroute_responses_total{{{},class=\"{}\"}} {}",
+                labels, class, count
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "reroute_response_bytes_total",
+        "counter",
+        "Response body bytes written by each route.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        let _ = writeln!(
+            out,
+            "reroute_response_bytes_total{{{}}} {}",
+            labels, route.bytes_out
+        );
+    }
+
+    header(
+        &mut out,
+        "reroute_request_duration_seconds",
+        "histogram",
+        "Time spent in each route's handler.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        for (bound, count) in &route.latency.buckets {
+            let _ = writeln!(
+                out,
+                "reroute_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels,
+                bound.as_secs_f64(),
+                count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "reroute_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            labels, route.latency.count
+        );
+        let _ = writeln!(
+            out,
+            "reroute_request_duration_seconds_sum{{{}}} {}",
+            labels,
+            route.latency.sum.as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "reroute_request_duration_seconds_count{{{}}} {}",
+            labels, route.latency.count
+        );
+    }
+
+    header(
+        &mut out,
+        "reroute_slo_compliance_ratio",
+        "gauge",
+        "Fraction of requests meeting each route's objective over its window.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        if let Some(slo) = &route.slo {
+            let _ = writeln!(
+                out,
+                "reroute_slo_compliance_ratio{{{}}} {}",
+                labels, slo.compliance
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "reroute_slo_burn_rate",
+        "gauge",
+        "How fast each route is spending its error budget.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        if let Some(slo) = &route.slo {
+            let _ = writeln!(
+                out,
+                "reroute_slo_burn_rate{{{}}} {}",
+                labels,
+                float(slo.burn_rate)
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "reroute_not_found_total",
+        "counter",
+        "Requests that matched no route.",
+    );
+    let _ = writeln!(out, "reroute_not_found_total {}", metrics.not_found);
+
+    header(
+        &mut out,
+        "reroute_method_not_allowed_total",
+        "counter",
+        "Requests that matched a route but not its method.",
+    );
+    let _ = writeln!(
+        out,
+        "reroute_method_not_allowed_total {}",
+        metrics.method_not_allowed
+    );
+
+    header(
+        &mut out,
+        "reroute_route_in_flight_requests",
+        "gauge",
+        "Requests each route's handler is currently executing.",
+    );
+    for (route, labels) in metrics.routes.iter().zip(&labels) {
+        let _ = writeln!(
+            out,
+            "reroute_route_in_flight_requests{{{}}} {}",
+            labels, route.in_flight
+        );
+    }
+
+    header(
+        &mut out,
+        "reroute_in_flight_requests",
+        "gauge",
+        "Handlers currently executing.",
+    );
+    let _ = writeln!(out, "reroute_in_flight_requests {}", metrics.in_flight);
+
+    out
+}
+
+// Prometheus spells infinity differently from Rust.
+fn float(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// The labels of each route. Routes with the same method, pattern and name,
+// such as ones for different hosts or guards, would otherwise share a series,
+// so those are told apart by their index in registration order.
+fn labels(routes: &[RouteMetrics]) -> Vec<String> {
+    let mut labels: Vec<String> = routes
+        .iter()
+        .map(|route| {
+            let mut labels = format!(
+                "method=\"{}\",pattern=\"{}\"",
+                escape(route.method.as_str()),
+                escape(&route.pattern)
+            );
+            if let Some(name) = &route.name {
+                let _ = write!(labels, ",name=\"{}\"", escape(name));
+            }
+            labels
+        })
+        .collect();
+    let shared: Vec<bool> = labels
+        .iter()
+        .map(|label| labels.iter().filter(|other| *other == label).count() > 1)
+        .collect();
+    for (index, (labels, shared)) in labels.iter_mut().zip(shared).enumerate() {
+        if shared {
+            let _ = write!(labels, ",route=\"{}\"", index);
+        }
+    }
+    labels
+}
+
+// Label values must have backslashes, quotes and newlines escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+#[test]
+fn renders_escaped_route_labels() {
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.metrics_endpoint("/metrics");
+    let router = builder.finalize().unwrap();
+    router.handle(testing::get("/users/7"));
+
+    let text = render(&router.metrics());
+    assert!(text.contains(r#"reroute_requests_total{method="GET",pattern="/users/(\\d+)"} 1"#));
+    assert!(text.contains("reroute_in_flight_requests 0"));
+
+    let response = router.handle(testing::get("/metrics"));
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn named_routes_are_labelled() {
+    use hyper::Method;
+
+    use crate::{RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/users",
+        RouteOptions::new().name("list_users").tag("users"),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let text = render(&router.metrics());
+    assert!(text
+        .contains(r#"reroute_requests_total{method="GET",pattern="/users",name="list_users"} 0"#));
+    assert_eq!(router.metrics().routes[0].tags, ["users"]);
+}
+
+#[test]
+fn routes_sharing_labels_are_told_apart() {
+    use hyper::header::HOST;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/health", |_, _| Response::new(Body::empty()));
+    builder.get_host("api.example.com", r"/users", |_, _| {
+        Response::new(Body::empty())
+    });
+    builder.get_host("admin.example.com", r"/users", |_, _| {
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+    let mut req = testing::get("/users");
+    req.headers_mut()
+        .insert(HOST, "admin.example.com".parse().unwrap());
+    router.handle(req);
+
+    let text = render(&router.metrics());
+    assert!(text.contains(r#"reroute_requests_total{method="GET",pattern="/health"} 0"#));
+    assert!(text.contains(r#"reroute_requests_total{method="GET",pattern="/users",route="1"} 0"#));
+    assert!(text.contains(r#"reroute_requests_total{method="GET",pattern="/users",route="2"} 1"#));
+}
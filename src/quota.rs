@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use hyper::{Body, Request, Response, StatusCode};
+
+const API_KEY: &str = "x-api-key";
+
+type KeyFn<B> = Box<dyn Fn(&Request<B>) -> Option<String> + Send + Sync>;
+type LimitFn = Box<dyn Fn(&str) -> Option<u64> + Send + Sync>;
+
+/// Where quota usage is kept. Windows are numbered from the Unix epoch so a
+/// store shared by several servers counts the same window everywhere.
+///
+/// The default store keeps usage in memory, so it is lost on restart and not
+/// shared. Implement this over a database or cache to persist it.
+pub trait QuotaStore: Send + Sync {
+    /// Count a request by `key` in `window`, returning its usage including
+    /// this request.
+    fn increment(&self, key: &str, window: u64) -> u64;
+
+    /// The usage of `key` in `window`.
+    fn get(&self, key: &str, window: u64) -> u64;
+
+    /// The usage of every key used in `window`.
+    fn usage(&self, window: u64) -> Vec<(String, u64)>;
+}
+
+/// A `QuotaStore` that keeps the current window's usage in memory.
+#[derive(Default)]
+pub struct MemoryQuotaStore {
+    window: Mutex<(u64, HashMap<String, u64>)>,
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn increment(&self, key: &str, window: u64) -> u64 {
+        let mut current = self.window.lock().unwrap();
+        if current.0 != window {
+            *current = (window, HashMap::new());
+        }
+        let used = current.1.entry(key.to_owned()).or_insert(0);
+        *used += 1;
+        *used
+    }
+
+    fn get(&self, key: &str, window: u64) -> u64 {
+        let current = self.window.lock().unwrap();
+        if current.0 == window {
+            current.1.get(key).copied().unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    fn usage(&self, window: u64) -> Vec<(String, u64)> {
+        let current = self.window.lock().unwrap();
+        if current.0 == window {
+            current
+                .1
+                .iter()
+                .map(|(key, used)| (key.clone(), *used))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// A limit on how many requests each API key can make in a billing window,
+/// such as a day or a month, attached to routes with `RouteOptions::quota`.
+///
+/// Requests are counted against the key in their `X-API-Key` header unless
+/// `key` says otherwise, and requests without a key aren't counted. Once a
+/// key's usage passes its limit its requests are answered with
+/// `429 Too Many Requests` and `Retry-After` until the window ends. All
+/// responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+/// `X-RateLimit-Reset` headers.
+///
+/// A quota can be cloned to share it between routes, and given to
+/// `Admin::quota` to serve the usage of each key.
+pub struct Quota<B = Body> {
+    inner: Arc<Inner<B>>,
+}
+
+struct Inner<B> {
+    limit: u64,
+    window: Duration,
+    key: KeyFn<B>,
+    limits: Option<LimitFn>,
+    store: Box<dyn QuotaStore>,
+}
+
+/// The usage of one API key in the current window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub key: String,
+    pub used: u64,
+    pub limit: u64,
+    /// When the window ends and usage starts again from zero.
+    pub resets: SystemTime,
+}
+
+impl QuotaUsage {
+    /// How many more requests the key can make in this window.
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+
+    /// Whether the key has made more requests than its limit allows.
+    pub fn exceeded(&self) -> bool {
+        self.used > self.limit
+    }
+}
+
+impl<B> Quota<B> {
+    /// Allow every key `limit` requests in each `window`. Windows start at
+    /// multiples of their length since the Unix epoch, so a one day window
+    /// resets at midnight UTC.
+    pub fn new(limit: u64, window: Duration) -> Quota<B> {
+        Quota {
+            inner: Arc::new(Inner {
+                limit,
+                window: window.max(Duration::from_secs(1)),
+                key: Box::new(|req| {
+                    let key = req.headers().get(API_KEY)?.to_str().ok()?.trim();
+                    Some(key.to_owned()).filter(|key| !key.is_empty())
+                }),
+                limits: None,
+                store: Box::new(MemoryQuotaStore::default()),
+            }),
+        }
+    }
+
+    /// Find the API key of a request with `key` instead of reading the
+    /// `X-API-Key` header. Requests it returns `None` for aren't counted.
+    pub fn key<F>(self, key: F) -> Quota<B>
+    where
+        F: Fn(&Request<B>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.map(|inner| inner.key = Box::new(key))
+    }
+
+    /// Look up the limit of each key with `limits`, such as from its billing
+    /// plan. Keys it returns `None` for get the default limit.
+    pub fn limits<F>(self, limits: F) -> Quota<B>
+    where
+        F: Fn(&str) -> Option<u64> + Send + Sync + 'static,
+    {
+        self.map(|inner| inner.limits = Some(Box::new(limits)))
+    }
+
+    /// Keep usage in `store` instead of in memory.
+    pub fn store<S>(self, store: S) -> Quota<B>
+    where
+        S: QuotaStore + 'static,
+    {
+        self.map(|inner| inner.store = Box::new(store))
+    }
+
+    /// The usage of `key` in the current window.
+    pub fn usage(&self, key: &str) -> QuotaUsage {
+        let window = self.window();
+        self.describe(key, self.inner.store.get(key, window), window)
+    }
+
+    /// The usage of every key that has been used in the current window.
+    pub fn usages(&self) -> Vec<QuotaUsage> {
+        let window = self.window();
+        let mut usages: Vec<QuotaUsage> = self
+            .inner
+            .store
+            .usage(window)
+            .into_iter()
+            .map(|(key, used)| self.describe(&key, used, window))
+            .collect();
+        usages.sort_by(|a, b| a.key.cmp(&b.key));
+        usages
+    }
+
+    // Settings can only be changed before the quota is shared.
+    fn map<F>(mut self, f: F) -> Quota<B>
+    where
+        F: FnOnce(&mut Inner<B>),
+    {
+        f(Arc::get_mut(&mut self.inner).expect("quota settings changed after cloning"));
+        self
+    }
+
+    // Count `req` against its key, if it has one.
+    pub(crate) fn count(&self, req: &Request<B>) -> Option<QuotaUsage> {
+        let key = (self.inner.key)(req)?;
+        let window = self.window();
+        let used = self.inner.store.increment(&key, window);
+        Some(self.describe(&key, used, window))
+    }
+
+    fn window(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        now.as_secs() / self.inner.window.as_secs()
+    }
+
+    fn describe(&self, key: &str, used: u64, window: u64) -> QuotaUsage {
+        let limit = self
+            .inner
+            .limits
+            .as_ref()
+            .and_then(|limits| limits(key))
+            .unwrap_or(self.inner.limit);
+        QuotaUsage {
+            key: key.to_owned(),
+            used,
+            limit,
+            resets: UNIX_EPOCH + Duration::from_secs(self.inner.window.as_secs() * (window + 1)),
+        }
+    }
+}
+
+impl<B> Clone for Quota<B> {
+    fn clone(&self) -> Quota<B> {
+        Quota {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<B> fmt::Debug for Quota<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Quota")
+            .field("limit", &self.inner.limit)
+            .field("window", &self.inner.window)
+            .finish()
+    }
+}
+
+// The response for a key that has used up its quota.
+pub(crate) fn exceeded(usage: &QuotaUsage) -> Response<Body> {
+    let mut response = Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .body("Too Many Requests".into())
+        .unwrap();
+    let retry_after = usage
+        .resets
+        .duration_since(SystemTime::now())
+        .unwrap_or_default();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs() + 1));
+    response
+}
+
+// Add the `X-RateLimit-*` headers describing `usage` to a response.
+pub(crate) fn apply(usage: &QuotaUsage, headers: &mut HeaderMap) {
+    let reset = usage
+        .resets
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(usage.limit));
+    headers.insert(
+        "x-ratelimit-remaining",
+        HeaderValue::from(usage.remaining()),
+    );
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+}
+
+#[test]
+fn enforces_quotas() {
+    use hyper::Method;
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let quota = Quota::new(2, Duration::from_secs(24 * 60 * 60)).limits(|key| {
+        if key == "premium" {
+            Some(3)
+        } else {
+            None
+        }
+    });
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::GET,
+        r"/search",
+        RouteOptions::new().quota(quota.clone()),
+        |_, _| Response::new(Body::empty()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let send = |key: &str| {
+        let mut req = testing::get("/search");
+        req.headers_mut().insert(API_KEY, key.parse().unwrap());
+        router.handle(req)
+    };
+    let response = send("basic");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-ratelimit-remaining"], "1");
+    assert_eq!(send("basic").status(), StatusCode::OK);
+    let response = send("basic");
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+    assert!(response.headers().contains_key(RETRY_AFTER));
+    for _ in 0..3 {
+        assert_eq!(send("premium").status(), StatusCode::OK);
+    }
+    // Requests without a key aren't counted.
+    assert_eq!(
+        router.handle(testing::get("/search")).status(),
+        StatusCode::OK
+    );
+
+    let usages = quota.usages();
+    assert_eq!(
+        usages
+            .iter()
+            .map(|usage| (usage.key.as_str(), usage.used, usage.limit))
+            .collect::<Vec<_>>(),
+        vec![("basic", 3, 2), ("premium", 3, 3)]
+    );
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyper::body::Bytes;
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode, Uri};
+
+use crate::body::RouteBody;
+use crate::decision::Sampler;
+
+type Sink = Box<dyn Fn(Recording) + Send + Sync>;
+
+/// Captures a sample of the requests to a route and the responses they got,
+/// for replaying later with `testing::replay`.
+///
+/// Attach one to routes with `RouteOptions::record`. A recorder can be
+/// cloned to share its sink and sampling between several routes.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    sink: Sink,
+    sampler: Sampler,
+    max_body: usize,
+}
+
+/// A recorded request and its response. Bodies are only captured up to the
+/// recorder's limit and only as far as they were actually read or written.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Whether the request body was longer than the limit.
+    pub body_truncated: bool,
+    pub status: StatusCode,
+    pub response_headers: HeaderMap,
+    pub response_body: Vec<u8>,
+    /// Whether the response body was longer than the limit.
+    pub response_body_truncated: bool,
+}
+
+impl Recorder {
+    /// Create a recorder that passes every recording to `sink` and captures
+    /// up to 64KiB of each body. By default every request is recorded.
+    pub fn new<F>(sink: F) -> Recorder
+    where
+        F: Fn(Recording) + Send + Sync + 'static,
+    {
+        Recorder {
+            inner: Arc::new(Inner {
+                sink: Box::new(sink),
+                sampler: Sampler::new(1),
+                max_body: 64 * 1024,
+            }),
+        }
+    }
+
+    /// Only record every `every`th request.
+    pub fn sample_every(self, every: u64) -> Recorder {
+        self.map(|inner| inner.sampler = Sampler::new(every))
+    }
+
+    /// Capture at most `bytes` of each request and response body.
+    pub fn max_body(self, bytes: usize) -> Recorder {
+        self.map(|inner| inner.max_body = bytes)
+    }
+
+    // Settings can only be changed before the recorder is shared.
+    fn map<F>(mut self, f: F) -> Recorder
+    where
+        F: FnOnce(&mut Inner),
+    {
+        f(Arc::get_mut(&mut self.inner).expect("recorder settings changed after cloning"));
+        self
+    }
+
+    // Start recording `req` if it is sampled. The returned capture must be
+    // given the response with `finish`.
+    pub(crate) fn start<B: RouteBody>(&self, req: &mut Request<B>) -> Option<Arc<Capture>> {
+        if !self.inner.sampler.sample() {
+            return None;
+        }
+
+        let capture = Arc::new(Capture {
+            recorder: Arc::clone(&self.inner),
+            recording: Mutex::new(Recording {
+                method: req.method().clone(),
+                uri: req.uri().clone(),
+                headers: req.headers().clone(),
+                ..Recording::default()
+            }),
+        });
+        let body = std::mem::replace(req.body_mut(), Body::empty().into());
+        *req.body_mut() = B::from(Body::wrap_stream(Tee {
+            body: body.into_hyper(),
+            capture: Arc::clone(&capture),
+            side: Side::Request,
+        }));
+        Some(capture)
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("max_body", &self.inner.max_body)
+            .finish()
+    }
+}
+
+impl Recording {
+    /// Rebuild the recorded request, with the captured part of its body.
+    pub fn to_request(&self) -> Request<Body> {
+        let mut req = Request::new(Body::from(self.body.clone()));
+        *req.method_mut() = self.method.clone();
+        *req.uri_mut() = self.uri.clone();
+        *req.headers_mut() = self.headers.clone();
+        req
+    }
+}
+
+// A recording in progress. It is shared by the request and response bodies
+// and handed to the sink once both have been dropped.
+pub(crate) struct Capture {
+    recorder: Arc<Inner>,
+    recording: Mutex<Recording>,
+}
+
+impl Capture {
+    pub(crate) fn finish<B: RouteBody>(self: Arc<Capture>, response: Response<B>) -> Response<B> {
+        {
+            let mut recording = self.recording.lock().unwrap();
+            recording.status = response.status();
+            recording.response_headers = response.headers().clone();
+        }
+        response.map(|body| {
+            B::from(Body::wrap_stream(Tee {
+                body: body.into_hyper(),
+                capture: self,
+                side: Side::Response,
+            }))
+        })
+    }
+
+    fn append(&self, side: Side, chunk: &[u8]) {
+        let mut recording = self.recording.lock().unwrap();
+        let (body, truncated) = match side {
+            Side::Request => {
+                let recording = &mut *recording;
+                (&mut recording.body, &mut recording.body_truncated)
+            }
+            Side::Response => {
+                let recording = &mut *recording;
+                (
+                    &mut recording.response_body,
+                    &mut recording.response_body_truncated,
+                )
+            }
+        };
+        let room = self.recorder.max_body.saturating_sub(body.len());
+        if chunk.len() > room {
+            *truncated = true;
+        }
+        body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        let recording = std::mem::take(self.recording.get_mut().unwrap());
+        (self.recorder.sink)(recording);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Request,
+    Response,
+}
+
+// Passes a body through unchanged while copying it into a capture.
+struct Tee {
+    body: Body,
+    capture: Arc<Capture>,
+    side: Side,
+}
+
+impl Stream for Tee {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.body).poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            self.capture.append(self.side, chunk);
+        }
+        poll
+    }
+}
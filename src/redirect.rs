@@ -0,0 +1,72 @@
+use hyper::header::LOCATION;
+use hyper::{Body, Response, StatusCode};
+
+use crate::Captures;
+
+// Respond with a redirect to `target`, replacing `$1` and so on with the
+// corresponding captures.
+pub(crate) fn respond(target: &str, status: StatusCode, captures: &Captures) -> Response<Body> {
+    let mut location = target.to_owned();
+    // Replace the higher numbers first so `$1` doesn't clobber `$10`.
+    for (i, capture) in captures.iter().enumerate().skip(1).rev() {
+        location = location.replace(&format!("${}", i), capture.unwrap_or(""));
+    }
+    Response::builder()
+        .status(status)
+        .header(LOCATION, location)
+        .body(Body::empty())
+        .unwrap()
+}
+
+// Redirect a request for a path with a trailing slash to `path`, the
+// canonical form without it.
+pub(crate) fn canonical(path: &str, query: Option<&str>) -> Response<Body> {
+    let location = match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_owned(),
+    };
+    Response::builder()
+        .status(StatusCode::PERMANENT_REDIRECT)
+        .header(LOCATION, location)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[test]
+fn redirects_with_captures() {
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.redirect(
+        r"/old/(\d+)/(\w+)",
+        "/new/$2/$1",
+        StatusCode::MOVED_PERMANENTLY,
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::post("/old/7/edit", ""));
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[LOCATION], "/new/edit/7");
+}
+
+#[test]
+fn redirects_trailing_slashes() {
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.redirect_trailing_slash(true);
+    builder.get(r"/users/(\d+)", |_, _| Response::new(Body::empty()));
+    builder.get(r"/docs/", |_, _| Response::new(Body::empty()));
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/users/1/?tab=posts"));
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()[LOCATION], "/users/1?tab=posts");
+    for path in &["/users/1", "/docs/"] {
+        assert_eq!(router.handle(testing::get(path)).status(), StatusCode::OK);
+    }
+    assert_eq!(
+        router.handle(testing::get("/docs")).status(),
+        StatusCode::NOT_FOUND
+    );
+}
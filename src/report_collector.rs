@@ -0,0 +1,242 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{CONTENT_TYPE, USER_AGENT};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::Value;
+
+/// A report sent by a browser to an endpoint added with
+/// `RouterBuilder::reports`, such as a Content Security Policy violation or a
+/// Network Error Logging failure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// The type of report, such as `csp-violation` or `network-error`.
+    pub kind: String,
+    /// The URL of the page or request the report is about.
+    pub url: String,
+    pub user_agent: Option<String>,
+    /// The details of the report as a JSON object, whose fields depend on
+    /// its kind.
+    pub body: String,
+}
+
+/// Collects reports sent with the Reporting API (`application/reports+json`)
+/// or by the older CSP `report-uri` directive (`application/csp-report`)
+/// and passes each one to a sink.
+///
+/// Handlers can't wait for a request body, so the reports are read as the
+/// response is sent. That response is a `202 Accepted` with an empty body
+/// rather than a `204 No Content`, whose body servers never send.
+pub struct ReportCollector {
+    sink: Box<dyn Fn(Report) + Send + Sync>,
+    max_size: usize,
+}
+
+impl ReportCollector {
+    /// Pass the reports that are collected to `sink`.
+    pub fn new<F>(sink: F) -> ReportCollector
+    where
+        F: Fn(Report) + Send + Sync + 'static,
+    {
+        ReportCollector {
+            sink: Box::new(sink),
+            max_size: 64 * 1024,
+        }
+    }
+
+    /// The largest payload to accept, 64 KiB by default. Larger payloads
+    /// are refused with `413 Payload Too Large` when they say how large
+    /// they are up front and dropped unread otherwise.
+    pub fn max_size(mut self, bytes: usize) -> ReportCollector {
+        self.max_size = bytes;
+        self
+    }
+}
+
+// The kinds of payload a collector accepts.
+#[derive(Clone, Copy)]
+enum Format {
+    Reports,
+    CspReport,
+}
+
+// Answer a request to a report endpoint.
+pub(crate) fn respond(collector: &Arc<ReportCollector>, req: Request<Body>) -> Response<Body> {
+    let media_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    let format = match media_type.as_deref() {
+        Some("application/reports+json") => Format::Reports,
+        // Some browsers send CSP reports as plain JSON.
+        Some("application/csp-report") | Some("application/json") => Format::CspReport,
+        _ => return status(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    };
+    // Bodies with a `Content-Length` know their size before they're read.
+    if HttpBody::size_hint(req.body()).lower() > collector.max_size as u64 {
+        return status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let mut response = Response::new(Body::wrap_stream(Collect {
+        body: req.into_body(),
+        payload: Vec::new(),
+        collector: Arc::clone(collector),
+        format,
+        user_agent,
+    }));
+    *response.status_mut() = StatusCode::ACCEPTED;
+    response
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+// An empty response body that reads the request body and hands the reports
+// in it to the sink before ending.
+struct Collect {
+    body: Body,
+    payload: Vec<u8>,
+    collector: Arc<ReportCollector>,
+    format: Format,
+    user_agent: Option<String>,
+}
+
+impl Stream for Collect {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.body).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(chunk))) => {
+                    if self.payload.len() + chunk.len() > self.collector.max_size {
+                        return Poll::Ready(None);
+                    }
+                    self.payload.extend_from_slice(&chunk);
+                }
+                // The client has gone, so there is no one to answer.
+                Poll::Ready(Some(Err(_))) => return Poll::Ready(None),
+                Poll::Ready(None) => {
+                    let payload = std::mem::take(&mut self.payload);
+                    let reports = std::str::from_utf8(&payload)
+                        .ok()
+                        .and_then(|payload| parse(self.format, payload, &self.user_agent))
+                        .unwrap_or_default();
+                    for report in reports {
+                        (self.collector.sink)(report);
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+fn parse(format: Format, payload: &str, user_agent: &Option<String>) -> Option<Vec<Report>> {
+    let payload: Value = serde_json::from_str(payload).ok()?;
+    let string = |value: &Value, key| value.get(key)?.as_str().map(str::to_owned);
+    match format {
+        Format::Reports => match payload {
+            Value::Array(reports) => Some(
+                reports
+                    .iter()
+                    .filter_map(|report| {
+                        Some(Report {
+                            kind: string(report, "type")?,
+                            url: string(report, "url")?,
+                            user_agent: string(report, "user_agent").or_else(|| user_agent.clone()),
+                            body: report
+                                .get("body")
+                                .map_or_else(|| "{}".to_owned(), Value::to_string),
+                        })
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        },
+        Format::CspReport => {
+            let body = payload.get("csp-report")?;
+            Some(vec![Report {
+                kind: "csp-violation".to_owned(),
+                url: string(body, "document-uri")?,
+                user_agent: user_agent.clone(),
+                body: body.to_string(),
+            }])
+        }
+    }
+}
+
+#[test]
+fn collects_reports() {
+    use std::sync::Mutex;
+
+    use crate::{testing, RouterBuilder};
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut builder = RouterBuilder::new();
+    {
+        let reports = Arc::clone(&reports);
+        builder.reports(
+            r"/reports",
+            ReportCollector::new(move |report| reports.lock().unwrap().push(report)).max_size(512),
+        );
+    }
+    let router = builder.finalize().unwrap();
+
+    let send = |content_type: &str, payload: &str| {
+        let mut req = testing::post("/reports", payload.to_owned());
+        req.headers_mut()
+            .insert(CONTENT_TYPE, content_type.parse().unwrap());
+        let response = router.handle(req);
+        let status = response.status();
+        testing::body(response);
+        status
+    };
+    let status = send(
+        "application/reports+json",
+        r#"[{"type":"network-error","url":"https://example.com/","user_agent":"Firefox","body":{"type":"tcp.timed_out"}}]"#,
+    );
+    assert_eq!(status, StatusCode::ACCEPTED);
+    send(
+        "application/csp-report",
+        r#"{"csp-report":{"document-uri":"https://example.com/","violated-directive":"script-src"}}"#,
+    );
+    assert_eq!(
+        send("text/plain", "hello"),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    assert_eq!(
+        send("application/csp-report", &"x".repeat(1024)),
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(
+        reports[0],
+        Report {
+            kind: "network-error".to_owned(),
+            url: "https://example.com/".to_owned(),
+            user_agent: Some("Firefox".to_owned()),
+            body: r#"{"type":"tcp.timed_out"}"#.to_owned(),
+        }
+    );
+    assert_eq!(reports[1].kind, "csp-violation");
+    assert_eq!(
+        reports[1].body,
+        r#"{"document-uri":"https://example.com/","violated-directive":"script-src"}"#
+    );
+    assert_eq!(reports.len(), 2);
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use hyper::header::{HeaderMap, HeaderValue};
+
+use crate::json;
+
+/// The headers that tell browsers where to send reports, added to every
+/// response by `RouterBuilder::reporting`.
+///
+/// `Reporting-Endpoints` and the older `Report-To` name the endpoint as a
+/// group, which Content Security Policies can send violations to with a
+/// `report-to` directive. With `nel` a `NEL` header also asks for network
+/// errors to be reported there.
+#[derive(Clone, Debug)]
+pub struct Reporting {
+    endpoint: String,
+    group: String,
+    max_age: Duration,
+    include_subdomains: bool,
+    failure_fraction: Option<f64>,
+}
+
+impl Reporting {
+    /// Send reports to `endpoint`, which must be an absolute `https` URL.
+    pub fn new(endpoint: &str) -> Reporting {
+        Reporting {
+            endpoint: endpoint.to_owned(),
+            group: "default".to_owned(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+            include_subdomains: false,
+            failure_fraction: None,
+        }
+    }
+
+    /// The name of the endpoint group, `default` unless changed.
+    pub fn group(mut self, group: &str) -> Reporting {
+        self.group = group.to_owned();
+        self
+    }
+
+    /// How long browsers remember the endpoint, a day by default.
+    pub fn max_age(mut self, max_age: Duration) -> Reporting {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Apply the policy to subdomains as well.
+    pub fn include_subdomains(mut self) -> Reporting {
+        self.include_subdomains = true;
+        self
+    }
+
+    /// Ask for Network Error Logging, reporting the given fraction of
+    /// failed requests between 0 and 1.
+    pub fn nel(mut self, failure_fraction: f64) -> Reporting {
+        self.failure_fraction = Some(failure_fraction.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Add the headers to `headers`, keeping any already there.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let max_age = self.max_age.as_secs().to_string();
+        let subdomains = if self.include_subdomains {
+            Some("true".to_owned())
+        } else {
+            None
+        };
+        let mut values = vec![
+            (
+                "reporting-endpoints",
+                format!("{}={}", self.group, json::quoted(&self.endpoint)),
+            ),
+            (
+                "report-to",
+                json::object(
+                    vec![
+                        ("group", Some(json::quoted(&self.group))),
+                        ("max_age", Some(max_age.clone())),
+                        (
+                            "endpoints",
+                            Some(json::array(std::iter::once(json::object(std::iter::once(
+                                ("url", json::quoted(&self.endpoint)),
+                            ))))),
+                        ),
+                        ("include_subdomains", subdomains.clone()),
+                    ]
+                    .into_iter()
+                    .filter_map(|(name, value)| Some((name, value?))),
+                ),
+            ),
+        ];
+        if let Some(fraction) = self.failure_fraction {
+            values.push((
+                "nel",
+                json::object(
+                    vec![
+                        ("report_to", Some(json::quoted(&self.group))),
+                        ("max_age", Some(max_age)),
+                        ("include_subdomains", subdomains),
+                        ("failure_fraction", Some(fraction.to_string())),
+                    ]
+                    .into_iter()
+                    .filter_map(|(name, value)| Some((name, value?))),
+                ),
+            ));
+        }
+        for (name, value) in values {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.entry(name).or_insert(value);
+            }
+        }
+    }
+}
+
+#[test]
+fn adds_reporting_headers() {
+    use hyper::{Body, Response};
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/", |_, _| Response::new(Body::empty()));
+    builder.reporting(Reporting::new("https://example.com/reports").nel(0.5));
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/"));
+    assert_eq!(
+        response.headers()["reporting-endpoints"],
+        r#"default="https://example.com/reports""#
+    );
+    assert_eq!(
+        response.headers()["nel"],
+        r#"{"report_to":"default","max_age":86400,"failure_fraction":0.5}"#
+    );
+}
@@ -0,0 +1,151 @@
+use hyper::{Body, Method, Request, Response};
+
+use crate::body::RouteBody;
+use crate::{Captures, RouteHandler, RouterBuilder};
+
+/// The handlers for the conventional routes of a REST resource, given to
+/// `RouterBuilder::resource`.
+///
+/// For a resource at `/articles` they are:
+///
+/// ```text
+/// GET          /articles       index
+/// POST         /articles       create
+/// GET          /articles/{id}  show
+/// PUT, PATCH   /articles/{id}  update
+/// DELETE       /articles/{id}  delete
+/// ```
+///
+/// The id is captured in a group called `id`. Only the handlers that are
+/// given get routes, so requests for the others are answered with
+/// `405 Method Not Allowed`.
+pub struct Resource<B = Body> {
+    index: Option<RouteHandler<B>>,
+    show: Option<RouteHandler<B>>,
+    create: Option<RouteHandler<B>>,
+    update: Option<RouteHandler<B>>,
+    delete: Option<RouteHandler<B>>,
+}
+
+impl<B> Default for Resource<B> {
+    fn default() -> Resource<B> {
+        Resource {
+            index: None,
+            show: None,
+            create: None,
+            update: None,
+            delete: None,
+        }
+    }
+}
+
+impl<B> Resource<B> {
+    /// Create a resource without any handlers.
+    pub fn new() -> Resource<B> {
+        Resource::default()
+    }
+
+    /// Handle listing the collection.
+    pub fn index<H>(mut self, handler: H) -> Resource<B>
+    where
+        H: Fn(Request<B>, Captures) -> Response<B> + Send + Sync + 'static,
+    {
+        self.index = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle fetching one item.
+    pub fn show<H>(mut self, handler: H) -> Resource<B>
+    where
+        H: Fn(Request<B>, Captures) -> Response<B> + Send + Sync + 'static,
+    {
+        self.show = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle adding an item to the collection.
+    pub fn create<H>(mut self, handler: H) -> Resource<B>
+    where
+        H: Fn(Request<B>, Captures) -> Response<B> + Send + Sync + 'static,
+    {
+        self.create = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle changing one item, with either PUT or PATCH.
+    pub fn update<H>(mut self, handler: H) -> Resource<B>
+    where
+        H: Fn(Request<B>, Captures) -> Response<B> + Send + Sync + 'static,
+    {
+        self.update = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle removing one item.
+    pub fn delete<H>(mut self, handler: H) -> Resource<B>
+    where
+        H: Fn(Request<B>, Captures) -> Response<B> + Send + Sync + 'static,
+    {
+        self.delete = Some(Box::new(handler));
+        self
+    }
+}
+
+impl<B: RouteBody> RouterBuilder<B> {
+    /// Add the conventional routes of a REST resource at `path`, such as
+    /// `/articles`, for the handlers in `resource`. The path is matched
+    /// literally.
+    pub fn resource(&mut self, path: &str, resource: Resource<B>) -> &mut RouterBuilder<B> {
+        let collection = regex::escape(path.trim_end_matches('/'));
+        let item = format!("{}/(?P<id>[^/]+)", collection);
+        if let Some(handler) = resource.index {
+            self.route(Method::GET, &collection, handler);
+        }
+        if let Some(handler) = resource.create {
+            self.route(Method::POST, &collection, handler);
+        }
+        if let Some(handler) = resource.show {
+            self.route(Method::GET, &item, handler);
+        }
+        if let Some(handler) = resource.update {
+            self.methods(&[Method::PUT, Method::PATCH], &item, handler);
+        }
+        if let Some(handler) = resource.delete {
+            self.route(Method::DELETE, &item, handler);
+        }
+        self
+    }
+}
+
+#[test]
+fn scaffolds_resources() {
+    use hyper::StatusCode;
+
+    use crate::testing;
+
+    let mut builder = RouterBuilder::new();
+    builder.resource(
+        "/articles/",
+        Resource::new()
+            .index(|_, _| Response::new("index".into()))
+            .show(|_, c| Response::new(c.name("id").unwrap().to_owned().into()))
+            .update(|_, _| Response::new("update".into())),
+    );
+    let router = builder.finalize().unwrap();
+
+    let body = |req: Request<Body>| testing::body(router.handle(req));
+    assert_eq!(body(testing::get("/articles")), "index");
+    assert_eq!(body(testing::get("/articles/7")), "7");
+    assert_eq!(
+        body(testing::request(
+            Method::PATCH,
+            "/articles/7",
+            Body::empty()
+        )),
+        "update"
+    );
+    assert_eq!(
+        router.handle(testing::post("/articles", "")).status(),
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+}
@@ -0,0 +1,129 @@
+use hyper::{Body, Response, StatusCode};
+
+use crate::body::RouteBody;
+use crate::failure::{self, HandlerError};
+
+/// A value a handler can return, which the router turns into its response.
+///
+/// Handlers added with `RouterBuilder::route` and the methods named after
+/// verbs can return any of these, so `|_, _| "ok"` is a handler. Text is
+/// sent with a `200 OK`, a status on its own with an empty body, and a
+/// status paired with another value with that status instead of its own.
+/// Errors are turned into responses like those of `route_fallible`.
+///
+/// `B` is the body type of the router, `hyper::Body` unless it was built for
+/// another `RouteBody`.
+pub trait IntoResponse<B = Body> {
+    /// Turn this value into a response.
+    fn into_response(self) -> Response<B>;
+}
+
+impl<B> IntoResponse<B> for Response<B> {
+    fn into_response(self) -> Response<B> {
+        self
+    }
+}
+
+impl<B: RouteBody> IntoResponse<B> for String {
+    fn into_response(self) -> Response<B> {
+        Response::new(Body::from(self).into())
+    }
+}
+
+impl<B: RouteBody> IntoResponse<B> for &'static str {
+    fn into_response(self) -> Response<B> {
+        Response::new(Body::from(self).into())
+    }
+}
+
+impl<B: RouteBody> IntoResponse<B> for StatusCode {
+    fn into_response(self) -> Response<B> {
+        let mut response = Response::new(Body::empty().into());
+        *response.status_mut() = self;
+        response
+    }
+}
+
+impl<B, T: IntoResponse<B>> IntoResponse<B> for (StatusCode, T) {
+    fn into_response(self) -> Response<B> {
+        let mut response = self.1.into_response();
+        *response.status_mut() = self.0;
+        response
+    }
+}
+
+impl<B, T, E> IntoResponse<B> for Result<T, E>
+where
+    B: RouteBody,
+    T: IntoResponse<B>,
+    E: Into<HandlerError>,
+{
+    fn into_response(self) -> Response<B> {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(error) => failure::respond(error.into()),
+        }
+    }
+}
+
+#[test]
+fn converts_return_values() {
+    use std::io;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/ok", |_, _| "ok");
+    builder.get(r"/name/(\w+)", |_, c| format!("hello {}", &c[1]));
+    builder.delete(r"/ok", |_, _| StatusCode::NO_CONTENT);
+    builder.post(r"/ok", |_, _| (StatusCode::CREATED, "created"));
+    builder.put(r"/ok", |_, _| -> Result<&str, io::Error> {
+        Err(io::Error::other("read only"))
+    });
+    let router = builder.finalize().unwrap();
+
+    let send = |req| {
+        let response = router.handle(req);
+        let status = response.status();
+        let body = testing::body(response);
+        (status, body)
+    };
+    assert_eq!(send(testing::get("/ok")), (StatusCode::OK, "ok".into()));
+    assert_eq!(send(testing::get("/name/rust")).1, "hello rust");
+    assert_eq!(
+        send(testing::request(
+            hyper::Method::DELETE,
+            "/ok",
+            Body::empty()
+        ))
+        .0,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        send(testing::post("/ok", "")),
+        (StatusCode::CREATED, "created".into())
+    );
+    assert_eq!(
+        send(testing::request(hyper::Method::PUT, "/ok", Body::empty())).0,
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
+
+#[test]
+fn fallbacks_convert_return_values() {
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/ok", |_, _| "ok");
+    builder.not_found(|_, _| (StatusCode::NOT_FOUND, "nothing here"));
+    builder.method_not_allowed(|_, _| StatusCode::METHOD_NOT_ALLOWED);
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/missing"));
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = testing::body(response);
+    assert_eq!(body, "nothing here");
+    let response = router.handle(testing::post("/ok", ""));
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()["allow"], "GET, OPTIONS");
+}
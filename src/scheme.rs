@@ -0,0 +1,117 @@
+use hyper::header::FORWARDED;
+use hyper::Request;
+
+const FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Which headers to believe about the scheme a request was sent with, for
+/// routes limited to one with `RouteOptions::scheme`.
+///
+/// Behind a proxy that terminates TLS every request reaches the server over
+/// plain HTTP, and only the proxy's headers say otherwise. Clients can send
+/// those headers too, so only trust the ones your proxy always sets. By
+/// default none are trusted and the scheme comes from the request URI,
+/// falling back to `http`.
+#[derive(Clone, Debug, Default)]
+pub struct SchemePolicy {
+    forwarded_proto: bool,
+    forwarded: bool,
+}
+
+impl SchemePolicy {
+    /// Trust nothing but the request URI.
+    pub fn new() -> SchemePolicy {
+        SchemePolicy::default()
+    }
+
+    /// Trust the `X-Forwarded-Proto` header, using its first value.
+    pub fn trust_forwarded_proto(mut self) -> SchemePolicy {
+        self.forwarded_proto = true;
+        self
+    }
+
+    /// Trust the `proto` of the first element of the standard `Forwarded`
+    /// header. It is checked before `X-Forwarded-Proto` if both are trusted.
+    pub fn trust_forwarded(mut self) -> SchemePolicy {
+        self.forwarded = true;
+        self
+    }
+
+    /// The scheme `req` was sent with, in lower case.
+    pub fn scheme<B>(&self, req: &Request<B>) -> String {
+        let header = |name| req.headers().get(name)?.to_str().ok();
+        let forwarded = header(FORWARDED.as_str())
+            .filter(|_| self.forwarded)
+            .and_then(|value| {
+                value
+                    .split(',')
+                    .next()?
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("proto"))
+                    .map(|(_, proto)| proto.trim().trim_matches('"'))
+            });
+        let forwarded_proto = header(FORWARDED_PROTO)
+            .filter(|_| self.forwarded_proto)
+            .and_then(|value| value.split(',').next())
+            .map(str::trim);
+        forwarded
+            .or(forwarded_proto)
+            .filter(|scheme| !scheme.is_empty())
+            .or_else(|| req.uri().scheme_str())
+            .unwrap_or("http")
+            .to_ascii_lowercase()
+    }
+}
+
+#[test]
+fn routes_by_scheme() {
+    use hyper::header::LOCATION;
+    use hyper::{Body, Method, Response, StatusCode};
+
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.scheme_policy(
+        SchemePolicy::new()
+            .trust_forwarded_proto()
+            .trust_forwarded(),
+    );
+    builder.route_with(
+        Method::GET,
+        r"/account",
+        RouteOptions::new().scheme("https"),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.get(r"/account", |_, _| {
+        Response::builder()
+            .status(StatusCode::PERMANENT_REDIRECT)
+            .header(LOCATION, "https://example.com/account")
+            .body(Body::empty())
+            .unwrap()
+    });
+    let router = builder.finalize().unwrap();
+
+    let send = |name: Option<&'static str>, value: &str| {
+        let mut req = testing::get("/account");
+        if let Some(name) = name {
+            req.headers_mut().insert(name, value.parse().unwrap());
+        }
+        router.handle(req).status()
+    };
+    assert_eq!(send(None, ""), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(send(Some(FORWARDED_PROTO), "HTTPS, http"), StatusCode::OK);
+    assert_eq!(
+        send(Some("forwarded"), "for=192.0.2.1;proto=https"),
+        StatusCode::OK
+    );
+    assert_eq!(
+        send(Some(FORWARDED_PROTO), "http"),
+        StatusCode::PERMANENT_REDIRECT
+    );
+    assert_eq!(
+        router
+            .handle(testing::get("https://example.com/account"))
+            .status(),
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,163 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+use hyper::Method;
+use regex::Regex;
+
+use crate::body::RouteBody;
+use crate::{accepts, anchor, example, path, Error, RouterBuilder};
+
+/// A route that an earlier route takes some or all requests from, because
+/// the first matching route wins. Found by `RouterBuilder::analyze`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shadowing {
+    /// The method of the shadowed route.
+    pub method: Method,
+    /// The pattern of the earlier route, which wins.
+    pub shadowing: String,
+    /// The pattern of the later route, which loses.
+    pub shadowed: String,
+    /// A path both patterns match.
+    pub example: String,
+    /// Whether the earlier route takes every request the later one could
+    /// match, so the later one is never used. This is only known for later
+    /// routes without regular expression syntax, like `/users/new`.
+    pub complete: bool,
+}
+
+impl fmt::Display for Shadowing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let extent = if self.complete { "always" } else { "partly" };
+        write!(
+            f,
+            "{} {} is {} shadowed by {}, such as for {}",
+            self.method, self.shadowed, extent, self.shadowing, self.example
+        )
+    }
+}
+
+impl<B: RouteBody> RouterBuilder<B> {
+    /// Find routes that earlier routes for the same method shadow, such as
+    /// `/users/new` added after `/users/(.*)`, in the order the router would
+    /// try them. Overlaps are found by testing an example path of each route
+    /// against the routes before it, so routes that only overlap on unusual
+    /// paths can be missed.
+    pub fn analyze(&self) -> Result<Vec<Shadowing>, Error> {
+        let mut order: Vec<usize> = (0..self.routes.len()).collect();
+        order.sort_by_key(|&index| Reverse(self.options[index].priority));
+        let patterns = order
+            .iter()
+            .map(|&index| Regex::new(&anchor(&self.pattern(index))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut found = Vec::new();
+        for (position, &later) in order.iter().enumerate() {
+            let pattern = self.pattern(later);
+            let literal = path::literal(&pattern);
+            let example = match literal.clone().or_else(|| example::path(&pattern)) {
+                Some(example) if patterns[position].is_match(&example) => example,
+                _ => continue,
+            };
+            let method = &self.handlers[later].0;
+            let earlier = order[..position]
+                .iter()
+                .zip(&patterns)
+                .find(|&(&earlier, pattern)| {
+                    accepts(&self.handlers[earlier].0, method) && pattern.is_match(&example)
+                });
+            if let Some((&earlier, _)) = earlier {
+                found.push(Shadowing {
+                    method: method.clone(),
+                    shadowing: self.routes[earlier].clone(),
+                    shadowed: self.routes[later].clone(),
+                    example,
+                    complete: literal.is_some() && !self.options[earlier].conditional(),
+                });
+            }
+        }
+        Ok(found)
+    }
+
+    // Report or refuse routes that can never be selected, if asked to.
+    pub(crate) fn check_reachable(&self) -> Result<(), Error> {
+        if self.on_unreachable.is_none() && !self.deny_unreachable {
+            return Ok(());
+        }
+        for shadowing in self.analyze()? {
+            if !shadowing.complete {
+                continue;
+            }
+            if let Some(hook) = &self.on_unreachable {
+                hook(&shadowing);
+            }
+            if self.deny_unreachable {
+                return Err(Error::UnreachableRoute(shadowing));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn finds_shadowed_routes() {
+    use hyper::{Body, Request, Response};
+
+    use crate::{Captures, RouteOptions};
+
+    fn handler(_: Request<Body>, _: Captures) -> Response<Body> {
+        Response::new(Body::empty())
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(.*)", handler);
+    builder.get(r"/users/new", handler);
+    builder.post(r"/users/new", handler);
+    builder.get(r"/users/(\d+)", handler);
+    builder.route_with(
+        Method::GET,
+        r"/users/me",
+        RouteOptions::new().priority(1),
+        handler,
+    );
+
+    let found = builder.analyze().unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(
+        found[0].to_string(),
+        r"GET /users/new is always shadowed by /users/(.*), such as for /users/new"
+    );
+    assert_eq!(found[1].shadowed, r"/users/(\d+)");
+    assert_eq!(found[1].example, "/users/1");
+    assert!(!found[1].complete);
+}
+
+#[test]
+fn reports_unreachable_routes() {
+    use std::sync::{Arc, Mutex};
+
+    use hyper::{Body, Response};
+
+    let build = |deny: bool| {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let reported = Arc::clone(&found);
+        let mut builder = RouterBuilder::new();
+        builder.on_unreachable(move |shadowing| {
+            reported.lock().unwrap().push(shadowing.shadowed.clone())
+        });
+        builder.deny_unreachable(deny);
+        builder.get(r"/a/(.*)", |_, _| Response::new(Body::empty()));
+        builder.get(r"/a/b", |_, _| Response::new(Body::empty()));
+        builder.get(r"/a/(\d+)", |_, _| Response::new(Body::empty()));
+        let result = builder.finalize();
+        let found = found.lock().unwrap().clone();
+        (result, found)
+    };
+
+    let (result, found) = build(false);
+    assert!(result.is_ok());
+    assert_eq!(found, vec!["/a/b"]);
+    match build(true).0 {
+        Err(Error::UnreachableRoute(shadowing)) => assert_eq!(shadowing.shadowing, "/a/(.*)"),
+        _ => panic!("expected an unreachable route"),
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Write;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Method, Response, StatusCode};
+
+use crate::metrics::Metrics;
+use crate::path;
+
+type Enumerator = Box<dyn Fn() -> Vec<String> + Send + Sync>;
+
+/// Which routes to list in the sitemap served by `RouterBuilder::sitemap`.
+///
+/// GET routes with the tag `public` whose patterns have no regular
+/// expression syntax are listed as they are. Parameterized routes are only
+/// listed if they are named and have an enumerator given with `paths`.
+pub struct Sitemap {
+    base: String,
+    tag: String,
+    enumerators: Vec<(String, Enumerator)>,
+}
+
+impl Sitemap {
+    /// List routes under `base`, the site's scheme and host such as
+    /// `https://example.com`.
+    pub fn new(base: &str) -> Sitemap {
+        Sitemap {
+            base: base.trim_end_matches('/').to_owned(),
+            tag: "public".to_owned(),
+            enumerators: Vec::new(),
+        }
+    }
+
+    /// List routes with `tag` instead of `public`.
+    pub fn tag(mut self, tag: &str) -> Sitemap {
+        self.tag = tag.to_owned();
+        self
+    }
+
+    /// List the paths returned by `paths` for the route named `route`, which
+    /// is called each time the sitemap is requested. This is how routes with
+    /// captures, such as one for each article, are included.
+    pub fn paths<F>(mut self, route: &str, paths: F) -> Sitemap
+    where
+        F: Fn() -> Vec<String> + Send + Sync + 'static,
+    {
+        self.enumerators.push((route.to_owned(), Box::new(paths)));
+        self
+    }
+
+    pub(crate) fn render(&self, metrics: &Metrics) -> String {
+        let mut paths = Vec::new();
+        for (method, pattern, metadata) in metrics.routes() {
+            if *method != Method::GET {
+                continue;
+            }
+            let enumerator = self
+                .enumerators
+                .iter()
+                .find(|(route, _)| metadata.name.as_ref() == Some(route));
+            if let Some((_, enumerator)) = enumerator {
+                paths.extend(enumerator());
+            } else if metadata.tags.contains(&self.tag) {
+                paths.extend(path::literal(pattern));
+            }
+        }
+
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        ));
+        for path in paths {
+            let _ = writeln!(
+                out,
+                "  <url><loc>{}{}</loc></url>",
+                escape(&self.base),
+                escape(&path)
+            );
+        }
+        out.push_str("</urlset>\n");
+        out
+    }
+}
+
+// The handler installed by `RouterBuilder::sitemap`.
+pub(crate) fn response(sitemap: &Sitemap, metrics: &Metrics) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/xml")
+        .body(sitemap.render(metrics).into())
+        .unwrap()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[test]
+fn lists_public_routes() {
+    use crate::{testing, RouteOptions, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    let public = RouteOptions::new().tag("public");
+    builder.route_with(Method::GET, r"/about", public.clone(), |_, _| {
+        Response::new(Body::empty())
+    });
+    builder.route_with(Method::GET, r"/q&a\.html", public, |_, _| {
+        Response::new(Body::empty())
+    });
+    builder.get(r"/admin", |_, _| Response::new(Body::empty()));
+    builder.route_with(
+        Method::GET,
+        r"/articles/(\d+)",
+        RouteOptions::new().name("article"),
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.sitemap(
+        Sitemap::new("https://example.com/").paths("article", || vec!["/articles/1".to_owned()]),
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/sitemap.xml"));
+    let body = testing::body(response);
+    assert_eq!(
+        body,
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+            "  <url><loc>https://example.com/about</loc></url>\n",
+            "  <url><loc>https://example.com/q&amp;a.html</loc></url>\n",
+            "  <url><loc>https://example.com/articles/1</loc></url>\n",
+            "</urlset>\n",
+        )
+    );
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hyper::{Method, StatusCode};
+
+// The rolling window is tracked in this many slots, so old requests age out
+// of it in steps of a sixtieth of the window.
+const SLOTS: usize = 60;
+
+/// A service level objective for a route, such as 99% of requests answered
+/// within 200ms. Server errors never count as meeting the objective.
+///
+/// Attach one to a route with `RouteOptions::slo`.
+#[derive(Clone, Debug)]
+pub struct Slo {
+    target: f64,
+    threshold: Duration,
+    window: Duration,
+    alert_burn_rate: f64,
+}
+
+/// How a route is doing against its objective over the rolling window.
+#[derive(Clone, Debug)]
+pub struct SloStatus {
+    /// The fraction of requests that must meet the objective.
+    pub target: f64,
+    pub threshold: Duration,
+    pub window: Duration,
+    /// Requests in the window.
+    pub total: u64,
+    /// Requests in the window that met the objective.
+    pub good: u64,
+    /// The fraction of requests in the window that met the objective, or 1
+    /// when there were none.
+    pub compliance: f64,
+    /// How fast the error budget is being spent. At 1 the budget lasts
+    /// exactly the window while at 10 it is gone in a tenth of it.
+    pub burn_rate: f64,
+}
+
+/// Passed to the hook installed with `RouterBuilder::on_slo_burn` when a
+/// route starts burning its error budget faster than its alert rate.
+#[derive(Debug)]
+pub struct SloAlert<'a> {
+    pub method: &'a Method,
+    pub route: &'a str,
+    pub status: SloStatus,
+}
+
+impl Slo {
+    /// An objective that `target` of requests, between 0 and 1, are handled
+    /// within `threshold`. It is measured over the last hour by default.
+    pub fn new(target: f64, threshold: Duration) -> Slo {
+        Slo {
+            target: target.clamp(0.0, 1.0),
+            threshold,
+            window: Duration::from_secs(60 * 60),
+            alert_burn_rate: 1.0,
+        }
+    }
+
+    /// Measure compliance over the last `window` instead of the last hour.
+    pub fn window(mut self, window: Duration) -> Slo {
+        self.window = window;
+        self
+    }
+
+    /// Alert once the burn rate rises above `rate` rather than above 1.
+    pub fn alert_burn_rate(mut self, rate: f64) -> Slo {
+        self.alert_burn_rate = rate;
+        self
+    }
+}
+
+impl fmt::Display for Slo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "slo {}% within {:?}",
+            self.target * 100.0,
+            self.threshold
+        )
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    // Which slot length sized period since the tracker was created this
+    // slot holds, so stale slots can be told apart.
+    period: u64,
+    good: u64,
+    total: u64,
+}
+
+pub(crate) struct SloTracker {
+    slo: Slo,
+    created: Instant,
+    slots: Mutex<[Slot; SLOTS]>,
+    alerting: AtomicBool,
+}
+
+impl SloTracker {
+    pub(crate) fn new(slo: Slo) -> SloTracker {
+        SloTracker {
+            slo,
+            created: Instant::now(),
+            slots: Mutex::new([Slot::default(); SLOTS]),
+            alerting: AtomicBool::new(false),
+        }
+    }
+
+    // Record a request and return the new status if this request pushed the
+    // burn rate over the alert rate.
+    pub(crate) fn record(&self, status: StatusCode, elapsed: Duration) -> Option<SloStatus> {
+        let good = elapsed <= self.slo.threshold && !status.is_server_error();
+        let period = self.period();
+        let current = {
+            let mut slots = self.slots.lock().unwrap();
+            let slot = &mut slots[period as usize % SLOTS];
+            if slot.period != period {
+                *slot = Slot {
+                    period,
+                    ..Slot::default()
+                };
+            }
+            slot.total += 1;
+            if good {
+                slot.good += 1;
+            }
+            self.summarize(&slots, period)
+        };
+
+        let burning = current.burn_rate > self.slo.alert_burn_rate;
+        let was_burning = self.alerting.swap(burning, Ordering::Relaxed);
+        if burning && !was_burning {
+            Some(current)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn status(&self) -> SloStatus {
+        let slots = self.slots.lock().unwrap();
+        self.summarize(&slots, self.period())
+    }
+
+    fn period(&self) -> u64 {
+        let slot_length = (self.slo.window / SLOTS as u32).max(Duration::from_millis(1));
+        (self.created.elapsed().as_nanos() / slot_length.as_nanos()) as u64
+    }
+
+    fn summarize(&self, slots: &[Slot; SLOTS], period: u64) -> SloStatus {
+        let oldest = period.saturating_sub(SLOTS as u64 - 1);
+        let (good, total) = slots
+            .iter()
+            .filter(|slot| slot.period >= oldest && slot.period <= period)
+            .fold((0, 0), |(good, total), slot| {
+                (good + slot.good, total + slot.total)
+            });
+
+        let compliance = if total == 0 {
+            1.0
+        } else {
+            good as f64 / total as f64
+        };
+        let budget = 1.0 - self.slo.target;
+        let burn_rate = if budget > 0.0 {
+            (1.0 - compliance) / budget
+        } else if compliance < 1.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+
+        SloStatus {
+            target: self.slo.target,
+            threshold: self.slo.threshold,
+            window: self.slo.window,
+            total,
+            good,
+            compliance,
+            burn_rate,
+        }
+    }
+}
+
+#[test]
+fn alerts_once_when_budget_burns() {
+    let tracker = SloTracker::new(Slo::new(0.5, Duration::from_millis(100)));
+    let fast = Duration::from_millis(1);
+
+    assert!(tracker.record(StatusCode::OK, fast).is_none());
+    assert!(tracker
+        .record(StatusCode::INTERNAL_SERVER_ERROR, fast)
+        .is_none());
+    let alert = tracker
+        .record(StatusCode::OK, Duration::from_secs(1))
+        .unwrap();
+    assert_eq!((alert.good, alert.total), (1, 3));
+    assert!(tracker.record(StatusCode::BAD_GATEWAY, fast).is_none());
+    assert!((tracker.status().burn_rate - 1.5).abs() < 1e-9);
+}
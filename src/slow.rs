@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use hyper::Method;
+
+/// The placeholder reported in place of captures for routes configured with
+/// `RouteOptions::redact_captures`.
+pub const REDACTED: &str = "[redacted]";
+
+/// A request that took longer than its route's threshold, passed to the hook
+/// installed with `RouterBuilder::on_slow_request`.
+#[derive(Debug)]
+pub struct SlowRequest<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// The pattern of the route that handled the request.
+    pub route: &'a str,
+    pub captures: Vec<&'a str>,
+    pub threshold: Duration,
+    /// Time spent matching the request against the route table.
+    pub routing: Duration,
+    /// Time spent in the route's handler.
+    pub handler: Duration,
+    pub total: Duration,
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log(slow: &SlowRequest) {
+    tracing::warn!(
+        method = %slow.method,
+        path = slow.path,
+        route = slow.route,
+        captures = ?slow.captures,
+        threshold_ms = slow.threshold.as_secs_f64() * 1000.0,
+        routing_ms = slow.routing.as_secs_f64() * 1000.0,
+        handler_ms = slow.handler.as_secs_f64() * 1000.0,
+        total_ms = slow.total.as_secs_f64() * 1000.0,
+        "slow request"
+    );
+}
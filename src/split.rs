@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hyper::{Request, Response};
+
+use crate::{Captures, RouteHandler};
+
+// A large prime to shuffle the order requests are handed out in. Each
+// split steps by the nearest number to it that is coprime with its total
+// weight, so that no point repeats within a cycle.
+const SHUFFLE: u64 = 2_654_435_761;
+
+// Spreads a route's requests between handlers in proportion to their
+// weights, for `RouterBuilder::split` and `RouterBuilder::split_by`.
+pub(crate) struct Split<B> {
+    variants: Vec<(u64, RouteHandler<B>)>,
+    total: u64,
+    step: u64,
+    header: Option<String>,
+    seen: AtomicU64,
+}
+
+impl<B> Split<B> {
+    pub(crate) fn new(variants: Vec<(u32, RouteHandler<B>)>, header: Option<&str>) -> Split<B> {
+        let variants: Vec<(u64, RouteHandler<B>)> = variants
+            .into_iter()
+            .filter(|&(weight, _)| weight > 0)
+            .map(|(weight, handler)| (u64::from(weight), handler))
+            .collect();
+        assert!(
+            !variants.is_empty(),
+            "a split needs a handler with a weight"
+        );
+        let total = variants.iter().map(|&(weight, _)| weight).sum();
+        Split {
+            total,
+            step: step(total),
+            variants,
+            header: header.map(str::to_ascii_lowercase),
+            seen: AtomicU64::default(),
+        }
+    }
+
+    pub(crate) fn handle(&self, req: Request<B>, captures: Captures) -> Response<B> {
+        let key = self
+            .header
+            .as_ref()
+            .and_then(|name| req.headers().get(name.as_str()))
+            .map(|value| fnv(value.as_bytes()));
+        let point = match key {
+            Some(hash) => hash % self.total,
+            // Every cycle of `total` requests visits each point once.
+            None => {
+                let seen = self.seen.fetch_add(1, Ordering::Relaxed) % self.total;
+                (u128::from(seen) * u128::from(self.step) % u128::from(self.total)) as u64
+            }
+        };
+
+        let mut start = 0;
+        for (weight, handler) in &self.variants {
+            start += weight;
+            if point < start {
+                return handler(req, captures);
+            }
+        }
+        unreachable!("the weights add up to the total")
+    }
+}
+
+// The first number from `SHUFFLE` on, modulo `total`, that is coprime with
+// `total`, so that multiplying by it permutes the points of a cycle.
+fn step(total: u64) -> u64 {
+    let mut step = SHUFFLE % total;
+    while gcd(step, total) != 1 {
+        step += 1;
+    }
+    step
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let rest = a % b;
+        a = b;
+        b = rest;
+    }
+    a
+}
+
+// The 64 bit FNV-1a hash, which unlike the standard library's hasher is the
+// same on every server and release.
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[test]
+fn splits_traffic() {
+    use hyper::{Body, Method, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    fn handler(status: u16) -> RouteHandler {
+        Box::new(move |_, _| {
+            Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap()
+        })
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.split(
+        Method::GET,
+        r"/home",
+        vec![(90, handler(200)), (10, handler(202))],
+    );
+    builder.split_by(
+        Method::GET,
+        r"/search",
+        "X-User-Id",
+        vec![(50, handler(200)), (50, handler(202))],
+    );
+    let router = builder.finalize().unwrap();
+
+    let canaries = (0..100)
+        .filter(|_| router.handle(testing::get("/home")).status() == StatusCode::ACCEPTED)
+        .count();
+    assert_eq!(canaries, 10);
+
+    let send = |user: &str| {
+        let mut req = testing::get("/search");
+        req.headers_mut().insert("x-user-id", user.parse().unwrap());
+        router.handle(req).status()
+    };
+    for user in &["1", "2", "3", "4"] {
+        let status = send(user);
+        assert!((0..5).all(|_| send(user) == status));
+    }
+}
+
+#[test]
+fn steps_through_every_point() {
+    for &total in &[1, 2, 100, SHUFFLE, 2 * SHUFFLE, 3 * SHUFFLE + 3] {
+        assert_eq!(gcd(step(total), total), 1, "{}", total);
+    }
+    // Stepping by `SHUFFLE` itself would only ever visit two points.
+    assert_eq!(step(2 * SHUFFLE), SHUFFLE + 2);
+
+    let mut points: Vec<u64> = (0..360).map(|seen| seen * step(360) % 360).collect();
+    points.sort_unstable();
+    assert!(points.iter().copied().eq(0..360));
+}
@@ -0,0 +1,141 @@
+//! Responses whose bodies are sent as they are produced.
+//!
+//! The router hands back a response as soon as its handler returns and never
+//! collects the body, so a handler can respond with a stream that is still
+//! producing chunks, such as a long download or a feed of events. Each chunk
+//! is sent as it becomes ready and the response ends with the stream.
+//!
+//! ```ignore
+//! builder.get(r"/ticks", |_, _| {
+//!     let ticks = tokio::time::interval(Duration::from_secs(1))
+//!         .map(|_| Event::new("tick"));
+//!     reroute::stream::events(ticks)
+//! });
+//! ```
+
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyper::body::Bytes;
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, Response};
+
+/// A response sending each chunk of `chunks` as it is produced. An error
+/// ends the response early, which the client sees as a broken connection.
+pub fn chunked<S, O, E>(chunks: S) -> Response<Body>
+where
+    S: Stream<Item = Result<O, E>> + Send + 'static,
+    O: 'static,
+    E: Into<Box<dyn StdError + Send + Sync>> + 'static,
+    Bytes: From<O>,
+{
+    Response::new(Body::wrap_stream(chunks))
+}
+
+/// A `text/event-stream` response sending each of `events` as it happens,
+/// for browsers to read with `EventSource`.
+pub fn events<S>(events: S) -> Response<Body>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(Events(Box::pin(events))))
+        .unwrap()
+}
+
+/// A server-sent event, for `events`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    data: String,
+    name: Option<String>,
+    id: Option<String>,
+}
+
+impl Event {
+    /// An event carrying `data`, which may span several lines.
+    pub fn new(data: &str) -> Event {
+        Event {
+            data: data.to_owned(),
+            ..Event::default()
+        }
+    }
+
+    /// Name the event so clients can listen for it by name. Unnamed events
+    /// are `message` events.
+    pub fn name(mut self, name: &str) -> Event {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Set the id a reconnecting client sends back in `Last-Event-ID`.
+    pub fn id(mut self, id: &str) -> Event {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    fn encode(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            writeln!(out, "event: {}", name).unwrap();
+        }
+        if let Some(id) = &self.id {
+            writeln!(out, "id: {}", id).unwrap();
+        }
+        for line in self.data.split('\n') {
+            writeln!(out, "data: {}", line).unwrap();
+        }
+        out.push('\n');
+        out
+    }
+}
+
+struct Events<S>(Pin<Box<S>>);
+
+impl<S: Stream<Item = Event>> Stream for Events<S> {
+    type Item = Result<Bytes, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.0
+            .as_mut()
+            .poll_next(cx)
+            .map(|event| event.map(|event| Ok(event.encode().into())))
+    }
+}
+
+#[test]
+fn streams_events() {
+    use futures_util::stream;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/events", |_, _| {
+        events(stream::iter(vec![
+            Event::new("hello"),
+            Event::new("two\nlines").name("update").id("2"),
+        ]))
+    });
+    builder.get(r"/download", |_, _| {
+        let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("ab"), Ok("cd")];
+        chunked(stream::iter(chunks))
+    });
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(testing::get("/events"));
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+    let body = testing::body(response);
+    assert_eq!(
+        body,
+        "data: hello\n\nevent: update\nid: 2\ndata: two\ndata: lines\n\n"
+    );
+
+    let response = router.handle(testing::get("/download"));
+    let body = testing::body(response);
+    assert_eq!(body, "abcd");
+}
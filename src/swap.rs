@@ -0,0 +1,79 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use hyper::{Body, Request, Response};
+
+use crate::Router;
+
+/// A router whose route table can be replaced while a server is using it,
+/// such as after reloading a route config.
+///
+/// Requests are handled by whichever router was current when they arrived.
+/// Replacing it doesn't wait for them, and the old router is dropped once
+/// the last of them finishes.
+pub struct SwappableRouter {
+    current: RwLock<Arc<Router>>,
+}
+
+impl SwappableRouter {
+    /// Start out routing with `router`.
+    pub fn new(router: Router) -> SwappableRouter {
+        SwappableRouter {
+            current: RwLock::new(Arc::new(router)),
+        }
+    }
+
+    /// Route `req` with the current router, like `Router::handle`.
+    pub fn handle(&self, req: Request<Body>) -> Response<Body> {
+        self.load().handle(req)
+    }
+
+    /// Replace the current router with `router` for every later request,
+    /// returning the one it replaced.
+    pub fn swap(&self, router: Router) -> Arc<Router> {
+        let mut current = self.current.write().unwrap();
+        std::mem::replace(&mut *current, Arc::new(router))
+    }
+
+    /// The current router, such as to read its metrics.
+    pub fn load(&self) -> Arc<Router> {
+        let current = self.current.read().unwrap();
+        Arc::clone(&current)
+    }
+}
+
+impl From<Router> for SwappableRouter {
+    fn from(router: Router) -> SwappableRouter {
+        SwappableRouter::new(router)
+    }
+}
+
+impl fmt::Debug for SwappableRouter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SwappableRouter")
+            .field(&self.load())
+            .finish()
+    }
+}
+
+#[test]
+fn swaps_routers() {
+    use hyper::StatusCode;
+
+    use crate::{testing, RouterBuilder};
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/old", |_, _| Response::new(Body::empty()));
+    let router = SwappableRouter::new(builder.finalize().unwrap());
+    assert_eq!(router.handle(testing::get("/old")).status(), StatusCode::OK);
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/new", |_, _| Response::new(Body::empty()));
+    let old = router.swap(builder.finalize().unwrap());
+    assert_eq!(
+        router.handle(testing::get("/old")).status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(router.handle(testing::get("/new")).status(), StatusCode::OK);
+    assert_eq!(old.handle(testing::get("/old")).status(), StatusCode::OK);
+}
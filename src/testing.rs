@@ -0,0 +1,121 @@
+//! Helpers for calling handlers directly from unit tests.
+//!
+//! A handler is just a function of a request and its captures, so it can be
+//! exercised without a server or even a `Router`. The functions here build
+//! the same inputs that a `Router` would hand to it.
+
+use hyper::body::Bytes;
+use hyper::{Body, Method, Request, Response};
+
+use crate::{Captures, Recording, Router};
+
+/// Build the captures a handler would receive for a match. As with the router,
+/// the first group should be the whole matched path followed by each capture
+/// group in order.
+pub fn captures<'a>(groups: &[&'a str]) -> Captures<'a> {
+    groups.iter().copied().collect()
+}
+
+/// Build captures like `captures`, giving each group after the whole path a
+/// name if it has one in the pattern.
+pub fn named_captures<'a>(path: &'a str, groups: &[(Option<&'a str>, &'a str)]) -> Captures<'a> {
+    let mut captures = Captures::default();
+    captures.push(None, path);
+    for &(name, value) in groups {
+        captures.push(name, value);
+    }
+    captures
+}
+
+/// Build a request with the given method, URI and body.
+///
+/// This panics if `uri` is not a valid URI since it is only meant for tests.
+pub fn request<B>(method: Method, uri: &str, body: B) -> Request<Body>
+where
+    B: Into<Body>,
+{
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(body.into())
+        .expect("invalid test request")
+}
+
+/// Build a GET request for `uri` with an empty body.
+pub fn get(uri: &str) -> Request<Body> {
+    request(Method::GET, uri, Body::empty())
+}
+
+/// Build a POST request for `uri` with the given body.
+pub fn post<B>(uri: &str, body: B) -> Request<Body>
+where
+    B: Into<Body>,
+{
+    request(Method::POST, uri, body)
+}
+
+/// Send a recorded request through `router` again, for checking that a
+/// route still responds the way it did when the recording was made.
+pub fn replay(router: &Router, recording: &Recording) -> Response<Body> {
+    router.handle(recording.to_request())
+}
+
+/// Read the whole body of `response`, such as one returned by
+/// `Router::handle`, on a runtime of its own.
+///
+/// This panics if the body fails since it is only meant for tests.
+pub fn body(response: Response<Body>) -> Bytes {
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_time()
+        .build()
+        .expect("failed to start test runtime");
+    runtime
+        .block_on(hyper::body::to_bytes(response.into_body()))
+        .expect("failed to read test response body")
+}
+
+#[test]
+fn handler_with_captures() {
+    fn echo_id(req: Request<Body>, c: Captures) -> Response<Body> {
+        assert_eq!(req.method(), Method::GET);
+        Response::new(c.name("id").unwrap_or(&c[1]).to_string().into())
+    }
+
+    let response = echo_id(get("/users/42"), captures(&["/users/42", "42"]));
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captures = named_captures("/users/42", &[(Some("id"), "42")]);
+    assert_eq!(captures.name("id"), Some("42"));
+}
+
+#[test]
+fn record_and_replay() {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Recorder, RouteOptions, RouterBuilder};
+
+    let recordings = Arc::new(Mutex::new(Vec::new()));
+    let recorder = {
+        let recordings = Arc::clone(&recordings);
+        Recorder::new(move |recording| recordings.lock().unwrap().push(recording)).max_body(4)
+    };
+    let mut builder = RouterBuilder::new();
+    builder.route_with(
+        Method::POST,
+        r"/echo",
+        RouteOptions::new().record(recorder),
+        |req, _| Response::new(req.into_body()),
+    );
+    let router = builder.finalize().unwrap();
+
+    let response = router.handle(post("/echo", "hello"));
+    assert_eq!(body(response), "hello");
+
+    let recording = recordings.lock().unwrap().pop().unwrap();
+    assert_eq!(recording.body, b"hell");
+    assert!(recording.body_truncated);
+    assert_eq!(recording.response_body, b"hell");
+
+    let replayed = replay(&router, &recording);
+    assert_eq!(body(replayed), "hell");
+}
@@ -0,0 +1,154 @@
+use std::time::Instant;
+
+use hyper::{Request, Response};
+use tracing::field;
+
+// Run `f` inside a span for the request so that events logged by the handler
+// are tied to it. The status and latency are recorded once it returns.
+pub(crate) fn instrument<B, F, T>(req: Request<B>, f: F) -> (T, Response<B>)
+where
+    F: FnOnce(Request<B>) -> (T, Response<B>),
+{
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        route = field::Empty,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    );
+
+    let start = Instant::now();
+    let (routed, response) = span.in_scope(|| f(req));
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", start.elapsed().as_secs_f64() * 1000.0);
+
+    (routed, response)
+}
+
+// Record the pattern of the matched route on the current request span.
+pub(crate) fn record_route(route: &str) {
+    tracing::Span::current().record("route", route);
+}
+
+#[test]
+fn records_request_spans() {
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use hyper::StatusCode;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+    use tracing_core::span::Current;
+
+    use crate::{testing, RouterBuilder};
+
+    type Fields = BTreeMap<String, String>;
+    type Spans = Vec<(&'static Metadata<'static>, Fields)>;
+    type Events = Vec<(Option<u64>, Fields)>;
+
+    struct Collect<'a>(&'a mut Fields);
+
+    impl Visit for Collect<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_owned(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+    }
+
+    // Keeps the fields of every span and which span each event was in.
+    #[derive(Clone, Default)]
+    struct Capture {
+        spans: Arc<Mutex<Spans>>,
+        events: Arc<Mutex<Events>>,
+        entered: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &Attributes) -> Id {
+            let mut fields = Fields::new();
+            attributes.record(&mut Collect(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attributes.metadata(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record) {
+            let mut spans = self.spans.lock().unwrap();
+            let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut Collect(fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields::new();
+            event.record(&mut Collect(&mut fields));
+            let current = self.entered.lock().unwrap().last().copied();
+            self.events.lock().unwrap().push((current, fields));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.entered.lock().unwrap().last() {
+                Some(&id) => Current::new(
+                    Id::from_u64(id),
+                    self.spans.lock().unwrap()[id as usize - 1].0,
+                ),
+                None => Current::none(),
+            }
+        }
+    }
+
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/users/(\d+)", |_, _| {
+        tracing::info!("loading user");
+        StatusCode::OK
+    });
+    let router = builder.finalize().unwrap();
+
+    let capture = Capture::default();
+    tracing::subscriber::with_default(capture.clone(), || {
+        router.handle(testing::get("/users/7"));
+        router.handle(testing::get("/missing"));
+    });
+
+    let spans = capture.spans.lock().unwrap();
+    assert_eq!(spans.len(), 2);
+    let (metadata, fields) = &spans[0];
+    assert_eq!(metadata.name(), "request");
+    assert_eq!(fields["method"], "GET");
+    assert_eq!(fields["path"], "/users/7");
+    assert_eq!(fields["route"], r"/users/(\d+)");
+    assert_eq!(fields["status"], "200");
+    assert!(fields.contains_key("latency_ms"));
+
+    // Fallbacks get a span too, without a route.
+    let (metadata, fields) = &spans[1];
+    assert_eq!(metadata.name(), "request");
+    assert_eq!(fields["path"], "/missing");
+    assert!(!fields.contains_key("route"));
+    assert_eq!(fields["status"], "404");
+
+    let events = capture.events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, Some(1));
+    assert_eq!(events[0].1["message"], "loading user");
+}
@@ -0,0 +1,135 @@
+use hyper::header::{HeaderMap, HeaderValue};
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+
+/// The W3C trace context of a request, parsed from its `traceparent` and
+/// `tracestate` headers.
+///
+/// The router inserts this into the extensions of every request carrying a
+/// valid `traceparent` header, so handlers can read it with
+/// `req.extensions().get::<TraceContext>()` and pass it on to upstream
+/// requests with `inject`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The 32 character lowercase hex trace ID.
+    pub trace_id: String,
+    /// The 16 character lowercase hex ID of the caller's span.
+    pub parent_id: String,
+    pub flags: u8,
+    /// The vendor specific `tracestate` header, passed along untouched.
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Parse the trace context from a set of headers. This returns `None` if
+    /// there is no `traceparent` header or it is malformed, in which case the
+    /// specification says the `tracestate` header must be ignored as well.
+    pub fn from_headers(headers: &HeaderMap) -> Option<TraceContext> {
+        let traceparent = headers.get(TRACEPARENT)?.to_str().ok()?;
+        let mut context = parse(traceparent.trim())?;
+
+        // Multiple tracestate headers are equivalent to one joined with commas.
+        let states: Vec<&str> = headers
+            .get_all(TRACESTATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if !states.is_empty() {
+            context.tracestate = Some(states.join(","));
+        }
+
+        Some(context)
+    }
+
+    /// Whether the caller recorded this trace.
+    pub fn sampled(&self) -> bool {
+        self.flags & 1 == 1
+    }
+
+    /// Write the `traceparent` and `tracestate` headers for this context, so
+    /// that a request made on behalf of this one continues the same trace.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let traceparent = format!("00-{}-{}-{:02x}", self.trace_id, self.parent_id, self.flags);
+        if let Ok(value) = HeaderValue::from_str(&traceparent) {
+            headers.insert(TRACEPARENT, value);
+        }
+        match self
+            .tracestate
+            .as_ref()
+            .and_then(|state| HeaderValue::from_str(state).ok())
+        {
+            Some(value) => {
+                headers.insert(TRACESTATE, value);
+            }
+            None => {
+                headers.remove(TRACESTATE);
+            }
+        }
+    }
+}
+
+// Parse `version-trace_id-parent_id-flags`. Versions after 00 may append more
+// fields which are ignored, as the specification asks.
+fn parse(value: &str) -> Option<TraceContext> {
+    let mut parts = value.split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+
+    if !is_hex(version, 2) || version == "ff" || (version == "00" && parts.next().is_some()) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+        return None;
+    }
+    if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+
+    Some(TraceContext {
+        trace_id: trace_id.to_owned(),
+        parent_id: parent_id.to_owned(),
+        flags: u8::from_str_radix(flags, 16).ok()?,
+        tracestate: None,
+    })
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[test]
+fn parses_and_rejects_traceparents() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        TRACEPARENT,
+        HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+    );
+    headers.append(TRACESTATE, HeaderValue::from_static("congo=t61rcWkgMzE"));
+    headers.append(
+        TRACESTATE,
+        HeaderValue::from_static("rojo=00f067aa0ba902b7"),
+    );
+
+    let context = TraceContext::from_headers(&headers).unwrap();
+    assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert!(context.sampled());
+    assert_eq!(
+        context.tracestate.as_deref(),
+        Some("congo=t61rcWkgMzE,rojo=00f067aa0ba902b7")
+    );
+
+    let mut outgoing = HeaderMap::new();
+    context.inject(&mut outgoing);
+    assert_eq!(outgoing[TRACEPARENT], headers[TRACEPARENT]);
+
+    assert!(parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+    assert!(parse("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none());
+    assert!(parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra").is_none());
+    assert!(parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra").is_some());
+}
@@ -0,0 +1,128 @@
+use std::net::IpAddr;
+
+use hyper::{Method, Request, Response};
+use regex::RegexSet;
+
+use crate::access_log::client_ip;
+use crate::deprecation::user_agent;
+use crate::{anchor, Captures, Error, RouteHandler, TrapHook};
+
+/// A request to a decoy route added with `RouterBuilder::trap`, passed to
+/// the hook installed with `RouterBuilder::on_trap`. Nothing legitimate
+/// should request these so the client can be logged or banned.
+#[derive(Debug)]
+pub struct TrapHit<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// The pattern of the trap that was hit.
+    pub trap: &'a str,
+    /// The client address, found the same way as for access logs.
+    pub client_ip: Option<IpAddr>,
+    pub user_agent: Option<&'a str>,
+}
+
+// The decoy routes, which are checked for every method before any real route.
+pub(crate) struct Traps<B> {
+    set: RegexSet,
+    sources: Vec<String>,
+    response: Option<RouteHandler<B>>,
+    hook: Option<TrapHook>,
+}
+
+impl<B> Traps<B> {
+    pub(crate) fn new(
+        sources: Vec<String>,
+        response: Option<RouteHandler<B>>,
+        hook: Option<TrapHook>,
+    ) -> Result<Option<Traps<B>>, Error> {
+        if sources.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Traps {
+            set: RegexSet::new(sources.iter().map(|trap| anchor(trap)))?,
+            sources,
+            response,
+            hook,
+        }))
+    }
+
+    // The index of the first trap matching `path`, if any do.
+    pub(crate) fn matched(&self, path: &str) -> Option<usize> {
+        self.set.matches(path).iter().next()
+    }
+
+    // Answer a request that hit the trap at `index`. Without a trap response
+    // the router's not found handler answers, so traps look like any other
+    // missing page.
+    pub(crate) fn spring(
+        &self,
+        index: usize,
+        req: Request<B>,
+        path: &str,
+        not_found: &RouteHandler<B>,
+    ) -> Response<B> {
+        if let Some(hook) = &self.hook {
+            hook(&TrapHit {
+                method: req.method(),
+                path,
+                trap: &self.sources[index],
+                client_ip: client_ip(&req),
+                user_agent: user_agent(&req),
+            });
+        }
+        self.response.as_ref().unwrap_or(not_found)(req, Captures::default())
+    }
+}
+
+#[test]
+fn traps_report_hits() {
+    use std::sync::{Arc, Mutex};
+
+    use hyper::{Body, StatusCode};
+
+    use crate::{testing, RouterBuilder};
+
+    let hits = Arc::new(Mutex::new(Vec::new()));
+    let mut builder = RouterBuilder::new();
+    builder.get(r"/wp-login", |_, _| Response::new(Body::empty()));
+    builder.trap(r"/wp-(admin|login).*");
+    {
+        let hits = Arc::clone(&hits);
+        builder.on_trap(move |hit| {
+            hits.lock()
+                .unwrap()
+                .push((hit.method.clone(), hit.path.to_owned(), hit.client_ip))
+        });
+    }
+    let router = builder.finalize().unwrap();
+
+    let mut req = testing::post("/wp-login.php", "");
+    req.headers_mut()
+        .insert("x-forwarded-for", "203.0.113.9".parse().unwrap());
+    assert_eq!(router.handle(req).status(), StatusCode::NOT_FOUND);
+    // Traps are checked before routes.
+    assert_eq!(
+        router.handle(testing::get("/wp-login")).status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let hits = hits.lock().unwrap();
+    assert_eq!(
+        hits[0],
+        (
+            Method::POST,
+            "/wp-login.php".to_owned(),
+            "203.0.113.9".parse().ok()
+        )
+    );
+    assert_eq!(hits.len(), 2);
+
+    let mut builder = RouterBuilder::new();
+    builder.trap(r"/\.env");
+    builder.trap_response(|_, _| StatusCode::GONE);
+    let router = builder.finalize().unwrap();
+    assert_eq!(
+        router.handle(testing::get("/.env")).status(),
+        StatusCode::GONE
+    );
+}
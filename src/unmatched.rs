@@ -0,0 +1,88 @@
+use std::cmp::Reverse;
+
+use hyper::Method;
+
+/// Why a request was not handled by any route.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnmatchedKind {
+    /// No pattern matched the path.
+    NotFound,
+    /// A pattern matched the path but none of them for the request method.
+    MethodNotAllowed,
+}
+
+/// A request that fell through to the not found or method not allowed
+/// response, passed to the hook installed with `RouterBuilder::on_unmatched`.
+#[derive(Debug)]
+pub struct Unmatched<'a> {
+    pub kind: UnmatchedKind,
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// The patterns most likely to have been intended. For a method that was
+    /// not allowed these are the patterns that matched the path, otherwise
+    /// they are the patterns sharing the longest literal prefix with it.
+    pub nearest: Vec<&'a str>,
+}
+
+const MAX_NEAREST: usize = 3;
+
+// Find the patterns whose literal prefix shares the most leading characters
+// with `path`. This is only a heuristic but it catches the common typos and
+// missing prefixes that make a route table hard to debug.
+pub(crate) fn nearest<'a>(sources: &'a [String], path: &str) -> Vec<&'a str> {
+    let mut scored: Vec<(usize, &str)> = sources
+        .iter()
+        .map(|source| (common_prefix(literal_prefix(source), path), source.as_str()))
+        // Every path shares the leading slash so require more than that.
+        .filter(|&(score, _)| score > 1)
+        .collect();
+
+    // Sorting is stable so ties keep registration order.
+    scored.sort_by_key(|&(score, _)| Reverse(score));
+    scored
+        .into_iter()
+        .take(MAX_NEAREST)
+        .map(|(_, source)| source)
+        .collect()
+}
+
+// The part of a pattern before its first regex metacharacter.
+fn literal_prefix(pattern: &str) -> &str {
+    let end = pattern
+        .find(|c| "\\.+*?()|[]{}^$".contains(c))
+        .unwrap_or(pattern.len());
+    &pattern[..end]
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log(unmatched: &Unmatched) {
+    let message = match unmatched.kind {
+        UnmatchedKind::NotFound => "no route matched the request",
+        UnmatchedKind::MethodNotAllowed => "a route matched but not the request method",
+    };
+    tracing::warn!(
+        method = %unmatched.method,
+        path = unmatched.path,
+        nearest = ?unmatched.nearest,
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn nearest_prefers_longest_literal_prefix() {
+    let sources = vec![
+        r"/users/(\d+)".to_owned(),
+        r"/users/new".to_owned(),
+        r"/posts/(\d+)".to_owned(),
+    ];
+    assert_eq!(
+        nearest(&sources, "/users/newx"),
+        vec![r"/users/new", r"/users/(\d+)"]
+    );
+    assert!(nearest(&sources, "/other").is_empty());
+}
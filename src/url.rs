@@ -0,0 +1,140 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use regex::Regex;
+
+use crate::Router;
+
+impl<B> Router<B> {
+    /// Build a URL for the route named `name` with `RouteOptions::name` or
+    /// `RouterBuilder::get_named`, filling each capture group with the value
+    /// in `params` for its name, or for its number if it has no name. For a
+    /// route `/users/(?P<id>\d+)` named `user_show`,
+    /// `url_for("user_show", &[("id", "42")])` gives `/users/42`.
+    ///
+    /// Optional groups without a value are left out. Returns `None` if no
+    /// route has the name, a required value is missing or a value doesn't
+    /// match its group. Values are used as they are, so they should be
+    /// percent-encoded first if need be.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Option<String> {
+        let index = self
+            .options
+            .iter()
+            .position(|options| options.metadata.name.as_deref() == Some(name))?;
+        expand(&mut self.sources[index].chars().peekable(), params, &mut 0)
+    }
+}
+
+// Fill in the groups of a pattern, numbering unnamed groups from `group`.
+fn expand(
+    chars: &mut Peekable<Chars>,
+    params: &[(&str, &str)],
+    group: &mut usize,
+) -> Option<String> {
+    let mut url = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => url.extend(chars.next()),
+            '^' | '$' | '?' | '*' | '+' => {}
+            '(' => {
+                let contents = contents(chars);
+                let optional = chars.next_if(|&c| c == '?' || c == '*').is_some();
+                let named = contents
+                    .strip_prefix("?P<")
+                    .or_else(|| contents.strip_prefix("?<"))
+                    .and_then(|rest| rest.split_once('>'));
+                let expanded = match named {
+                    Some((name, pattern)) => {
+                        *group += 1;
+                        value(params, name, pattern)
+                    }
+                    None => match contents.strip_prefix('?') {
+                        // Other groups starting with `?` don't capture anything.
+                        Some(rest) => {
+                            let inner = rest.split_once(':').map_or("", |(_, inner)| inner);
+                            expand(&mut inner.chars().peekable(), params, group)
+                        }
+                        None => {
+                            *group += 1;
+                            let number = group.to_string();
+                            value(params, &number, &contents)
+                                .or_else(|| expand(&mut contents.chars().peekable(), params, group))
+                        }
+                    },
+                };
+                match expanded {
+                    Some(expanded) => url.push_str(&expanded),
+                    None if optional => {}
+                    None => return None,
+                }
+            }
+            c => url.push(c),
+        }
+    }
+    Some(url)
+}
+
+// The value given for the group `name`, if it matches the group's pattern.
+fn value(params: &[(&str, &str)], name: &str, pattern: &str) -> Option<String> {
+    let &(_, value) = params.iter().find(|&&(param, _)| param == name)?;
+    let pattern = Regex::new(&format!(r"\A(?:{})\z", pattern)).ok()?;
+    if pattern.is_match(value) {
+        Some(value.to_owned())
+    } else {
+        None
+    }
+}
+
+// Take the contents of a group whose opening parenthesis was just consumed.
+fn contents(chars: &mut Peekable<Chars>) -> String {
+    let mut contents = String::new();
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                contents.push(c);
+                contents.extend(chars.next());
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        contents.push(c);
+    }
+    contents
+}
+
+#[test]
+fn builds_urls_for_named_routes() {
+    use hyper::{Body, Response};
+
+    use crate::RouterBuilder;
+
+    let mut builder = RouterBuilder::new();
+    builder.get_named(
+        "user_show",
+        r"/users/(?P<id>\d+)(/(?P<tab>\w+))?",
+        |_, _| Response::new(Body::empty()),
+    );
+    builder.get_named("file", r"/files/(\w+)\.txt", |_, _| {
+        Response::new(Body::empty())
+    });
+    let router = builder.finalize().unwrap();
+
+    let url = |name, params: &[(&str, &str)]| router.url_for(name, params);
+    assert_eq!(url("user_show", &[("id", "42")]).unwrap(), "/users/42");
+    assert_eq!(
+        url("user_show", &[("id", "42"), ("tab", "posts")]).unwrap(),
+        "/users/42/posts"
+    );
+    assert_eq!(url("file", &[("1", "notes")]).unwrap(), "/files/notes.txt");
+    assert_eq!(url("user_show", &[("id", "me")]), None);
+    assert_eq!(url("user_show", &[]), None);
+    assert_eq!(url("missing", &[]), None);
+}
@@ -0,0 +1,195 @@
+use hyper::{Body, Method, Request};
+
+use crate::body::RouteBody;
+use crate::{Captures, IntoResponse, RouteOptions, RouterBuilder};
+
+const VERSION_HEADERS: [&str; 2] = ["accept-version", "api-version"];
+
+/// Adds routes for one version of an API, given to the closure passed to
+/// `RouterBuilder::version`.
+pub struct VersionScope<B = Body> {
+    builder: RouterBuilder<B>,
+    match_headers: bool,
+}
+
+impl<B: RouteBody> VersionScope<B> {
+    /// Also match requests without the version prefix whose
+    /// `Accept-Version` or `Api-Version` header names this version. Routes
+    /// added later, such as those of the current version, still get
+    /// requests that name no version.
+    pub fn match_headers(&mut self, match_headers: bool) -> &mut VersionScope<B> {
+        self.match_headers = match_headers;
+        self
+    }
+
+    /// Install a handler for requests to this version with `verb` and a
+    /// path matching `route` after the version prefix.
+    pub fn route<H, R>(&mut self, verb: Method, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.builder.route(verb, route, handler);
+        self
+    }
+
+    /// Install a handler like `route` but with settings specific to this
+    /// route.
+    pub fn route_with<H, R>(
+        &mut self,
+        verb: Method,
+        route: &str,
+        options: RouteOptions<B>,
+        handler: H,
+    ) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.builder.route_with(verb, route, options, handler);
+        self
+    }
+
+    /// Convenience method to install a GET handler for this version.
+    pub fn get<H, R>(&mut self, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::GET, route, handler)
+    }
+
+    /// Convenience method to install a POST handler for this version.
+    pub fn post<H, R>(&mut self, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::POST, route, handler)
+    }
+
+    /// Convenience method to install a PUT handler for this version.
+    pub fn put<H, R>(&mut self, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::PUT, route, handler)
+    }
+
+    /// Convenience method to install a PATCH handler for this version.
+    pub fn patch<H, R>(&mut self, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::PATCH, route, handler)
+    }
+
+    /// Convenience method to install a DELETE handler for this version.
+    pub fn delete<H, R>(&mut self, route: &str, handler: H) -> &mut VersionScope<B>
+    where
+        H: Fn(Request<B>, Captures) -> R + Send + Sync + 'static,
+        R: IntoResponse<B>,
+    {
+        self.route(Method::DELETE, route, handler)
+    }
+}
+
+impl<B: RouteBody> RouterBuilder<B> {
+    /// Add the routes for one version of an API, such as `v2`, under a
+    /// prefix of that name, so `/users` in the scope matches `/v2/users`:
+    ///
+    /// ```ignore
+    /// builder.version("v2", |scope| {
+    ///     scope.get(r"/users", list_users);
+    /// });
+    /// ```
+    pub fn version<F>(&mut self, version: &str, routes: F) -> &mut RouterBuilder<B>
+    where
+        F: FnOnce(&mut VersionScope<B>),
+    {
+        let mut scope = VersionScope {
+            builder: RouterBuilder::default(),
+            match_headers: false,
+        };
+        routes(&mut scope);
+
+        let prefix = format!("/{}", version.trim_matches('/'));
+        if !scope.match_headers {
+            return self.mount(&prefix, scope.builder);
+        }
+
+        // Make the prefix optional, and require the version header of
+        // requests without it.
+        let mut builder = scope.builder;
+        let optional = format!("(?:{})?", regex::escape(&prefix));
+        for route in &mut builder.routes {
+            route.insert_str(0, &optional);
+        }
+        builder.options = builder
+            .options
+            .into_iter()
+            .map(|options| {
+                let version = version.to_owned();
+                let prefix = prefix.clone();
+                options.guard(move |req| requested(req, &prefix, &version))
+            })
+            .collect();
+        self.mount("", builder)
+    }
+}
+
+// Whether `req` is for `version`, by its path or headers.
+fn requested<B>(req: &Request<B>, prefix: &str, version: &str) -> bool {
+    let prefixed = req
+        .uri()
+        .path()
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+    if prefixed {
+        return true;
+    }
+    VERSION_HEADERS.iter().any(|&name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case(version))
+    })
+}
+
+#[test]
+fn scopes_versions() {
+    use hyper::{Response, StatusCode};
+
+    use crate::testing;
+
+    let mut builder = RouterBuilder::new();
+    builder.version("v1", |scope| {
+        scope.get(r"/users", |_, _| Response::new("v1".into()));
+    });
+    builder.version("v2", |scope| {
+        scope.match_headers(true).get(r"/users/(\d+)", |_, c| {
+            Response::new(format!("v2 {}", &c[1]).into())
+        });
+    });
+    builder.get(r"/users/(\d+)", |_, _| Response::new("current".into()));
+    let router = builder.finalize().unwrap();
+
+    let send = |path: &str, version: Option<&str>| {
+        let mut req = testing::get(path);
+        if let Some(version) = version {
+            req.headers_mut()
+                .insert("api-version", version.parse().unwrap());
+        }
+        let response = router.handle(req);
+        let status = response.status();
+        let body = testing::body(response);
+        (status, body)
+    };
+    assert_eq!(send("/v1/users", None).1, "v1");
+    assert_eq!(send("/users", None).0, StatusCode::NOT_FOUND);
+    assert_eq!(send("/v2/users/3", None).1, "v2 3");
+    assert_eq!(send("/users/3", Some("V2")).1, "v2 3");
+    assert_eq!(send("/users/3", None).1, "current");
+}